@@ -0,0 +1,270 @@
+use std::prelude::v1::*;
+
+use super::EntityHandle;
+
+/// A point in a storage's history of changes. Every mutable access advances the storage's tick, so
+/// saving `change_tick()` and later passing it to `iter_added`/`iter_changed`/`iter_removed` finds
+/// what happened in between. Ticks of different storages are unrelated.
+pub type ChangeTick = u64;
+
+/// Records which components of a storage were added, changed or removed, and when. Indexed by
+/// entity index, so it can be used alongside any storage that is parallel to the entity array.
+///
+/// Changes are detected by mutable access, not by comparing values, so a component that was
+/// fetched mutably but not written to is still reported as changed.
+pub struct ChangeTracker {
+    tick: ChangeTick,
+    added_ticks: Vec<ChangeTick>,
+    changed_ticks: Vec<ChangeTick>,
+
+    // Everything that existed when the storage was iterated mutably is treated as changed at this
+    // tick, so that iterating doesn't need to touch every entry in changed_ticks
+    all_changed_tick: ChangeTick,
+
+    removed: Vec<(EntityHandle, ChangeTick)>,
+
+    // Tick when trim_removed() was last called
+    trimmed_tick: ChangeTick,
+}
+
+impl Default for ChangeTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChangeTracker {
+    pub fn new() -> Self {
+        ChangeTracker {
+            tick: 0,
+            added_ticks: Vec::with_capacity(32),
+            changed_ticks: Vec::with_capacity(32),
+            all_changed_tick: 0,
+            removed: vec![],
+            trimmed_tick: 0,
+        }
+    }
+
+    /// The tick of the most recent change
+    pub fn change_tick(&self) -> ChangeTick {
+        self.tick
+    }
+
+    fn next_tick(&mut self) -> ChangeTick {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Call when a component is allocated. A new component counts as both added and changed.
+    pub fn on_allocate(&mut self, entity_index: u32) {
+        let entity_index = entity_index as usize;
+        if self.added_ticks.len() <= entity_index {
+            self.added_ticks.resize(entity_index + 1, 0);
+            self.changed_ticks.resize(entity_index + 1, 0);
+        }
+
+        let tick = self.next_tick();
+        self.added_ticks[entity_index] = tick;
+        self.changed_ticks[entity_index] = tick;
+    }
+
+    /// Call when a component is fetched mutably
+    pub fn on_change(&mut self, entity_index: u32) {
+        let tick = self.next_tick();
+        self.changed_ticks[entity_index as usize] = tick;
+    }
+
+    /// Call when every component is fetched mutably (i.e. by iter_mut)
+    pub fn on_change_all(&mut self) {
+        self.all_changed_tick = self.next_tick();
+    }
+
+    /// Call when a component is freed
+    pub fn on_free(&mut self, entity: &EntityHandle) {
+        let tick = self.next_tick();
+        self.removed.push((entity.clone(), tick));
+    }
+
+    /// Returns true if the component on the given entity was allocated after `since`. Only
+    /// meaningful for entities that currently have a component.
+    pub fn added_since(&self, entity_index: u32, since: ChangeTick) -> bool {
+        self.added_ticks[entity_index as usize] > since
+    }
+
+    /// Returns true if the component on the given entity was allocated or fetched mutably after
+    /// `since`. Only meaningful for entities that currently have a component.
+    pub fn changed_since(&self, entity_index: u32, since: ChangeTick) -> bool {
+        self.changed_ticks[entity_index as usize] > since || self.all_changed_tick > since
+    }
+
+    /// Entities that had their component freed after `since`. The handles may be stale if the
+    /// entity was destroyed.
+    pub fn removed_since(&self, since: ChangeTick) -> impl Iterator<Item = &EntityHandle> {
+        self.removed
+            .iter()
+            .filter(move |(_entity, tick)| *tick > since)
+            .map(|(entity, _tick)| entity)
+    }
+
+    /// Forget removals that happened before the previous call. `EntitySet::update` calls this once a
+    /// frame, so a task that looks for removals every frame never misses one.
+    pub fn trim_removed(&mut self) {
+        let trimmed_tick = self.trimmed_tick;
+        self.removed.retain(|(_entity, tick)| *tick > trimmed_tick);
+        self.trimmed_tick = self.tick;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::component::{
+        join, Component, ComponentRegistry, ComponentStorage, SlabComponentStorage,
+        VecComponentStorage, Without,
+    };
+    use crate::EntitySet;
+
+    struct Position(i32);
+    impl Component for Position {
+        type Storage = VecComponentStorage<Self>;
+    }
+
+    struct Velocity(i32);
+    impl Component for Velocity {
+        type Storage = SlabComponentStorage<Self>;
+    }
+
+    fn indices<'a>(entities: impl Iterator<Item = &'a crate::EntityHandle>) -> Vec<u32> {
+        let mut indices: Vec<_> = entities.map(|entity| entity.index()).collect();
+        indices.sort();
+        indices
+    }
+
+    #[test]
+    fn test_vec_storage_changes() {
+        let mut entity_set = EntitySet::new(ComponentRegistry::new());
+        let mut positions = VecComponentStorage::new();
+        let entities: Vec<_> = (0..3).map(|_| entity_set.allocate()).collect();
+
+        positions.allocate(&entities[0], Position(0)).unwrap();
+        positions.allocate(&entities[1], Position(1)).unwrap();
+        let tick = positions.change_tick();
+
+        positions.allocate(&entities[2], Position(2)).unwrap();
+        positions.get_mut(&entities[0]).unwrap().0 += 10;
+        positions.free(&entities[1]);
+
+        let added: Vec<_> = positions
+            .iter_added(&entity_set, tick)
+            .map(|(e, _)| e)
+            .collect();
+        let changed: Vec<_> = positions
+            .iter_changed(&entity_set, tick)
+            .map(|(e, _)| e)
+            .collect();
+        assert_eq!(indices(added.iter()), vec![2]);
+        assert_eq!(indices(changed.iter()), vec![0, 2]);
+        assert_eq!(indices(positions.iter_removed(tick)), vec![1]);
+
+        // Nothing has happened since the latest tick
+        let tick = positions.change_tick();
+        assert_eq!(positions.iter_changed(&entity_set, tick).count(), 0);
+        assert_eq!(positions.iter_removed(tick).count(), 0);
+
+        // Reading doesn't count as a change, iterating mutably changes everything
+        positions.get(&entities[0]).unwrap();
+        assert_eq!(positions.iter_changed(&entity_set, tick).count(), 0);
+        positions
+            .iter_mut(&entity_set)
+            .for_each(|(_e, position)| position.0 += 1);
+        assert_eq!(positions.iter_changed(&entity_set, tick).count(), 2);
+        assert_eq!(positions.iter_added(&entity_set, tick).count(), 0);
+    }
+
+    #[test]
+    fn test_slab_storage_changes() {
+        let mut entity_set = EntitySet::new(ComponentRegistry::new());
+        let mut velocities = SlabComponentStorage::new();
+        let entities: Vec<_> = (0..3).map(|_| entity_set.allocate()).collect();
+
+        for entity in &entities {
+            velocities.allocate(entity, Velocity(0)).unwrap();
+        }
+        let tick = velocities.change_tick();
+
+        velocities.get_mut(&entities[2]).unwrap().0 = 5;
+        velocities.free(&entities[0]);
+
+        let changed: Vec<_> = velocities
+            .iter_changed(&entity_set, tick)
+            .map(|(e, _)| e)
+            .collect();
+        assert_eq!(indices(changed.iter()), vec![2]);
+        assert_eq!(velocities.iter_added(&entity_set, tick).count(), 0);
+        assert_eq!(indices(velocities.iter_removed(tick)), vec![0]);
+    }
+
+    #[test]
+    fn test_free_all_changes() {
+        let mut entity_set = EntitySet::new(ComponentRegistry::new());
+        let mut positions = VecComponentStorage::new();
+        let mut velocities = SlabComponentStorage::new();
+        let entities: Vec<_> = (0..3).map(|_| entity_set.allocate()).collect();
+
+        positions.allocate(&entities[0], Position(0)).unwrap();
+        positions.allocate(&entities[2], Position(2)).unwrap();
+        velocities.allocate(&entities[1], Velocity(1)).unwrap();
+        let position_tick = positions.change_tick();
+        let velocity_tick = velocities.change_tick();
+
+        positions.free_all(&entity_set);
+        velocities.free_all(&entity_set);
+
+        assert_eq!(positions.count(), 0);
+        assert_eq!(velocities.count(), 0);
+        assert_eq!(indices(positions.iter_removed(position_tick)), vec![0, 2]);
+        assert_eq!(indices(velocities.iter_removed(velocity_tick)), vec![1]);
+        assert!(positions.change_tick() > position_tick);
+        assert!(velocities.change_tick() > velocity_tick);
+    }
+
+    #[test]
+    fn test_join_changes() {
+        let mut entity_set = EntitySet::new(ComponentRegistry::new());
+        let mut positions = VecComponentStorage::new();
+        let mut velocities = SlabComponentStorage::new();
+        let entities: Vec<_> = (0..3).map(|_| entity_set.allocate()).collect();
+
+        for entity in &entities {
+            positions.allocate(entity, Position(0)).unwrap();
+        }
+        velocities.allocate(&entities[1], Velocity(1)).unwrap();
+        let tick = positions.change_tick();
+
+        // Entities skipped by the join aren't fetched mutably, so aren't changed
+        for (_entity, position, ()) in join(&entity_set, (&mut positions, Without(&velocities))) {
+            position.0 += 1;
+        }
+
+        let changed: Vec<_> = positions
+            .iter_changed(&entity_set, tick)
+            .map(|(e, _)| e)
+            .collect();
+        assert_eq!(indices(changed.iter()), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_trim_removed() {
+        let mut entity_set = EntitySet::new(ComponentRegistry::new());
+        let mut positions = VecComponentStorage::new();
+        let entity = entity_set.allocate();
+
+        positions.allocate(&entity, Position(0)).unwrap();
+        positions.free(&entity);
+
+        // Removals are kept until the end of the next frame
+        positions.trim_removed();
+        assert_eq!(positions.iter_removed(0).count(), 1);
+        positions.trim_removed();
+        assert_eq!(positions.iter_removed(0).count(), 0);
+    }
+}
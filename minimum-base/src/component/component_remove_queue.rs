@@ -0,0 +1,66 @@
+use std::prelude::v1::*;
+
+use super::Component;
+use crate::EntityHandle;
+
+use core::any::TypeId;
+use hashbrown::{HashMap, HashSet};
+
+/// Queues components to be removed from entities during the next `EntitySet::update`. The free
+/// handler registered for the component type runs before the component is removed, the same as when
+/// the entity is destroyed.
+pub struct ComponentRemoveQueue {
+    pending: HashMap<TypeId, PendingRemoves>,
+}
+
+// The removals queued for a single component type
+struct PendingRemoves {
+    // Kept for error messages
+    type_name: &'static str,
+
+    // In the order they were queued
+    entity_handles: Vec<EntityHandle>,
+
+    // The same handles as entity_handles, so that queueing one twice can be detected quickly
+    queued: HashSet<EntityHandle>,
+}
+
+impl Default for ComponentRemoveQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ComponentRemoveQueue {
+    pub fn new() -> Self {
+        ComponentRemoveQueue {
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Remove the entity's T during the next `EntitySet::update`. Does nothing if the entity no
+    /// longer has a T by then.
+    pub fn enqueue_remove<T: Component>(&mut self, entity_handle: &EntityHandle) {
+        let pending = self
+            .pending
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| PendingRemoves {
+                type_name: core::any::type_name::<T>(),
+                entity_handles: vec![],
+                queued: HashSet::new(),
+            });
+
+        if pending.queued.insert(entity_handle.clone()) {
+            pending.entity_handles.push(entity_handle.clone());
+        }
+    }
+
+    /// Take all pending removals, grouped by component type. The type name is included for error
+    /// messages.
+    pub(crate) fn take_pending(&mut self) -> HashMap<TypeId, (&'static str, Vec<EntityHandle>)> {
+        std::mem::replace(&mut self.pending, HashMap::new())
+            .into_iter()
+            .map(|(type_id, pending)| (type_id, (pending.type_name, pending.entity_handles)))
+            .collect()
+    }
+}
@@ -0,0 +1,551 @@
+use std::prelude::v1::*;
+
+use super::Component;
+use super::EntityHandle;
+use super::SlabComponentStorage;
+use super::VecComponentStorage;
+
+use crate::entity::EntitySet;
+
+#[cfg(feature = "std")]
+use rayon::prelude::*;
+
+/// Implemented by storages that can be joined. Components are looked up by entity index, which is
+/// only valid while the entity set isn't changed.
+///
+/// # Safety
+///
+/// `join` hands out mutable references from `get_by_index_mut` that outlive the call, one per
+/// entity index. Implementations must return a different component for every index, and never
+/// return a component from `get_by_index_mut` that is also reachable through another index.
+pub unsafe trait JoinStorage: Send + Sync {
+    type Component: Component;
+
+    /// Number of components in the storage. A join is driven by the storage with the fewest.
+    fn component_count(&self) -> usize;
+
+    /// Indices of all entities that have a component, in ascending order
+    fn entity_indices(&self) -> Vec<u32>;
+
+    fn contains_index(&self, entity_index: u32) -> bool;
+
+    fn get_by_index(&self, entity_index: u32) -> Option<&Self::Component>;
+
+    fn get_by_index_mut(&mut self, entity_index: u32) -> Option<&mut Self::Component>;
+}
+
+// Components are stored in a separate slot per entity index
+unsafe impl<T: Component> JoinStorage for SlabComponentStorage<T> {
+    type Component = T;
+
+    fn component_count(&self) -> usize {
+        self.count()
+    }
+
+    fn entity_indices(&self) -> Vec<u32> {
+        self.entity_indices()
+    }
+
+    fn contains_index(&self, entity_index: u32) -> bool {
+        self.get_by_index(entity_index).is_some()
+    }
+
+    fn get_by_index(&self, entity_index: u32) -> Option<&T> {
+        self.get_by_index(entity_index)
+    }
+
+    fn get_by_index_mut(&mut self, entity_index: u32) -> Option<&mut T> {
+        self.get_by_index_mut(entity_index)
+    }
+}
+
+// Components are stored in a separate slot per entity index
+unsafe impl<T: Component> JoinStorage for VecComponentStorage<T> {
+    type Component = T;
+
+    fn component_count(&self) -> usize {
+        self.count()
+    }
+
+    fn entity_indices(&self) -> Vec<u32> {
+        self.entity_indices()
+    }
+
+    fn contains_index(&self, entity_index: u32) -> bool {
+        self.get_by_index(entity_index).is_some()
+    }
+
+    fn get_by_index(&self, entity_index: u32) -> Option<&T> {
+        self.get_by_index(entity_index)
+    }
+
+    fn get_by_index_mut(&mut self, entity_index: u32) -> Option<&mut T> {
+        self.get_by_index_mut(entity_index)
+    }
+}
+
+/// Joins an optional component. Yields `Option<&T>` (or `Option<&mut T>`), and never skips an
+/// entity.
+pub struct Maybe<S>(pub S);
+
+/// Skips entities that have a component in the given storage. Yields `()`.
+pub struct Without<'a, S>(pub &'a S);
+
+/// One element of the tuple passed to `join`
+pub trait JoinParam<'a> {
+    type Item: 'a;
+
+    /// Number of entities this could match, or None if it doesn't limit which entities are
+    /// visited (i.e. `Maybe` and `Without`)
+    fn match_count(&self) -> Option<usize>;
+
+    /// Indices of the entities this could match. Only called on the element driving the join.
+    fn entity_indices(&self) -> Vec<u32>;
+
+    /// Returns true if the entity matches. Every element is checked before any are fetched, so that
+    /// mutable storages only record a change for entities the join yields.
+    fn matches(&self, entity_index: u32) -> bool;
+
+    /// Returns None if the entity doesn't match.
+    ///
+    /// # Safety
+    ///
+    /// Must be called at most once per entity index for the lifetime `'a`. Mutable storages return
+    /// references that outlive the borrow of `self`, so fetching an index twice would alias them.
+    unsafe fn fetch(&mut self, entity_index: u32) -> Option<Self::Item>;
+}
+
+impl<'a, S: JoinStorage> JoinParam<'a> for &'a S {
+    type Item = &'a S::Component;
+
+    fn match_count(&self) -> Option<usize> {
+        Some(self.component_count())
+    }
+
+    fn entity_indices(&self) -> Vec<u32> {
+        JoinStorage::entity_indices(*self)
+    }
+
+    fn matches(&self, entity_index: u32) -> bool {
+        self.contains_index(entity_index)
+    }
+
+    unsafe fn fetch(&mut self, entity_index: u32) -> Option<Self::Item> {
+        JoinStorage::get_by_index(*self, entity_index)
+    }
+}
+
+impl<'a, S: JoinStorage> JoinParam<'a> for &'a mut S {
+    type Item = &'a mut S::Component;
+
+    fn match_count(&self) -> Option<usize> {
+        Some(self.component_count())
+    }
+
+    fn entity_indices(&self) -> Vec<u32> {
+        JoinStorage::entity_indices(&**self)
+    }
+
+    fn matches(&self, entity_index: u32) -> bool {
+        self.contains_index(entity_index)
+    }
+
+    unsafe fn fetch(&mut self, entity_index: u32) -> Option<Self::Item> {
+        let component = self.get_by_index_mut(entity_index)?;
+
+        // The reference outlives this borrow of the storage. The caller fetches each index at most
+        // once, and JoinStorage guarantees each index has its own component, so they never alias.
+        let component: &'a mut S::Component = &mut *(component as *mut _);
+        Some(component)
+    }
+}
+
+impl<'a, S: JoinStorage> JoinParam<'a> for Maybe<&'a S> {
+    type Item = Option<&'a S::Component>;
+
+    fn match_count(&self) -> Option<usize> {
+        None
+    }
+
+    fn entity_indices(&self) -> Vec<u32> {
+        unreachable!()
+    }
+
+    fn matches(&self, _entity_index: u32) -> bool {
+        true
+    }
+
+    unsafe fn fetch(&mut self, entity_index: u32) -> Option<Self::Item> {
+        let storage: &'a S = self.0;
+        Some(storage.get_by_index(entity_index))
+    }
+}
+
+impl<'a, S: JoinStorage> JoinParam<'a> for Maybe<&'a mut S> {
+    type Item = Option<&'a mut S::Component>;
+
+    fn match_count(&self) -> Option<usize> {
+        None
+    }
+
+    fn entity_indices(&self) -> Vec<u32> {
+        unreachable!()
+    }
+
+    fn matches(&self, _entity_index: u32) -> bool {
+        true
+    }
+
+    unsafe fn fetch(&mut self, entity_index: u32) -> Option<Self::Item> {
+        // See the comment in the impl for &mut S
+        let component = self
+            .0
+            .get_by_index_mut(entity_index)
+            .map(|component| &mut *(component as *mut S::Component));
+        Some(component)
+    }
+}
+
+impl<'a, S: JoinStorage> JoinParam<'a> for Without<'a, S> {
+    type Item = ();
+
+    fn match_count(&self) -> Option<usize> {
+        None
+    }
+
+    fn entity_indices(&self) -> Vec<u32> {
+        unreachable!()
+    }
+
+    fn matches(&self, entity_index: u32) -> bool {
+        !self.0.contains_index(entity_index)
+    }
+
+    unsafe fn fetch(&mut self, entity_index: u32) -> Option<Self::Item> {
+        if self.0.contains_index(entity_index) {
+            None
+        } else {
+            Some(())
+        }
+    }
+}
+
+/// Implemented for tuples of `JoinParam`s
+pub trait JoinParams<'a> {
+    type Item;
+
+    /// Indices of the entities to visit, taken from the element that could match the fewest
+    /// entities. If every element is optional, all entities are visited.
+    fn driving_entity_indices(&self, entity_set: &EntitySet) -> Vec<u32>;
+
+    /// Fetches every element for the entity, or returns None if any of them doesn't match.
+    ///
+    /// # Safety
+    ///
+    /// Same as `JoinParam::fetch`, must be called at most once per entity index for the lifetime
+    /// `'a`.
+    unsafe fn fetch(
+        &mut self,
+        entity_handle: EntityHandle,
+        entity_index: u32,
+    ) -> Option<Self::Item>;
+}
+
+macro_rules! impl_join_params {
+    ( $($param:ident),* ) => {
+        impl<'a, $($param: JoinParam<'a>),*> JoinParams<'a> for ( $( $param, )* ) {
+            type Item = (EntityHandle, $( $param::Item, )*);
+
+            #[allow(non_snake_case)]
+            fn driving_entity_indices(&self, entity_set: &EntitySet) -> Vec<u32> {
+                let ( $( $param, )* ) = self;
+
+                let mut smallest: Option<(usize, Vec<u32>)> = None;
+                $(
+                    if let Some(match_count) = $param.match_count() {
+                        let is_smaller = smallest
+                            .as_ref()
+                            .map(|(smallest_count, _)| match_count < *smallest_count)
+                            .unwrap_or(true);
+
+                        if is_smaller {
+                            smallest = Some((match_count, $param.entity_indices()));
+                        }
+                    }
+                )*
+
+                match smallest {
+                    Some((_count, entity_indices)) => entity_indices,
+                    None => entity_set.iter().map(|entity| entity.handle().index()).collect(),
+                }
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn fetch(&mut self, entity_handle: EntityHandle, entity_index: u32) -> Option<Self::Item> {
+                let ( $( $param, )* ) = self;
+                if $( !$param.matches(entity_index) )||* {
+                    return None;
+                }
+
+                Some((entity_handle, $( $param.fetch(entity_index)?, )*))
+            }
+        }
+    }
+}
+
+mod impl_join_params {
+    #![cfg_attr(rustfmt, rustfmt_skip)]
+
+    use super::*;
+
+    impl_join_params!(A);
+    impl_join_params!(A, B);
+    impl_join_params!(A, B, C);
+    impl_join_params!(A, B, C, D);
+    impl_join_params!(A, B, C, D, E);
+    impl_join_params!(A, B, C, D, E, F);
+    impl_join_params!(A, B, C, D, E, F, G);
+    impl_join_params!(A, B, C, D, E, F, G, H);
+}
+
+/// Iterates entities that have all the joined components. Created by `join`.
+pub struct Join<'a, P: JoinParams<'a>> {
+    entity_set: &'a EntitySet,
+    params: P,
+
+    // Strictly ascending, so each entity is fetched at most once
+    entity_indices: std::vec::IntoIter<u32>,
+}
+
+impl<'a, P: JoinParams<'a>> Iterator for Join<'a, P> {
+    type Item = P::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for entity_index in &mut self.entity_indices {
+            let entity_handle = self.entity_set.upgrade_index_to_handle(entity_index);
+
+            // entity_indices has no duplicates and each one is consumed by this loop, so no index is
+            // fetched twice
+            if let Some(item) = unsafe { self.params.fetch(entity_handle, entity_index) } {
+                return Some(item);
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterate the entities that have a component in every storage of the tuple, i.e.
+/// `join(&entity_set, (&*a, &mut *b, Maybe(&*c), Without(&*d)))` yields
+/// `(EntityHandle, &A, &mut B, Option<&C>, ())` for each entity that has an A and a B, but no D.
+/// The storage with the fewest components drives the iteration.
+pub fn join<'a, P: JoinParams<'a>>(entity_set: &'a EntitySet, params: P) -> Join<'a, P> {
+    // Fetching an entity twice would hand out aliasing mutable references. The indices should
+    // already be ascending, but that is up to the JoinParams implementation, so it's enforced here.
+    let mut entity_indices = params.driving_entity_indices(entity_set);
+    entity_indices.sort_unstable();
+    entity_indices.dedup();
+
+    let entity_indices = entity_indices.into_iter();
+    Join {
+        entity_set,
+        params,
+        entity_indices,
+    }
+}
+
+/// Parallel version of `join`, i.e.
+/// `par_join(&entity_set, (&mut *a, &*b)).for_each(|(entity, a, b)| ...)`. Entities are matched on
+/// the calling thread, and the matches are then split into chunks across rayon's thread pool, so
+/// this is worthwhile when the work done per entity is more than the lookup.
+#[cfg(feature = "std")]
+pub fn par_join<'a, P>(
+    entity_set: &'a EntitySet,
+    params: P,
+) -> impl ParallelIterator<Item = P::Item>
+where
+    P: JoinParams<'a>,
+    P::Item: Send,
+{
+    join(entity_set, params).collect::<Vec<_>>().into_par_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::{ComponentRegistry, ComponentStorage};
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {
+        type Storage = VecComponentStorage<Self>;
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+    impl Component for Velocity {
+        type Storage = SlabComponentStorage<Self>;
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Label(&'static str);
+    impl Component for Label {
+        type Storage = SlabComponentStorage<Self>;
+    }
+
+    struct Frozen;
+    impl Component for Frozen {
+        type Storage = VecComponentStorage<Self>;
+    }
+
+    struct TestWorld {
+        entity_set: EntitySet,
+        positions: VecComponentStorage<Position>,
+        velocities: SlabComponentStorage<Velocity>,
+        labels: SlabComponentStorage<Label>,
+        frozen: VecComponentStorage<Frozen>,
+        entities: Vec<EntityHandle>,
+    }
+
+    // Five entities with positions. 1, 2 and 4 have velocities, 2 is frozen and 4 has a label.
+    fn build_test_world() -> TestWorld {
+        let mut entity_set = EntitySet::new(ComponentRegistry::new());
+        let mut positions = VecComponentStorage::new();
+        let mut velocities = SlabComponentStorage::new();
+        let mut labels = SlabComponentStorage::new();
+        let mut frozen = VecComponentStorage::new();
+
+        let entities: Vec<_> = (0..5).map(|_| entity_set.allocate()).collect();
+        for (i, entity) in entities.iter().enumerate() {
+            positions.allocate(entity, Position(i as i32)).unwrap();
+        }
+
+        velocities.allocate(&entities[1], Velocity(10)).unwrap();
+        velocities.allocate(&entities[2], Velocity(20)).unwrap();
+        velocities.allocate(&entities[4], Velocity(40)).unwrap();
+        frozen.allocate(&entities[2], Frozen).unwrap();
+        labels.allocate(&entities[4], Label("four")).unwrap();
+
+        TestWorld {
+            entity_set,
+            positions,
+            velocities,
+            labels,
+            frozen,
+            entities,
+        }
+    }
+
+    #[test]
+    fn test_join() {
+        let mut world = build_test_world();
+
+        for (_entity, position, velocity, label, ()) in join(
+            &world.entity_set,
+            (
+                &mut world.positions,
+                &world.velocities,
+                Maybe(&world.labels),
+                Without(&world.frozen),
+            ),
+        ) {
+            position.0 += velocity.0;
+            if let Some(label) = label {
+                assert_eq!(*label, Label("four"));
+            }
+        }
+
+        let positions: Vec<_> = world
+            .entities
+            .iter()
+            .map(|entity| world.positions.get(entity).unwrap().0)
+            .collect();
+        assert_eq!(positions, vec![0, 11, 2, 3, 44]);
+    }
+
+    #[test]
+    fn test_join_driven_by_smallest() {
+        let world = build_test_world();
+
+        // Labels drive the join, so only one entity is looked up in the other storages
+        let params = (&world.positions, &world.velocities, &world.labels);
+        assert_eq!(
+            params.driving_entity_indices(&world.entity_set),
+            vec![world.entities[4].index()]
+        );
+
+        let joined: Vec<_> = join(&world.entity_set, params)
+            .map(|(entity, position, velocity, label)| (entity, position.0, velocity.0, label.0))
+            .collect();
+        assert_eq!(joined, vec![(world.entities[4].clone(), 4, 40, "four")]);
+    }
+
+    #[test]
+    fn test_join_only_optional() {
+        let world = build_test_world();
+
+        // Nothing limits the entities, so every entity is visited
+        let unlabeled = join(
+            &world.entity_set,
+            (Maybe(&world.velocities), Without(&world.labels)),
+        )
+        .filter(|(_entity, velocity, ())| velocity.is_none())
+        .count();
+        assert_eq!(unlabeled, 2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_par_join() {
+        let mut world = build_test_world();
+
+        par_join(
+            &world.entity_set,
+            (
+                &mut world.positions,
+                &world.velocities,
+                Without(&world.frozen),
+            ),
+        )
+        .for_each(|(_entity, position, velocity, ())| {
+            position.0 += velocity.0;
+        });
+
+        let positions: Vec<_> = world
+            .entities
+            .iter()
+            .map(|entity| world.positions.get(entity).unwrap().0)
+            .collect();
+        assert_eq!(positions, vec![0, 11, 2, 3, 44]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_storage_par_iter() {
+        let mut world = build_test_world();
+
+        world
+            .velocities
+            .par_iter_mut(&world.entity_set)
+            .for_each(|(_entity, velocity)| velocity.0 += 1);
+        world
+            .positions
+            .par_iter_mut(&world.entity_set)
+            .for_each(|(entity, position)| position.0 = entity.index() as i32 * 100);
+
+        let mut velocities: Vec<_> = world
+            .velocities
+            .par_iter(&world.entity_set)
+            .map(|(entity, velocity)| (entity.index(), velocity.0))
+            .collect();
+        velocities.sort();
+        assert_eq!(velocities, vec![(1, 11), (2, 21), (4, 41)]);
+
+        let position_sum: i32 = world
+            .positions
+            .par_iter(&world.entity_set)
+            .map(|(_entity, position)| position.0)
+            .sum();
+        assert_eq!(position_sum, 1000);
+    }
+}
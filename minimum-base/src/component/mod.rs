@@ -33,10 +33,49 @@
 //! }
 //! ```
 //!
+//! The same join can be written with `join`, which is driven by whichever required storage has the
+//! fewest components:
+//! ```
+//! # use minimum_base::component::{join, ComponentRegistry, SlabComponentStorage, VecComponentStorage};
+//! # use minimum_base::{Component, ComponentStorage, EntitySet};
+//! # struct PositionComponent { x: f32 }
+//! # impl Component for PositionComponent { type Storage = VecComponentStorage<Self>; }
+//! # struct VelocityComponent { x: f32 }
+//! # impl Component for VelocityComponent { type Storage = SlabComponentStorage<Self>; }
+//! # struct SpeedMultiplierComponent { multiplier: f32 }
+//! # impl Component for SpeedMultiplierComponent { type Storage = SlabComponentStorage<Self>; }
+//! # let mut game_entities = EntitySet::new(ComponentRegistry::new());
+//! # let mut position_components = VecComponentStorage::<PositionComponent>::new();
+//! # let mut velocity_components = SlabComponentStorage::<VelocityComponent>::new();
+//! # let mut speed_multiplier_components = SlabComponentStorage::<SpeedMultiplierComponent>::new();
+//! # let entity = game_entities.allocate();
+//! # position_components.allocate(&entity, PositionComponent { x: 0.0 }).unwrap();
+//! # velocity_components.allocate(&entity, VelocityComponent { x: 2.0 }).unwrap();
+//! # speed_multiplier_components.allocate(&entity, SpeedMultiplierComponent { multiplier: 3.0 }).unwrap();
+//! # let dt = 1.0;
+//! for (_entity, pos, vel, mul) in join(
+//!     &game_entities,
+//!     (&mut position_components, &velocity_components, &speed_multiplier_components),
+//! ) {
+//!     pos.x += dt * mul.multiplier * vel.x;
+//! }
+//! # assert_eq!(position_components.get(&entity).unwrap().x, 6.0);
+//! ```
+//! Wrap a storage in `Maybe` to yield an `Option` instead of skipping entities without the
+//! component, or in `Without` to skip entities that have it. With the `std` feature, `par_join`
+//! (and `par_iter`/`par_iter_mut` on the storages) split the work across rayon's thread pool.
+//!
+//! The provided storages track which components were added, changed (fetched mutably) or removed.
+//! A task that needs to react to changes saves the storage's `change_tick()` and passes it to
+//! `iter_added`, `iter_changed` or `iter_removed` the next time it runs.
+//!
 //! Users can implement custom storages which expose different ways to query the data. Since the storage
 //! type is not abstracted away, it's easy to create and use custom containers.
 
+mod change_tracker;
 mod component_factory;
+mod component_remove_queue;
+mod join;
 mod registry;
 mod slab_storage;
 mod vec_storage;
@@ -48,10 +87,22 @@ use slab::RawSlabKey;
 use crate::entity;
 use entity::EntityHandle;
 
+pub use change_tracker::ChangeTick;
+pub use change_tracker::ChangeTracker;
 pub use component_factory::ComponentCreateQueueFlushListener;
 pub use component_factory::ComponentPrototypeDyn;
 pub use component_factory::ComponentFactory;
 pub use component_factory::ComponentPrototype;
+pub use component_remove_queue::ComponentRemoveQueue;
+pub use join::join;
+#[cfg(feature = "std")]
+pub use join::par_join;
+pub use join::Join;
+pub use join::JoinParam;
+pub use join::JoinParams;
+pub use join::JoinStorage;
+pub use join::Maybe;
+pub use join::Without;
 pub use registry::ComponentFreeHandler;
 pub use registry::ComponentRegistry;
 pub use slab_storage::SlabComponentStorage;
@@ -98,6 +149,10 @@ where
 
     /// Get a mut ref to the component on the entity
     fn get_mut(&mut self, entity: &EntityHandle) -> Option<&mut T>;
+
+    /// Called by `EntitySet::update` once a frame. Storages that record removed components (see
+    /// `ChangeTracker`) can forget old removals here.
+    fn trim_removed(&mut self) {}
 }
 
 /// Implementation requirements of a component.
@@ -119,3 +174,9 @@ pub type ReadComponentOption<T> = crate::resource::ReadOption<<T as Component>::
 
 /// Gets read/write access to a component storage. Returns none if the storage is not registered.
 pub type WriteComponentOption<T> = crate::resource::WriteOption<<T as Component>::Storage>;
+
+/// A fetched component storage, i.e. for the arguments of a function task
+pub type ReadComponentBorrow<'a, T> = crate::resource::ReadBorrow<'a, <T as Component>::Storage>;
+
+/// A fetched component storage with read/write access, i.e. for the arguments of a function task
+pub type WriteComponentBorrow<'a, T> = crate::resource::WriteBorrow<'a, <T as Component>::Storage>;
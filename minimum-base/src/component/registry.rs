@@ -5,8 +5,12 @@ use super::ComponentStorage;
 use std::marker::PhantomData;
 
 use crate::component::component_factory::ComponentCreateQueueFlushListener;
+use crate::component::ComponentRemoveQueue;
 use crate::{EntityHandle, EntitySet, Resource, ResourceMap};
 
+use core::any::TypeId;
+use hashbrown::HashMap;
+
 /// Handler can be implemented to run custom logic just before entities are destroyed
 pub trait ComponentFreeHandler<T: Component>: Send + Sync {
     fn on_entities_free(
@@ -53,6 +57,11 @@ where
 /// Interface for a registered component type
 trait RegisteredComponentTrait: Send + Sync {
     fn on_entities_free(&self, resource_map: &ResourceMap, entity_handles: &[EntityHandle]);
+
+    /// Like on_entities_free, but only for the entities that have the component
+    fn on_components_remove(&self, resource_map: &ResourceMap, entity_handles: &[EntityHandle]);
+
+    fn trim_removed(&self, resource_map: &ResourceMap);
 }
 
 /// Represents a component that has been registered.
@@ -91,6 +100,25 @@ where
             storage.free_if_exists(entity_handle);
         }
     }
+
+    fn on_components_remove(&self, resource_map: &ResourceMap, entity_handles: &[EntityHandle]) {
+        let entity_handles: Vec<_> = {
+            let storage = resource_map.fetch::<T::Storage>();
+            entity_handles
+                .iter()
+                .filter(|entity_handle| storage.exists(entity_handle))
+                .cloned()
+                .collect()
+        };
+
+        if !entity_handles.is_empty() {
+            self.on_entities_free(resource_map, &entity_handles);
+        }
+    }
+
+    fn trim_removed(&self, resource_map: &ResourceMap) {
+        resource_map.fetch_mut::<T::Storage>().trim_removed();
+    }
 }
 
 /// Used to walk across all component factories and flush pending creates
@@ -133,6 +161,9 @@ where
 pub struct ComponentRegistry {
     registered_components: Vec<Box<dyn RegisteredComponentTrait>>,
     registered_factories: Vec<Box<dyn RegisteredComponentFactoryTrait>>,
+
+    // Index into registered_components for each component type, for removing single components
+    registered_component_indices: HashMap<TypeId, usize>,
 }
 
 impl ComponentRegistry {
@@ -140,11 +171,14 @@ impl ComponentRegistry {
         ComponentRegistry {
             registered_components: vec![],
             registered_factories: vec![],
+            registered_component_indices: HashMap::new(),
         }
     }
 
     /// All components must be registered
     pub fn register_component<T: Component + 'static>(&mut self) {
+        self.registered_component_indices
+            .insert(TypeId::of::<T>(), self.registered_components.len());
         self.registered_components
             .push(Box::new(RegisteredComponent::<T, DefaultFreeHandler>::new()));
     }
@@ -157,6 +191,8 @@ impl ComponentRegistry {
     >(
         &mut self,
     ) {
+        self.registered_component_indices
+            .insert(TypeId::of::<T>(), self.registered_components.len());
         self.registered_components.push(Box::new(
             RegisteredComponent::<T, CustomFreeHandler<T, F>>::new(),
         ));
@@ -182,4 +218,53 @@ impl ComponentRegistry {
             rc.on_entities_free(resource_map, entity_handles);
         }
     }
+
+    /// Immediately remove the T from each of the given entities that has one, running T's free
+    /// handler first. The T storage must not be borrowed. Panics if T was never registered.
+    pub fn remove_components<T: Component>(
+        &self,
+        resource_map: &ResourceMap,
+        entity_handles: &[EntityHandle],
+    ) {
+        self.remove_components_by_type_id(
+            TypeId::of::<T>(),
+            core::any::type_name::<T>(),
+            resource_map,
+            entity_handles,
+        );
+    }
+
+    fn remove_components_by_type_id(
+        &self,
+        type_id: TypeId,
+        type_name: &str,
+        resource_map: &ResourceMap,
+        entity_handles: &[EntityHandle],
+    ) {
+        let index = self
+            .registered_component_indices
+            .get(&type_id)
+            .unwrap_or_else(|| panic!("Component {} was never registered", type_name));
+
+        self.registered_components[*index].on_components_remove(resource_map, entity_handles);
+    }
+
+    /// Handle any removals that were queued in the ComponentRemoveQueue
+    pub fn on_flush_removes(&self, resource_map: &ResourceMap) {
+        let pending = match resource_map.try_fetch_mut::<ComponentRemoveQueue>() {
+            Some(mut remove_queue) => remove_queue.take_pending(),
+            None => return,
+        };
+
+        for (type_id, (type_name, entity_handles)) in pending {
+            self.remove_components_by_type_id(type_id, type_name, resource_map, &entity_handles);
+        }
+    }
+
+    /// Let every storage forget removals from before the previous call
+    pub fn on_trim_removed(&self, resource_map: &ResourceMap) {
+        for rc in &self.registered_components {
+            rc.trim_removed(resource_map);
+        }
+    }
 }
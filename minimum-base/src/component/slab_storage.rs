@@ -7,6 +7,11 @@ use super::RawSlab;
 use super::RawSlabKey;
 use super::ComponentAllocateResult;
 use super::ComponentAllocateError;
+use super::ChangeTick;
+use super::ChangeTracker;
+
+#[cfg(feature = "std")]
+use rayon::prelude::*;
 
 /// Allows iteration of all components
 pub struct SlabComponentIterator<'a, T, I>
@@ -114,6 +119,13 @@ where
 pub struct SlabComponentStorage<T: Component> {
     slab: RawSlab<T>,
     slab_keys: Vec<Option<RawSlabKey<T>>>,
+
+    // Parallel array with the slab, holding the entity index that owns each component. This lets
+    // the slab be iterated directly (i.e. in parallel) while still knowing which entity each
+    // component belongs to. Entries for free slots are stale.
+    slab_entity_indices: Vec<u32>,
+
+    changes: ChangeTracker,
 }
 
 impl<T: Component> SlabComponentStorage<T> {
@@ -123,6 +135,8 @@ impl<T: Component> SlabComponentStorage<T> {
         SlabComponentStorage::<T> {
             slab: RawSlab::new(),
             slab_keys: Vec::with_capacity(32),
+            slab_entity_indices: Vec::with_capacity(32),
+            changes: ChangeTracker::new(),
         }
     }
 
@@ -146,6 +160,7 @@ impl<T: Component> SlabComponentStorage<T> {
         &'a mut self,
         entity_set: &'a super::entity::EntitySet,
     ) -> impl Iterator<Item = (EntityHandle, &'a mut T)> {
+        self.changes.on_change_all();
         SlabComponentIteratorMut::<T, _>::new(
             &mut self.slab,
             entity_set,
@@ -156,6 +171,34 @@ impl<T: Component> SlabComponentStorage<T> {
         )
     }
 
+    /// Iterate all Ts in parallel, returning (EntityHandle, &T) pairs. The slab is split into
+    /// chunks across rayon's thread pool.
+    #[cfg(feature = "std")]
+    pub fn par_iter<'a>(
+        &'a self,
+        entity_set: &'a super::entity::EntitySet,
+    ) -> impl ParallelIterator<Item = (EntityHandle, &'a T)> {
+        let slab_entity_indices = &self.slab_entity_indices;
+        self.slab.par_iter().map(move |(slab_key, component)| {
+            let entity_index = slab_entity_indices[slab_key.index() as usize];
+            (entity_set.upgrade_index_to_handle(entity_index), component)
+        })
+    }
+
+    /// Iterate all Ts mutably in parallel, returning (EntityHandle, &mut T) pairs
+    #[cfg(feature = "std")]
+    pub fn par_iter_mut<'a>(
+        &'a mut self,
+        entity_set: &'a super::entity::EntitySet,
+    ) -> impl ParallelIterator<Item = (EntityHandle, &'a mut T)> {
+        self.changes.on_change_all();
+        let slab_entity_indices = &self.slab_entity_indices;
+        self.slab.par_iter_mut().map(move |(slab_key, component)| {
+            let entity_index = slab_entity_indices[slab_key.index() as usize];
+            (entity_set.upgrade_index_to_handle(entity_index), component)
+        })
+    }
+
     /// Iterate just the components
     pub fn iter_values(&self) -> impl Iterator<Item = &T> {
         self.slab.iter().map(|(_key, value)| value)
@@ -163,17 +206,53 @@ impl<T: Component> SlabComponentStorage<T> {
 
     /// Iterate just the components mutably
     pub fn iter_values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.changes.on_change_all();
         self.slab.iter_mut().map(|(_key, value)| value)
     }
 
+    /// The tick of the most recent change to this storage. Save it to find out what changed after
+    /// this point with `iter_added`, `iter_changed` and `iter_removed`.
+    pub fn change_tick(&self) -> ChangeTick {
+        self.changes.change_tick()
+    }
+
+    /// Iterate Ts that were allocated after `since`
+    pub fn iter_added<'a>(
+        &'a self,
+        entity_set: &'a super::entity::EntitySet,
+        since: ChangeTick,
+    ) -> impl Iterator<Item = (EntityHandle, &'a T)> {
+        let changes = &self.changes;
+        self.iter(entity_set)
+            .filter(move |(entity, _component)| changes.added_since(entity.index(), since))
+    }
+
+    /// Iterate Ts that were allocated or fetched mutably after `since`
+    pub fn iter_changed<'a>(
+        &'a self,
+        entity_set: &'a super::entity::EntitySet,
+        since: ChangeTick,
+    ) -> impl Iterator<Item = (EntityHandle, &'a T)> {
+        let changes = &self.changes;
+        self.iter(entity_set)
+            .filter(move |(entity, _component)| changes.changed_since(entity.index(), since))
+    }
+
+    /// Iterate entities that had their T freed after `since`. Removals are forgotten after a frame
+    /// (see `ChangeTracker::trim_removed`).
+    pub fn iter_removed(&self, since: ChangeTick) -> impl Iterator<Item = &EntityHandle> {
+        self.changes.removed_since(since)
+    }
+
     /// Removes all components of type T from all entities
-    pub fn free_all(&mut self) {
+    pub fn free_all(&mut self, entity_set: &super::entity::EntitySet) {
         //TODO: This is not calling the free handler
-        for slab_key in &mut self.slab_keys {
-            if let Some(key) = &*slab_key {
+        for (entity_index, slab_key) in self.slab_keys.iter_mut().enumerate() {
+            if let Some(key) = slab_key.take() {
                 self.slab.free(&key);
+                let entity = entity_set.upgrade_index_to_handle(entity_index as u32);
+                self.changes.on_free(&entity);
             }
-            *slab_key = None;
         }
     }
 
@@ -181,12 +260,36 @@ impl<T: Component> SlabComponentStorage<T> {
     pub fn count(&self) -> usize {
         self.slab.count()
     }
+
+    /// Indices of all entities that have a T, in ascending order
+    pub fn entity_indices(&self) -> Vec<u32> {
+        self.slab_keys
+            .iter()
+            .enumerate()
+            .filter(|(_entity_index, component_key)| component_key.is_some())
+            .map(|(entity_index, _component_key)| entity_index as u32)
+            .collect()
+    }
+
+    /// Get the T of the entity in the given slot, without checking the entity's generation. Used
+    /// by joins, which get the index from the entity set.
+    pub fn get_by_index(&self, entity_index: u32) -> Option<&T> {
+        self.slab
+            .get(self.slab_keys.get(entity_index as usize)?.as_ref()?)
+    }
+
+    /// Mutable version of `get_by_index`
+    pub fn get_by_index_mut(&mut self, entity_index: u32) -> Option<&mut T> {
+        let component = self
+            .slab
+            .get_mut(self.slab_keys.get(entity_index as usize)?.as_ref()?)?;
+        self.changes.on_change(entity_index);
+        Some(component)
+    }
 }
 
 impl<T: Component> ComponentStorage<T> for SlabComponentStorage<T> {
     fn allocate(&mut self, entity: &EntityHandle, data: T) -> ComponentAllocateResult {
-        let slab_key = self.slab.allocate(data);
-
         // If the slab keys vec isn't long enough, expand it
         if self.slab_keys.len() <= entity.index() as usize {
             // Can't use resize() because T is not guaranteed to be cloneable
@@ -196,11 +299,21 @@ impl<T: Component> ComponentStorage<T> for SlabComponentStorage<T> {
             }
         }
 
+        // Check before allocating so that the slab never holds a component no entity owns
         if self.slab_keys[entity.index() as usize].is_some() {
             return Err(ComponentAllocateError::AlreadyHasComponent);
         }
 
+        let slab_key = self.slab.allocate(data);
+
+        let slab_index = slab_key.index() as usize;
+        if self.slab_entity_indices.len() <= slab_index {
+            self.slab_entity_indices.resize(slab_index + 1, 0);
+        }
+        self.slab_entity_indices[slab_index] = entity.index();
+
         self.slab_keys[entity.index() as usize] = Some(slab_key);
+        self.changes.on_allocate(entity.index());
         Ok(())
     }
 
@@ -211,6 +324,7 @@ impl<T: Component> ComponentStorage<T> for SlabComponentStorage<T> {
         self.slab
             .free(self.slab_keys[entity.index() as usize].as_ref().unwrap());
         self.slab_keys[entity.index() as usize] = None;
+        self.changes.on_free(entity);
     }
 
     fn free_if_exists(&mut self, entity: &EntityHandle) {
@@ -245,7 +359,14 @@ impl<T: Component> ComponentStorage<T> for SlabComponentStorage<T> {
             return None;
         }
 
-        self.slab
-            .get_mut(self.slab_keys[entity.index() as usize].as_ref()?)
+        let component = self
+            .slab
+            .get_mut(self.slab_keys[entity.index() as usize].as_ref()?)?;
+        self.changes.on_change(entity.index());
+        Some(component)
+    }
+
+    fn trim_removed(&mut self) {
+        self.changes.trim_removed();
     }
 }
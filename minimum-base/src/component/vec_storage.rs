@@ -5,6 +5,11 @@ use super::ComponentStorage;
 use super::EntityHandle;
 use super::ComponentAllocateResult;
 use super::ComponentAllocateError;
+use super::ChangeTick;
+use super::ChangeTracker;
+
+#[cfg(feature = "std")]
+use rayon::prelude::*;
 
 /// Allows iteration of all components
 pub struct VecComponentIterator<'a, T, I>
@@ -90,6 +95,11 @@ where
 /// is fast, but can use an unnecessary amount of memory
 pub struct VecComponentStorage<T: Component> {
     components: Vec<Option<T>>,
+
+    // Number of Some in components, so it doesn't need to be counted when choosing how to join
+    count: usize,
+
+    changes: ChangeTracker,
 }
 
 impl<T: Component> VecComponentStorage<T> {
@@ -98,6 +108,8 @@ impl<T: Component> VecComponentStorage<T> {
     pub fn new() -> Self {
         VecComponentStorage::<T> {
             components: Vec::with_capacity(32),
+            count: 0,
+            changes: ChangeTracker::new(),
         }
     }
 
@@ -120,6 +132,7 @@ impl<T: Component> VecComponentStorage<T> {
         &'a mut self,
         entity_set: &'a super::entity::EntitySet,
     ) -> impl Iterator<Item = (EntityHandle, &'a mut T)> {
+        self.changes.on_change_all();
         VecComponentIteratorMut::<T, _>::new(
             entity_set,
             self.components
@@ -129,6 +142,46 @@ impl<T: Component> VecComponentStorage<T> {
         )
     }
 
+    /// Iterate all Ts in parallel, returning (EntityHandle, &T) pairs. The vec is split into chunks
+    /// across rayon's thread pool.
+    #[cfg(feature = "std")]
+    pub fn par_iter<'a>(
+        &'a self,
+        entity_set: &'a super::entity::EntitySet,
+    ) -> impl ParallelIterator<Item = (EntityHandle, &'a T)> {
+        self.components
+            .par_iter()
+            .enumerate()
+            .filter_map(move |(entity_index, component)| {
+                component.as_ref().map(|component| {
+                    (
+                        entity_set.upgrade_index_to_handle(entity_index as u32),
+                        component,
+                    )
+                })
+            })
+    }
+
+    /// Iterate all Ts mutably in parallel, returning (EntityHandle, &mut T) pairs
+    #[cfg(feature = "std")]
+    pub fn par_iter_mut<'a>(
+        &'a mut self,
+        entity_set: &'a super::entity::EntitySet,
+    ) -> impl ParallelIterator<Item = (EntityHandle, &'a mut T)> {
+        self.changes.on_change_all();
+        self.components
+            .par_iter_mut()
+            .enumerate()
+            .filter_map(move |(entity_index, component)| {
+                component.as_mut().map(|component| {
+                    (
+                        entity_set.upgrade_index_to_handle(entity_index as u32),
+                        component,
+                    )
+                })
+            })
+    }
+
     /// Iterate just the components
     pub fn iter_values(&self) -> impl Iterator<Item = &T> {
         self.components
@@ -138,22 +191,85 @@ impl<T: Component> VecComponentStorage<T> {
 
     /// Iterate just the components mutably
     pub fn iter_values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.changes.on_change_all();
         self.components
             .iter_mut()
             .filter_map(|component_key| component_key.as_mut())
     }
 
+    /// The tick of the most recent change to this storage. Save it to find out what changed after
+    /// this point with `iter_added`, `iter_changed` and `iter_removed`.
+    pub fn change_tick(&self) -> ChangeTick {
+        self.changes.change_tick()
+    }
+
+    /// Iterate Ts that were allocated after `since`
+    pub fn iter_added<'a>(
+        &'a self,
+        entity_set: &'a super::entity::EntitySet,
+        since: ChangeTick,
+    ) -> impl Iterator<Item = (EntityHandle, &'a T)> {
+        let changes = &self.changes;
+        self.iter(entity_set)
+            .filter(move |(entity, _component)| changes.added_since(entity.index(), since))
+    }
+
+    /// Iterate Ts that were allocated or fetched mutably after `since`
+    pub fn iter_changed<'a>(
+        &'a self,
+        entity_set: &'a super::entity::EntitySet,
+        since: ChangeTick,
+    ) -> impl Iterator<Item = (EntityHandle, &'a T)> {
+        let changes = &self.changes;
+        self.iter(entity_set)
+            .filter(move |(entity, _component)| changes.changed_since(entity.index(), since))
+    }
+
+    /// Iterate entities that had their T freed after `since`. Removals are forgotten after a frame
+    /// (see `ChangeTracker::trim_removed`).
+    pub fn iter_removed(&self, since: ChangeTick) -> impl Iterator<Item = &EntityHandle> {
+        self.changes.removed_since(since)
+    }
+
     /// Removes all components of type T from all entities
-    pub fn free_all(&mut self) {
+    pub fn free_all(&mut self, entity_set: &super::entity::EntitySet) {
         //TODO: This is not calling the free handler
-        for component in &mut self.components {
-            *component = None;
+        for (entity_index, component) in self.components.iter_mut().enumerate() {
+            if component.take().is_some() {
+                let entity = entity_set.upgrade_index_to_handle(entity_index as u32);
+                self.changes.on_free(&entity);
+            }
         }
+
+        self.count = 0;
     }
 
     /// Returns count of allocated components
     pub fn count(&self) -> usize {
-        self.components.iter().filter(|x| x.is_some()).count()
+        self.count
+    }
+
+    /// Indices of all entities that have a T, in ascending order
+    pub fn entity_indices(&self) -> Vec<u32> {
+        self.components
+            .iter()
+            .enumerate()
+            .filter(|(_entity_index, component)| component.is_some())
+            .map(|(entity_index, _component)| entity_index as u32)
+            .collect()
+    }
+
+    /// Get the T of the entity in the given slot, without checking the entity's generation. Used
+    /// by joins, which get the index from the entity set.
+    pub fn get_by_index(&self, entity_index: u32) -> Option<&T> {
+        self.components.get(entity_index as usize)?.as_ref()
+    }
+
+    /// Mutable version of `get_by_index`
+    pub fn get_by_index_mut(&mut self, entity_index: u32) -> Option<&mut T> {
+        let component = self.components.get_mut(entity_index as usize)?.as_mut()?;
+        self.changes.on_change(entity_index);
+        Some(component)
     }
 }
 
@@ -174,6 +290,8 @@ impl<T: Component> ComponentStorage<T> for VecComponentStorage<T> {
         }
 
         self.components[entity.index() as usize] = Some(data);
+        self.count += 1;
+        self.changes.on_allocate(entity.index());
         Ok(())
     }
 
@@ -182,6 +300,8 @@ impl<T: Component> ComponentStorage<T> for VecComponentStorage<T> {
         // since it's a bit dangerous
         assert!(self.components[entity.index() as usize].is_some());
         self.components[entity.index() as usize] = None;
+        self.count -= 1;
+        self.changes.on_free(entity);
     }
 
     fn free_if_exists(&mut self, entity: &EntityHandle) {
@@ -215,6 +335,12 @@ impl<T: Component> ComponentStorage<T> for VecComponentStorage<T> {
             return None;
         }
 
-        self.components[entity.index() as usize].as_mut()
+        let component = self.components[entity.index() as usize].as_mut()?;
+        self.changes.on_change(entity.index());
+        Some(component)
+    }
+
+    fn trim_removed(&mut self) {
+        self.changes.trim_removed();
     }
 }
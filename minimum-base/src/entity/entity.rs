@@ -4,6 +4,9 @@ use crate::component;
 use component::Component;
 use component::ComponentStorage;
 use crate::component::ComponentAllocateResult;
+use crate::component::ComponentRegistry;
+use crate::component::ComponentRemoveQueue;
+use crate::resource::ResourceMap;
 
 /// Represents a created entity. All data for entities is stored in components. Usually, you will work
 /// with an EntityRef, not an entity directly.
@@ -46,15 +49,19 @@ pub struct EntityRef<'e> {
     /// as long as the Ref structure
     _entity: &'e Entity,
 
+    /// Used to run free handlers when components are removed
+    component_registry: &'e ComponentRegistry,
+
     /// Handle of the entity we point to
     handle: EntityHandle,
 }
 
 impl<'e> EntityRef<'e> {
     /// Creates an entity ref
-    pub(super) fn new(entity: &'e Entity) -> Self {
+    pub(super) fn new(entity: &'e Entity, component_registry: &'e ComponentRegistry) -> Self {
         EntityRef {
             _entity: entity,
+            component_registry,
             handle: entity.handle(),
         }
     }
@@ -73,11 +80,20 @@ impl<'e> EntityRef<'e> {
         storage.allocate(&self.handle, data)
     }
 
-    // TODO: Cannot support this without calling free handler
-    // Remove a component from the entity. Removal is immediate, but the free
-    //    pub fn remove_component<T: Component>(&self, storage: &mut T::Storage) {
-    //        storage.free(&self.handle);
-    //    }
+    /// Remove a component from the entity immediately, running the free handler registered for T
+    /// first. Does nothing if the entity doesn't have a T. The T storage (and anything the free
+    /// handler fetches) must not be borrowed, so from inside a task, prefer
+    /// `enqueue_remove_component`.
+    pub fn remove_component<T: Component>(&self, resource_map: &ResourceMap) {
+        self.component_registry
+            .remove_components::<T>(resource_map, std::slice::from_ref(&self.handle));
+    }
+
+    /// Remove a component from the entity during the next `EntitySet::update`, running the free
+    /// handler registered for T first
+    pub fn enqueue_remove_component<T: Component>(&self, remove_queue: &mut ComponentRemoveQueue) {
+        remove_queue.enqueue_remove::<T>(&self.handle);
+    }
 
     /// Gets a component for the given entity
     pub fn get_component<'c, T: Component>(&self, storage: &'c T::Storage) -> Option<&'c T> {
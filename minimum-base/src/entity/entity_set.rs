@@ -53,7 +53,7 @@ impl EntitySet {
         let handle = self.slab.allocate(Entity::new());
         let entity = self.slab.get_mut(&handle).unwrap();
         entity.set_handle(handle.clone());
-        EntityRef::new(entity)
+        EntityRef::new(entity, &self.component_registry)
     }
 
     /// Free the given entity during the next EntitySet::update.
@@ -81,7 +81,7 @@ impl EntitySet {
     /// allows for adding/removing components from the entity
     pub fn get_entity_ref(&self, entity_handle: &EntityHandle) -> Option<EntityRef> {
         let e = self.slab.get(entity_handle)?;
-        Some(EntityRef::new(e))
+        Some(EntityRef::new(e, &self.component_registry))
     }
 
     /// Destroy all entities and their components immediately
@@ -90,16 +90,42 @@ impl EntitySet {
         self.do_flush_free(resource_map, entity_handles.as_slice());
     }
 
+    /// Immediately remove the entity's T, running the free handler registered for T first. Does
+    /// nothing if the entity doesn't have a T. The T storage must not be borrowed.
+    pub fn remove_component<T: Component>(
+        &self,
+        resource_map: &resource::ResourceMap,
+        entity_handle: &EntityHandle,
+    ) {
+        self.component_registry
+            .remove_components::<T>(resource_map, std::slice::from_ref(entity_handle));
+    }
+
+    /// Immediately handle all component removals queued in the ComponentRemoveQueue. Usually you
+    /// would just call update(), which would call this for you.
+    pub fn flush_removes(&self, resource_map: &resource::ResourceMap) {
+        self.component_registry.on_flush_removes(resource_map);
+    }
+
     /// Immediately handle all deferred free calls. Usually you would just call update(), which
     /// would call this for you.
+    ///
+    /// Free handlers may enqueue more entities to be freed (i.e. the children of a freed entity).
+    /// Those are freed too before this returns.
     pub fn flush_free(&mut self, resource_map: &resource::ResourceMap) {
-        let entity_handles: Vec<_> = {
-            let delete_components =
-                resource_map.fetch_mut::<<PendingDeleteComponent as Component>::Storage>();
-            delete_components.iter(&self).map(|x| x.0).collect()
-        };
-
-        self.do_flush_free(resource_map, &entity_handles);
+        loop {
+            let entity_handles: Vec<_> = {
+                let delete_components =
+                    resource_map.fetch_mut::<<PendingDeleteComponent as Component>::Storage>();
+                delete_components.iter(self).map(|x| x.0).collect()
+            };
+
+            if entity_handles.is_empty() {
+                break;
+            }
+
+            self.do_flush_free(resource_map, &entity_handles);
+        }
     }
 
     fn do_flush_free(
@@ -110,6 +136,15 @@ impl EntitySet {
         self.component_registry
             .on_entities_free(resource_map, entity_handles);
 
+        // PendingDeleteComponent isn't necessarily registered, but must not outlive the entity
+        if let Some(mut delete_components) =
+            resource_map.try_fetch_mut::<<PendingDeleteComponent as Component>::Storage>()
+        {
+            for pending_delete in entity_handles {
+                delete_components.free_if_exists(pending_delete);
+            }
+        }
+
         for pending_delete in entity_handles {
             self.slab.free(pending_delete);
         }
@@ -124,10 +159,12 @@ impl EntitySet {
         self.component_registry.on_flush_creates(resource_map, self);
     }
 
-    /// Call once a frame to handle any deferred entity create/free calls
+    /// Call once a frame to handle any deferred entity create/free calls and component removals
     pub fn update(&mut self, resource_map: &resource::ResourceMap) {
+        self.flush_removes(resource_map);
         self.flush_free(resource_map);
         self.flush_creates(resource_map);
+        self.component_registry.on_trim_removed(resource_map);
     }
 
     /// Iterate across all entities
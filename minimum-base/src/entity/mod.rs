@@ -1,6 +1,9 @@
 //! This module allows creating entities and associating components with the entities.
 //!
 //! Components are automatically cleared when entities are destroyed.
+//! A single component can be removed with `EntityRef::remove_component` (immediately) or
+//! `EntityRef::enqueue_remove_component` (during the next `EntitySet::update`). Either way, the
+//! component's free handler runs first, just like when the entity is destroyed.
 
 use crate::slab::GenSlabKey;
 
@@ -132,14 +135,182 @@ mod tests {
         let component = entity.get_component::<TestComponent>(&test_component_storage);
         assert!(component.is_some());
 
-        //TODO: Had to drop remove_component for now since it doesn't call the free handler
-        /*
-        // Remove the component
-        entity.remove_component::<TestComponent>(&mut test_component_storage);
+        // Remove the component. The storage can't be borrowed while the free handler runs.
+        drop(test_component_storage);
+        entity.remove_component::<TestComponent>(&resource_map);
 
         // Fail to find the component
+        let test_component_storage = resource_map.fetch::<Storage>();
         let component = entity.get_component::<TestComponent>(&test_component_storage);
         assert!(component.is_none());
-        */
+    }
+
+    struct FreedCount(usize);
+
+    struct CountedComponent;
+
+    impl Component for CountedComponent {
+        type Storage = VecComponentStorage<Self>;
+    }
+
+    struct CountedComponentFreeHandler;
+
+    impl component::ComponentFreeHandler<CountedComponent> for CountedComponentFreeHandler {
+        fn on_entities_free(
+            resource_map: &resource::ResourceMap,
+            entity_handles: &[EntityHandle],
+            storage: &mut <CountedComponent as Component>::Storage,
+        ) {
+            for entity_handle in entity_handles {
+                assert!(storage.exists(entity_handle));
+                resource_map.fetch_mut::<FreedCount>().0 += 1;
+            }
+        }
+    }
+
+    fn build_counted_world() -> (EntitySet, resource::ResourceMap, Vec<EntityHandle>) {
+        let mut component_registry = ComponentRegistry::new();
+        component_registry.register_component_with_free_handler::<
+            CountedComponent,
+            CountedComponentFreeHandler,
+        >();
+
+        let mut resource_map = resource::ResourceMap::new();
+        let mut entity_set = EntitySet::new(component_registry);
+        resource_map.insert(<CountedComponent as Component>::Storage::new());
+        resource_map.insert(<PendingDeleteComponent as Component>::Storage::new());
+        resource_map.insert(component::ComponentRemoveQueue::new());
+        resource_map.insert(EntityFactory::new());
+        resource_map.insert(FreedCount(0));
+
+        let entity_handles: Vec<_> = (0..2).map(|_| entity_set.allocate()).collect();
+        for entity_handle in &entity_handles {
+            resource_map
+                .fetch_mut::<<CountedComponent as Component>::Storage>()
+                .allocate(entity_handle, CountedComponent)
+                .unwrap();
+        }
+
+        (entity_set, resource_map, entity_handles)
+    }
+
+    #[test]
+    fn test_remove_component_runs_free_handler() {
+        let (entity_set, resource_map, entity_handles) = build_counted_world();
+
+        entity_set.remove_component::<CountedComponent>(&resource_map, &entity_handles[0]);
+        assert_eq!(resource_map.fetch::<FreedCount>().0, 1);
+
+        // Removing a component that doesn't exist does nothing
+        entity_set.remove_component::<CountedComponent>(&resource_map, &entity_handles[0]);
+        assert_eq!(resource_map.fetch::<FreedCount>().0, 1);
+
+        let storage = resource_map.fetch::<<CountedComponent as Component>::Storage>();
+        assert!(!storage.exists(&entity_handles[0]));
+        assert!(storage.exists(&entity_handles[1]));
+        assert_eq!(entity_set.entity_count(), 2);
+    }
+
+    #[test]
+    fn test_enqueue_remove_component() {
+        let (mut entity_set, resource_map, entity_handles) = build_counted_world();
+
+        {
+            let entity = entity_set.get_entity_ref(&entity_handles[1]).unwrap();
+            let mut remove_queue = resource_map.fetch_mut::<component::ComponentRemoveQueue>();
+            entity.enqueue_remove_component::<CountedComponent>(&mut remove_queue);
+            entity.enqueue_remove_component::<CountedComponent>(&mut remove_queue);
+        }
+
+        // Nothing happens until the entity set is updated, and the handler runs once
+        assert_eq!(resource_map.fetch::<FreedCount>().0, 0);
+        entity_set.update(&resource_map);
+        assert_eq!(resource_map.fetch::<FreedCount>().0, 1);
+
+        let storage = resource_map.fetch::<<CountedComponent as Component>::Storage>();
+        assert!(storage.exists(&entity_handles[0]));
+        assert!(!storage.exists(&entity_handles[1]));
+    }
+
+    #[test]
+    #[should_panic(expected = "was never registered")]
+    fn test_remove_unregistered_component() {
+        let (entity_set, resource_map, entity_handles) = build_counted_world();
+        entity_set.remove_component::<TestComponent>(&resource_map, &entity_handles[0]);
+    }
+
+    // Frees the linked entity when the entity that owns this component is freed
+    struct LinkedComponent {
+        linked: EntityHandle,
+    }
+
+    impl Component for LinkedComponent {
+        type Storage = VecComponentStorage<Self>;
+    }
+
+    struct LinkedComponentFreeHandler;
+
+    impl component::ComponentFreeHandler<LinkedComponent> for LinkedComponentFreeHandler {
+        fn on_entities_free(
+            resource_map: &resource::ResourceMap,
+            entity_handles: &[EntityHandle],
+            storage: &mut <LinkedComponent as Component>::Storage,
+        ) {
+            let mut delete_components =
+                resource_map.fetch_mut::<<PendingDeleteComponent as Component>::Storage>();
+            for entity_handle in entity_handles {
+                if let Some(linked_component) = storage.get(entity_handle) {
+                    delete_components
+                        .allocate(&linked_component.linked, PendingDeleteComponent::new())
+                        .unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_free_handler_enqueues_free() {
+        let mut component_registry = ComponentRegistry::new();
+        component_registry.register_component_with_free_handler::<
+            LinkedComponent,
+            LinkedComponentFreeHandler,
+        >();
+
+        let mut resource_map = resource::ResourceMap::new();
+        let mut entity_set = EntitySet::new(component_registry);
+        resource_map.insert(<LinkedComponent as Component>::Storage::new());
+        resource_map.insert(<PendingDeleteComponent as Component>::Storage::new());
+
+        // Freeing the first entity frees the second, which frees the third
+        let entity_handles: Vec<_> = (0..4).map(|_| entity_set.allocate()).collect();
+        {
+            let mut storage = resource_map.fetch_mut::<<LinkedComponent as Component>::Storage>();
+            for i in 0..2 {
+                storage
+                    .allocate(
+                        &entity_handles[i],
+                        LinkedComponent {
+                            linked: entity_handles[i + 1].clone(),
+                        },
+                    )
+                    .unwrap();
+            }
+        }
+
+        entity_set.enqueue_free(
+            &entity_handles[0],
+            &mut resource_map.fetch_mut::<<PendingDeleteComponent as Component>::Storage>(),
+        );
+        entity_set.flush_free(&resource_map);
+
+        assert_eq!(entity_set.entity_count(), 1);
+        assert!(entity_set.get_entity_ref(&entity_handles[3]).is_some());
+        assert_eq!(
+            resource_map
+                .fetch::<<PendingDeleteComponent as Component>::Storage>()
+                .iter(&entity_set)
+                .count(),
+            0
+        );
     }
 }
@@ -1,6 +1,4 @@
 #![cfg_attr(not(feature = "std"), no_std)]
-// Used for getting name of type, but only available in nightly
-#![cfg_attr(feature = "nightly", feature(core_intrinsics))]
 
 extern crate no_std_compat as std;
 
@@ -19,6 +17,13 @@ pub mod task;
 pub mod util;
 pub mod world;
 
+#[cfg(test)]
+pub(crate) mod test_tasks;
+
+// Re-exported so that users of par_iter/par_join have the parallel iterator traits available
+#[cfg(feature = "std")]
+pub use rayon;
+
 pub use entity::BasicEntityPrototype;
 pub use entity::Entity;
 pub use entity::EntityFactory;
@@ -35,8 +40,12 @@ pub use component::ComponentCreateQueueFlushListener;
 pub use component::ComponentPrototypeDyn;
 pub use component::ComponentFactory;
 pub use component::ComponentPrototype;
+pub use component::ComponentRemoveQueue;
 pub use component::ComponentStorage;
-pub use component::{ReadComponent, ReadComponentOption, WriteComponent, WriteComponentOption};
+pub use component::{
+    ReadComponent, ReadComponentBorrow, ReadComponentOption, WriteComponent, WriteComponentBorrow,
+    WriteComponentOption,
+};
 
 pub use resource::DataRequirement;
 pub use resource::Read;
@@ -47,23 +56,38 @@ pub use resource::ResourceMapBuilder;
 pub use resource::Write;
 pub use resource::WriteOption;
 
+pub use world::NamedSchedules;
+pub use world::UpdateLoop;
+pub use world::UpdateLoopGraph;
+pub use world::UpdateLoopMultiThreaded;
 pub use world::UpdateLoopSingleThreaded;
 pub use world::World;
 pub use world::WorldBuilder;
 
+pub use task::ContextFlag;
 pub use task::DispatchControl;
+pub use task::FixedTimestep;
 pub use task::Phase;
 pub use task::ReadAllTask;
 pub use task::ReadAllTaskImpl;
 pub use task::ResourceTask;
 pub use task::ResourceTaskImpl;
+pub use task::ScheduleDescription;
+pub use task::Scheduler;
+pub use task::SubScheduleTask;
+pub use task::SubScheduleTaskImpl;
 pub use task::TaskConfig;
 pub use task::TaskContextFlags;
 pub use task::TaskDependencyList;
+pub use task::TaskDependencyListBuildError;
 pub use task::TaskDependencyListBuilder;
 pub use task::TaskFactory;
+#[cfg(feature = "std")]
+pub use task::TaskProfiler;
+pub use task::TaskScheduleBuilderGraph;
 pub use task::TaskScheduleBuilderMultiThread;
 pub use task::TaskScheduleBuilderSingleThread;
+pub use task::TaskScheduleGraph;
 pub use task::TaskScheduleMultiThread;
 pub use task::TaskScheduleSingleThread;
 pub use task::WriteAllTask;
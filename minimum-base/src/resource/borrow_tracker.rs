@@ -0,0 +1,210 @@
+//! Records who holds each borrow in a `ResourceMap`. When a fetch conflicts with an active borrow,
+//! the panic names the resource and both borrowers (the task that was running, if any, and the call
+//! site).
+//!
+//! Only compiled in with the `borrow_tracking` feature. Every fetch and release goes through a
+//! global lock, so this is meant for debugging rather than being left on.
+
+use std::prelude::v1::*;
+
+use hashbrown::HashMap;
+
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+use std::panic::Location;
+use std::sync::{Mutex, MutexGuard};
+
+use super::ResourceId;
+
+thread_local! {
+    static CURRENT_TASK: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Attributes borrows made on this thread to the given task until dropped
+pub struct CurrentTaskScope {
+    previous: Option<&'static str>,
+}
+
+impl CurrentTaskScope {
+    pub fn new(task_name: &'static str) -> Self {
+        let previous = CURRENT_TASK.with(|x| x.replace(Some(task_name)));
+        CurrentTaskScope { previous }
+    }
+}
+
+impl Drop for CurrentTaskScope {
+    fn drop(&mut self) {
+        CURRENT_TASK.with(|x| x.set(self.previous));
+    }
+}
+
+// Whoever is holding a borrow
+#[derive(Clone, Copy)]
+struct Borrower {
+    task: Option<&'static str>,
+    location: &'static Location<'static>,
+}
+
+impl Borrower {
+    #[track_caller]
+    fn current() -> Self {
+        Borrower {
+            task: CURRENT_TASK.with(|x| x.get()),
+            location: Location::caller(),
+        }
+    }
+}
+
+impl Display for Borrower {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self.task {
+            Some(task) => write!(f, "task {} at {}", task, self.location),
+            None => write!(f, "{}", self.location),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ActiveBorrows {
+    writer: Option<(usize, Borrower)>,
+    readers: Vec<(usize, Borrower)>,
+}
+
+#[derive(Default)]
+struct BorrowTrackerState {
+    next_borrow_id: usize,
+    active: HashMap<ResourceId, ActiveBorrows>,
+}
+
+impl BorrowTrackerState {
+    fn allocate_borrow_id(&mut self) -> usize {
+        let borrow_id = self.next_borrow_id;
+        self.next_borrow_id += 1;
+        borrow_id
+    }
+}
+
+#[derive(Default)]
+pub(super) struct BorrowTracker {
+    state: Mutex<BorrowTrackerState>,
+}
+
+impl BorrowTracker {
+    // Conflicts panic while holding the lock. Keep going so that the borrows still held by other
+    // threads can be released
+    fn lock(&self) -> MutexGuard<'_, BorrowTrackerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    #[track_caller]
+    pub(super) fn track_read(&self, resource_id: &ResourceId) -> TrackedBorrow<'_> {
+        let borrower = Borrower::current();
+        let mut state = self.lock();
+        let borrow_id = state.allocate_borrow_id();
+        let active = state.active.entry(resource_id.clone()).or_default();
+
+        if let Some((_, writer)) = &active.writer {
+            panic!(
+                "Tried to fetch resource {} for reading from {}, but it is already borrowed for writing by {}",
+                resource_id.type_name(),
+                borrower,
+                writer
+            );
+        }
+
+        active.readers.push((borrow_id, borrower));
+        TrackedBorrow {
+            tracker: self,
+            resource_id: resource_id.clone(),
+            borrow_id,
+        }
+    }
+
+    #[track_caller]
+    pub(super) fn track_write(&self, resource_id: &ResourceId) -> TrackedBorrow<'_> {
+        let borrower = Borrower::current();
+        let mut state = self.lock();
+        let borrow_id = state.allocate_borrow_id();
+        let active = state.active.entry(resource_id.clone()).or_default();
+
+        if let Some((_, writer)) = &active.writer {
+            panic!(
+                "Tried to fetch resource {} for writing from {}, but it is already borrowed for writing by {}",
+                resource_id.type_name(),
+                borrower,
+                writer
+            );
+        }
+
+        if !active.readers.is_empty() {
+            let readers: Vec<_> = active
+                .readers
+                .iter()
+                .map(|(_, reader)| reader.to_string())
+                .collect();
+
+            panic!(
+                "Tried to fetch resource {} for writing from {}, but it is already borrowed for reading by {}",
+                resource_id.type_name(),
+                borrower,
+                readers.join(", ")
+            );
+        }
+
+        active.writer = Some((borrow_id, borrower));
+        TrackedBorrow {
+            tracker: self,
+            resource_id: resource_id.clone(),
+            borrow_id,
+        }
+    }
+
+    // Used when a read borrow is cloned. The clone is attributed to whoever made the original borrow.
+    fn duplicate_read(&self, borrow: &TrackedBorrow) -> TrackedBorrow<'_> {
+        let mut state = self.lock();
+        let borrow_id = state.allocate_borrow_id();
+        let active = state.active.get_mut(&borrow.resource_id).unwrap();
+        let borrower = active
+            .readers
+            .iter()
+            .find(|(id, _)| *id == borrow.borrow_id)
+            .unwrap()
+            .1;
+
+        active.readers.push((borrow_id, borrower));
+        TrackedBorrow {
+            tracker: self,
+            resource_id: borrow.resource_id.clone(),
+            borrow_id,
+        }
+    }
+
+    fn release(&self, borrow: &TrackedBorrow) {
+        let mut state = self.lock();
+
+        let active = state.active.get_mut(&borrow.resource_id).unwrap();
+        match active.writer {
+            Some((id, _)) if id == borrow.borrow_id => active.writer = None,
+            _ => active.readers.retain(|(id, _)| *id != borrow.borrow_id),
+        }
+    }
+}
+
+/// Removes the borrow from the tracker when dropped
+pub(super) struct TrackedBorrow<'a> {
+    tracker: &'a BorrowTracker,
+    resource_id: ResourceId,
+    borrow_id: usize,
+}
+
+impl<'a> Clone for TrackedBorrow<'a> {
+    fn clone(&self) -> Self {
+        self.tracker.duplicate_read(self)
+    }
+}
+
+impl<'a> Drop for TrackedBorrow<'a> {
+    fn drop(&mut self) {
+        self.tracker.release(self);
+    }
+}
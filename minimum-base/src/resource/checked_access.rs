@@ -0,0 +1,183 @@
+//! Restricts which resources may be fetched while a task is running to the reads/writes the task
+//! declared in its `TaskConfig`. While a task runs, the `ResourceMap` it is handed behaves like a
+//! view of only those resources, and fetching anything else panics. The parallel schedulers rely
+//! on declarations being accurate, so this catches tasks that would race with each other.
+//!
+//! Only compiled in with the `checked_access` feature.
+
+use std::prelude::v1::*;
+
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use super::ResourceId;
+
+/// The resources a task declared that it will access
+#[derive(Debug, Default)]
+pub struct ResourceAccess {
+    pub read_all: bool,
+    pub write_all: bool,
+    pub reads: Vec<ResourceId>,
+    pub writes: Vec<ResourceId>,
+}
+
+impl ResourceAccess {
+    fn can_read(&self, resource_id: &ResourceId) -> bool {
+        self.read_all
+            || self.write_all
+            || self.reads.contains(resource_id)
+            || self.writes.contains(resource_id)
+    }
+
+    fn can_write(&self, resource_id: &ResourceId) -> bool {
+        self.write_all || self.writes.contains(resource_id)
+    }
+
+    fn describe(&self) -> String {
+        if self.write_all {
+            return "write access to all resources".to_string();
+        }
+
+        let reads: Vec<_> = self.reads.iter().map(|x| x.type_name()).collect();
+        let writes: Vec<_> = self.writes.iter().map(|x| x.type_name()).collect();
+
+        format!(
+            "{}reads [{}] and writes [{}]",
+            if self.read_all {
+                "read access to all resources, "
+            } else {
+                ""
+            },
+            reads.join(", "),
+            writes.join(", ")
+        )
+    }
+}
+
+thread_local! {
+    static CURRENT_ACCESS: RefCell<Option<(&'static str, Arc<ResourceAccess>)>> = const { RefCell::new(None) };
+}
+
+/// Restricts fetches made on this thread to the given task's declared access until dropped
+pub struct ResourceAccessScope {
+    previous: Option<(&'static str, Arc<ResourceAccess>)>,
+}
+
+impl ResourceAccessScope {
+    pub fn new(task_name: &'static str, access: Arc<ResourceAccess>) -> Self {
+        let previous = CURRENT_ACCESS.with(|x| x.replace(Some((task_name, access))));
+        ResourceAccessScope { previous }
+    }
+}
+
+impl Drop for ResourceAccessScope {
+    fn drop(&mut self) {
+        CURRENT_ACCESS.with(|x| *x.borrow_mut() = self.previous.take());
+    }
+}
+
+// Panics if a task is running on this thread and it did not declare read access to the resource
+pub(super) fn check_read(resource_id: &ResourceId) {
+    CURRENT_ACCESS.with(|x| {
+        if let Some((task_name, access)) = &*x.borrow() {
+            if !access.can_read(resource_id) {
+                panic!(
+                    "Task {} fetched resource {} for reading, but did not declare it. The task declared {}",
+                    task_name,
+                    resource_id.type_name(),
+                    access.describe()
+                );
+            }
+        }
+    });
+}
+
+// Panics if a task is running on this thread and it did not declare write access to the resource
+pub(super) fn check_write(resource_id: &ResourceId) {
+    CURRENT_ACCESS.with(|x| {
+        if let Some((task_name, access)) = &*x.borrow() {
+            if !access.can_write(resource_id) {
+                panic!(
+                    "Task {} fetched resource {} for writing, but did not declare it. The task declared {}",
+                    task_name,
+                    resource_id.type_name(),
+                    access.describe()
+                );
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::TaskConfig;
+    use crate::test_tasks::{CounterA, CounterB};
+    use crate::{ReadAllTask, ReadAllTaskImpl, ResourceMap, TaskContextFlags, WorldBuilder};
+
+    struct IncrementFromReadAll;
+    type IncrementFromReadAllTask = ReadAllTask<IncrementFromReadAll>;
+    impl ReadAllTaskImpl for IncrementFromReadAll {
+        fn configure(_config: &mut TaskConfig) {}
+
+        fn run(_context_flags: &TaskContextFlags, resource_map: &ResourceMap) {
+            resource_map.fetch_mut::<CounterA>().0 += 1;
+        }
+    }
+
+    fn build_resource_map() -> ResourceMap {
+        let mut resource_map = ResourceMap::new();
+        resource_map.insert(CounterA(0));
+        resource_map.insert(CounterB(0));
+        resource_map
+    }
+
+    #[test]
+    fn test_declared_access_allowed() {
+        let resource_map = build_resource_map();
+        let access = ResourceAccess {
+            reads: vec![ResourceId::new::<CounterA>()],
+            writes: vec![ResourceId::new::<CounterB>()],
+            ..Default::default()
+        };
+
+        let _scope = ResourceAccessScope::new("TestTask", Arc::new(access));
+        resource_map.fetch::<CounterA>();
+        resource_map.fetch::<CounterB>();
+        resource_map.fetch_mut::<CounterB>();
+    }
+
+    #[test]
+    #[should_panic(expected = "Task TestTask fetched resource")]
+    fn test_undeclared_read() {
+        let resource_map = build_resource_map();
+        let access = ResourceAccess {
+            reads: vec![ResourceId::new::<CounterA>()],
+            ..Default::default()
+        };
+
+        let _scope = ResourceAccessScope::new("TestTask", Arc::new(access));
+        resource_map.fetch::<CounterB>();
+    }
+
+    #[test]
+    fn test_scope_ends() {
+        let resource_map = build_resource_map();
+        {
+            let _scope = ResourceAccessScope::new("TestTask", Arc::new(ResourceAccess::default()));
+        }
+
+        resource_map.fetch_mut::<CounterA>();
+    }
+
+    #[test]
+    #[should_panic(expected = "for writing, but did not declare it")]
+    fn test_read_all_task_writes() {
+        let mut update_loop = WorldBuilder::new()
+            .with_resource(CounterA(0))
+            .with_task::<IncrementFromReadAllTask>()
+            .build_update_loop_single_threaded(0);
+
+        update_loop.step();
+    }
+}
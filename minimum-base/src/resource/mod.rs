@@ -16,16 +16,31 @@ use std::marker::PhantomData;
 
 use crate::util::{TrustCell, TrustCellRef as Ref, TrustCellRefMut as RefMut};
 
+// Optionally record who holds each borrow so that conflicts can name both borrowers
+#[cfg(feature = "borrow_tracking")]
+mod borrow_tracker;
+#[cfg(feature = "borrow_tracking")]
+pub use borrow_tracker::CurrentTaskScope;
+
+// Optionally restrict what a task may fetch to what it declared
+#[cfg(feature = "checked_access")]
+mod checked_access;
+#[cfg(feature = "borrow_tracking")]
+use borrow_tracker::{BorrowTracker, TrackedBorrow};
+#[cfg(feature = "checked_access")]
+pub use checked_access::{ResourceAccess, ResourceAccessScope};
+
 //
 // ResourceId
 //
 use std::any::TypeId;
 
 /// Every type can be converted to a `ResourceId`. The ResourceId is used to look up the type's value
-/// in the `ResourceMap`
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+/// in the `ResourceMap`. The type name is kept for debug output.
+#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct ResourceId {
     type_id: TypeId,
+    type_name: &'static str,
 }
 
 impl ResourceId {
@@ -34,8 +49,20 @@ impl ResourceId {
     pub fn new<T: 'static>() -> Self {
         ResourceId {
             type_id: std::any::TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
         }
     }
+
+    /// The name of the type, for debug purposes. (Not guaranteed to be unique or stable)
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl std::fmt::Debug for ResourceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "ResourceId({})", self.type_name)
+    }
 }
 
 /// Any data that can be stored in the ResourceMap must implement this trait. There is a blanket
@@ -93,6 +120,9 @@ impl ResourceMapBuilder {
 #[derive(Default)]
 pub struct ResourceMap {
     resources: HashMap<ResourceId, TrustCell<Box<dyn Resource>>>,
+
+    #[cfg(feature = "borrow_tracking")]
+    borrow_tracker: BorrowTracker,
 }
 
 impl ResourceMap {
@@ -100,6 +130,9 @@ impl ResourceMap {
     pub fn new() -> Self {
         ResourceMap {
             resources: HashMap::new(),
+
+            #[cfg(feature = "borrow_tracking")]
+            borrow_tracker: BorrowTracker::default(),
         }
     }
 
@@ -138,59 +171,89 @@ impl ResourceMap {
             .map(|x| *x)
     }
 
-    // Use optional nightly support for access to std::intrinsics::type_name
-    #[cfg(feature = "nightly")]
-    fn unwrap_resource<R>(resource: Option<R>) -> R {
-        if resource.is_none() {
-            let name = core::any::type_name::<R>();
-            // Tried to fetch or fetch_mut on a resource that is not registered.
-            panic!("Resource not found: {}", name);
-        }
-
-        resource.unwrap()
-    }
-
-    #[cfg(not(feature = "nightly"))]
-    fn unwrap_resource<R>(resource: Option<R>) -> R {
-        // Tried to fetch or fetch_mut on a resource that is not registered. (Nightly will give better error message)
-        resource.unwrap()
+    #[cfg_attr(debug_assertions, track_caller)]
+    fn unwrap_resource<R: Resource, T>(resource: Option<T>) -> T {
+        // Tried to fetch or fetch_mut on a resource that is not registered.
+        resource.unwrap_or_else(|| panic!("Resource not found: {}", core::any::type_name::<R>()))
     }
 
     /// Read-only fetch of a resource. Trying to get a resource that is not in the map is fatal. Use
     /// try_fetch if unsure whether the resource exists. Requesting read access to a resource that
     /// has any concurrently active writer is fatal.
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn fetch<R: Resource>(&self) -> ReadBorrow<R> {
-        let result = self.try_fetch();
-        Self::unwrap_resource(result)
+        let result = self.try_fetch::<R>();
+        Self::unwrap_resource::<R, _>(result)
     }
 
     /// Read-only fetch of a resource. Requesting read access to a resource that has a concurrently
     /// active writer is fatal. Returns None if the type is not registered.
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn try_fetch<R: Resource>(&self) -> Option<ReadBorrow<R>> {
         let res_id = ResourceId::new::<R>();
 
-        self.resources.get(&res_id).map(|r| ReadBorrow {
-            inner: Ref::map(r.borrow(), Box::as_ref),
+        #[cfg(feature = "checked_access")]
+        checked_access::check_read(&res_id);
+
+        let r = self.resources.get(&res_id)?;
+
+        // Check with the tracker first, it can say who holds the conflicting borrow
+        #[cfg(feature = "borrow_tracking")]
+        let tracked = self.borrow_tracker.track_read(&res_id);
+
+        let borrow = r.try_borrow().unwrap_or_else(|_| {
+            panic!(
+                "Tried to fetch resource {} for reading, but it was already borrowed mutably",
+                res_id.type_name()
+            )
+        });
+
+        Some(ReadBorrow {
+            inner: Ref::map(borrow, Box::as_ref),
             phantom: PhantomData,
+
+            #[cfg(feature = "borrow_tracking")]
+            _tracked: tracked,
         })
     }
 
     /// Read/Write fetch of a resource. Trying to get a resource that is not in the map is fatal. Use
     /// try_fetch if unsure whether the resource exists. Requesting write access to a resource with
     /// any concurrently active read/write is fatal
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn fetch_mut<R: Resource>(&self) -> WriteBorrow<R> {
-        let result = self.try_fetch_mut();
-        Self::unwrap_resource(result)
+        let result = self.try_fetch_mut::<R>();
+        Self::unwrap_resource::<R, _>(result)
     }
 
     /// Read/Write fetch of a resource. Requesting write access to a resource with
     /// any concurrently active read/write is fatal. Returns None if the type is not registered.
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn try_fetch_mut<R: Resource>(&self) -> Option<WriteBorrow<R>> {
         let res_id = ResourceId::new::<R>();
 
-        self.resources.get(&res_id).map(|r| WriteBorrow::<R> {
-            inner: RefMut::map(r.borrow_mut(), Box::as_mut),
+        #[cfg(feature = "checked_access")]
+        checked_access::check_write(&res_id);
+
+        let r = self.resources.get(&res_id)?;
+
+        // Check with the tracker first, it can say who holds the conflicting borrow
+        #[cfg(feature = "borrow_tracking")]
+        let tracked = self.borrow_tracker.track_write(&res_id);
+
+        let borrow = r.try_borrow_mut().unwrap_or_else(|_| {
+            panic!(
+                "Tried to fetch resource {} for writing, but it was already borrowed",
+                res_id.type_name()
+            )
+        });
+
+        Some(WriteBorrow::<R> {
+            inner: RefMut::map(borrow, Box::as_mut),
             phantom: PhantomData,
+
+            #[cfg(feature = "borrow_tracking")]
+            _tracked: tracked,
         })
     }
 
@@ -238,6 +301,7 @@ pub type ReadOption<T> = Option<Read<T>>;
 impl<'a, T: Resource> DataRequirement<'a> for Read<T> {
     type Borrow = ReadBorrow<'a, T>;
 
+    #[cfg_attr(debug_assertions, track_caller)]
     fn fetch(resource_map: &'a ResourceMap) -> Self::Borrow {
         resource_map.fetch::<T>()
     }
@@ -246,6 +310,7 @@ impl<'a, T: Resource> DataRequirement<'a> for Read<T> {
 impl<'a, T: Resource> DataRequirement<'a> for Option<Read<T>> {
     type Borrow = Option<ReadBorrow<'a, T>>;
 
+    #[cfg_attr(debug_assertions, track_caller)]
     fn fetch(resource_map: &'a ResourceMap) -> Self::Borrow {
         resource_map.try_fetch::<T>()
     }
@@ -263,6 +328,7 @@ pub type WriteOption<T> = Option<Write<T>>;
 impl<'a, T: Resource> DataRequirement<'a> for Write<T> {
     type Borrow = WriteBorrow<'a, T>;
 
+    #[cfg_attr(debug_assertions, track_caller)]
     fn fetch(resource_map: &'a ResourceMap) -> Self::Borrow {
         resource_map.fetch_mut::<T>()
     }
@@ -271,6 +337,7 @@ impl<'a, T: Resource> DataRequirement<'a> for Write<T> {
 impl<'a, T: Resource> DataRequirement<'a> for Option<Write<T>> {
     type Borrow = Option<WriteBorrow<'a, T>>;
 
+    #[cfg_attr(debug_assertions, track_caller)]
     fn fetch(resource_map: &'a ResourceMap) -> Self::Borrow {
         resource_map.try_fetch_mut::<T>()
     }
@@ -287,6 +354,10 @@ impl DataBorrow for () {}
 pub struct ReadBorrow<'a, T> {
     inner: Ref<'a, dyn Resource>,
     phantom: PhantomData<&'a T>,
+
+    // Must be dropped after inner
+    #[cfg(feature = "borrow_tracking")]
+    _tracked: TrackedBorrow<'a>,
 }
 
 impl<'a, T> DataBorrow for ReadBorrow<'a, T> {}
@@ -308,6 +379,9 @@ impl<'a, T> Clone for ReadBorrow<'a, T> {
         ReadBorrow {
             inner: self.inner.clone(),
             phantom: PhantomData,
+
+            #[cfg(feature = "borrow_tracking")]
+            _tracked: self._tracked.clone(),
         }
     }
 }
@@ -316,6 +390,10 @@ impl<'a, T> Clone for ReadBorrow<'a, T> {
 pub struct WriteBorrow<'a, T> {
     inner: RefMut<'a, dyn Resource>,
     phantom: PhantomData<&'a mut T>,
+
+    // Must be dropped after inner
+    #[cfg(feature = "borrow_tracking")]
+    _tracked: TrackedBorrow<'a>,
 }
 
 impl<'a, T> DataBorrow for WriteBorrow<'a, T> {}
@@ -404,3 +482,75 @@ mod impl_data {
     impl_data!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y);
     impl_data!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter(u32);
+
+    #[test]
+    fn test_resource_id_debug_name() {
+        let debug = format!("{:?}", ResourceId::new::<Counter>());
+        assert!(debug.contains("Counter"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Resource not found: minimum_base::resource::tests::Counter")]
+    fn test_fetch_missing_resource() {
+        let resource_map = ResourceMap::new();
+        resource_map.fetch::<Counter>();
+    }
+
+    #[test]
+    #[should_panic(expected = "resource minimum_base::resource::tests::Counter for writing")]
+    fn test_fetch_mut_while_borrowed() {
+        let mut resource_map = ResourceMap::new();
+        resource_map.insert(Counter(0));
+
+        let _counter = resource_map.fetch::<Counter>();
+        resource_map.fetch_mut::<Counter>();
+    }
+
+    #[cfg(feature = "borrow_tracking")]
+    #[test]
+    fn test_conflict_names_both_borrowers() {
+        let mut resource_map = ResourceMap::new();
+        resource_map.insert(Counter(0));
+
+        let task_scope = CurrentTaskScope::new("ReadingTask");
+        let (_counter, read_line) = (resource_map.fetch::<Counter>(), line!());
+        drop(task_scope);
+
+        let (result, write_line) = (
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                resource_map.fetch_mut::<Counter>();
+            })),
+            line!() - 2,
+        );
+
+        let error = result.err().unwrap();
+        let message = error.downcast_ref::<String>().unwrap();
+        assert!(message.contains("minimum_base::resource::tests::Counter"));
+        assert!(message.contains(&format!("from {}:{}", file!(), write_line)));
+        assert!(message.contains(&format!(
+            "for reading by task ReadingTask at {}:{}",
+            file!(),
+            read_line
+        )));
+    }
+
+    #[test]
+    fn test_released_borrows_are_forgotten() {
+        let mut resource_map = ResourceMap::new();
+        resource_map.insert(Counter(0));
+
+        {
+            let counter = resource_map.fetch::<Counter>();
+            let _counter_clone = counter.clone();
+        }
+
+        resource_map.fetch_mut::<Counter>().0 += 1;
+        assert_eq!(resource_map.fetch::<Counter>().0, 1);
+    }
+}
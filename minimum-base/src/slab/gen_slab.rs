@@ -8,7 +8,7 @@ use std::marker::PhantomData;
 //TODO: Seems like this could be rewritten to use RawSlab internally?
 
 /// A key to use with a GenSlab. Internally, it holds an element index and a generation index
-#[derive(Copy, Eq)]
+#[derive(Copy)]
 pub struct GenSlabKey<T> {
     /// Raw index to location within the slab
     index: SlabIndexT,
@@ -51,6 +51,9 @@ impl<T> PartialEq for GenSlabKey<T> {
     }
 }
 
+// Implemented by hand so that T doesn't need to be Eq
+impl<T> Eq for GenSlabKey<T> {}
+
 impl<T> std::hash::Hash for GenSlabKey<T> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.index.hash(state);
@@ -3,6 +3,9 @@ use std::prelude::v1::*;
 use super::SlabIndexT;
 use std::marker::PhantomData;
 
+#[cfg(feature = "std")]
+use rayon::prelude::*;
+
 #[derive(Copy, Clone)]
 /// A key to a value in a RawSlab
 pub struct RawSlabKey<T: Sized> {
@@ -121,6 +124,38 @@ impl<T> RawSlab<T> {
             .map(|(index, value)| (RawSlabKey::new(index as u32), value.as_mut().unwrap()))
     }
 
+    /// Iterate all values in parallel. The storage is split into chunks across rayon's thread pool.
+    #[cfg(feature = "std")]
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (RawSlabKey<T>, &T)>
+    where
+        T: Send + Sync,
+    {
+        self.storage
+            .par_iter()
+            .enumerate()
+            .filter_map(|(index, value)| {
+                value
+                    .as_ref()
+                    .map(|value| (RawSlabKey::new(index as u32), value))
+            })
+    }
+
+    /// Iterate all values mutably in parallel
+    #[cfg(feature = "std")]
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = (RawSlabKey<T>, &mut T)>
+    where
+        T: Send + Sync,
+    {
+        self.storage
+            .par_iter_mut()
+            .enumerate()
+            .filter_map(|(index, value)| {
+                value
+                    .as_mut()
+                    .map(|value| (RawSlabKey::new(index as u32), value))
+            })
+    }
+
     /// Return count of allocated Ts
     pub fn count(&self) -> usize {
         self.storage.len() - self.free_list.len()
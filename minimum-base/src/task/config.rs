@@ -1,12 +1,20 @@
 use std::prelude::v1::*;
 
+use super::ContextFlag;
+use super::DataRequirement;
 use super::Phase;
 use super::RegisteredType;
+use super::RequiresResources;
 use super::ResourceId;
+use super::ResourceMap;
 use super::Task;
+use super::TaskContextFlags;
 use super::TaskContextFlagsFilter;
 use super::TaskFactory;
 
+// A predicate registered with TaskConfig::run_if(), with its resources already fetched
+pub(super) type RunCriteria = Box<dyn Fn(&TaskContextFlags, &ResourceMap) -> bool + Send + Sync>;
+
 /// Used internally to list all of a task's requirements
 /// Passed into a task's "configure" function to accumulate their settings
 #[derive(Derivative)]
@@ -20,6 +28,12 @@ pub struct TaskConfig {
     pub(super) require_run_after: Vec<RegisteredType>,
     pub(super) require_run_during: Vec<RegisteredType>,
     pub(super) context_flags_filter: TaskContextFlagsFilter,
+    #[derivative(Debug = "ignore")]
+    pub(super) run_criteria: Vec<RunCriteria>,
+
+    // Set when building the dependency list if the task runs during a fixed timestep phase
+    pub(super) fixed_timestep_phase: Option<RegisteredType>,
+
     #[derivative(Debug = "ignore")]
     pub(super) task: Option<Box<dyn Task>>,
 }
@@ -35,6 +49,8 @@ impl TaskConfig {
             require_run_after: vec![],
             require_run_during: vec![],
             context_flags_filter: TaskContextFlagsFilter::default(),
+            run_criteria: vec![],
+            fixed_timestep_phase: None,
             task,
         }
     }
@@ -100,4 +116,76 @@ impl TaskConfig {
     pub fn skip_if(&mut self, required_flags: usize) {
         self.context_flags_filter.skip_if(required_flags);
     }
+
+    /// Only run the task if the flag is set. The flag must be added to the world.
+    pub fn run_only_if_flag<F: ContextFlag>(&mut self) {
+        self.context_flags_filter.run_only_if_flag::<F>();
+    }
+
+    /// Skip the task if the flag is set. The flag must be added to the world.
+    pub fn skip_if_flag<F: ContextFlag>(&mut self) {
+        self.context_flags_filter.skip_if_flag::<F>();
+    }
+
+    /// Only run the task if the predicate returns true. The predicate is given read-only access to
+    /// the resources in `R` (i.e. `(Read<InputState>,)`), and those reads are added to the task's
+    /// reads. If a task has several predicates and/or context flag filters, all of them must pass.
+    ///
+    /// Panics if `R` requires write access to anything.
+    pub fn run_if<R, F>(&mut self, predicate: F)
+    where
+        R: for<'a> DataRequirement<'a> + RequiresResources<ResourceId> + 'static,
+        F: for<'a> Fn(&TaskContextFlags, <R as DataRequirement<'a>>::Borrow) -> bool
+            + Send
+            + Sync
+            + 'static,
+    {
+        assert!(
+            R::writes().is_empty(),
+            "Run criteria may only read resources, but {} requires write access",
+            core::any::type_name::<R>()
+        );
+
+        for read in R::reads() {
+            self.add_read(read);
+        }
+
+        self.run_criteria
+            .push(Box::new(move |context_flags, resource_map| {
+                predicate(context_flags, R::fetch(resource_map))
+            }));
+    }
+
+    /// Returns true if the two tasks can't safely run at the same time
+    pub(super) fn conflicts_with(&self, other: &TaskConfig) -> bool {
+        // Tasks that write all take a mutable borrow on the whole resource map, which conflicts
+        // with every other task (even ones that don't fetch anything)
+        if self.write_all || other.write_all {
+            return true;
+        }
+
+        if self.read_all && !other.writes.is_empty() {
+            return true;
+        }
+
+        if other.read_all && !self.writes.is_empty() {
+            return true;
+        }
+
+        // A task that reads all may still declare writes, so check those below as well
+
+        for write in &self.writes {
+            if other.writes.contains(write) || other.reads.contains(write) {
+                return true;
+            }
+        }
+
+        for write in &other.writes {
+            if self.reads.contains(write) {
+                return true;
+            }
+        }
+
+        false
+    }
 }
@@ -0,0 +1,304 @@
+use std::prelude::v1::*;
+
+use core::marker::PhantomData;
+
+use super::RegisteredType;
+
+/// Maximum number of context flags that can be registered, one per bit of `TaskContextFlags`
+pub const MAX_CONTEXT_FLAGS: usize = core::mem::size_of::<usize>() * 8;
+
+/// Implement on a marker type to declare a context flag (i.e. `struct PlayModePlaying;`). Flags must
+/// be added to the `WorldBuilder` with `add_context_flag`, which allocates a bit for them. Because
+/// the bit is chosen when the world is built, flags declared in different crates can't collide.
+pub trait ContextFlag: 'static {}
+
+/// A typed reference to the bit that was allocated for a context flag
+pub struct ContextFlagHandle<F: ContextFlag> {
+    bits: usize,
+    phantom_data: PhantomData<fn() -> F>,
+}
+
+impl<F: ContextFlag> ContextFlagHandle<F> {
+    /// The bit allocated for the flag
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    pub fn name(&self) -> &'static str {
+        core::any::type_name::<F>()
+    }
+
+    /// Returns true if the flag is set in the given raw flags
+    pub fn is_set(&self, flags: usize) -> bool {
+        flags & self.bits != 0
+    }
+}
+
+impl<F: ContextFlag> Clone for ContextFlagHandle<F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<F: ContextFlag> Copy for ContextFlagHandle<F> {}
+
+impl<F: ContextFlag> core::fmt::Debug for ContextFlagHandle<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "ContextFlagHandle({}, {:#x})", self.name(), self.bits)
+    }
+}
+
+/// All context flags that were added to the world, and the bit allocated for each one. Bits are
+/// allocated from the lowest bit up, so hand-assigned raw flags should not be mixed with registered
+/// ones.
+#[derive(Default, Debug, Clone)]
+pub struct ContextFlagRegistry {
+    // Indexed by the bit allocated for the flag
+    flags: Vec<RegisteredType>,
+}
+
+impl ContextFlagRegistry {
+    pub fn new() -> Self {
+        ContextFlagRegistry { flags: vec![] }
+    }
+
+    /// Allocates a bit for the flag. Registering the same flag twice returns the same bit. Panics if
+    /// more than `MAX_CONTEXT_FLAGS` flags are registered.
+    pub fn register<F: ContextFlag>(&mut self) -> ContextFlagHandle<F> {
+        if let Some(handle) = self.handle::<F>() {
+            return handle;
+        }
+
+        assert!(
+            self.flags.len() < MAX_CONTEXT_FLAGS,
+            "Could not register context flag {}, only {} context flags may be registered",
+            core::any::type_name::<F>(),
+            MAX_CONTEXT_FLAGS
+        );
+
+        self.flags.push(RegisteredType::of::<F>());
+        ContextFlagHandle {
+            bits: 1 << (self.flags.len() - 1),
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Registers every flag from the other registry that isn't already registered here. Flags that
+    /// were already registered keep their bits.
+    pub fn extend(&mut self, other: &ContextFlagRegistry) {
+        for flag in &other.flags {
+            if self.bits_of(flag).is_none() {
+                assert!(
+                    self.flags.len() < MAX_CONTEXT_FLAGS,
+                    "Could not register context flag {}, only {} context flags may be registered",
+                    flag.type_name(),
+                    MAX_CONTEXT_FLAGS
+                );
+
+                self.flags.push(flag.clone());
+            }
+        }
+    }
+
+    /// Returns the handle for the flag, or None if it was never registered
+    pub fn handle<F: ContextFlag>(&self) -> Option<ContextFlagHandle<F>> {
+        self.bits_of(&RegisteredType::of::<F>())
+            .map(|bits| ContextFlagHandle {
+                bits,
+                phantom_data: PhantomData,
+            })
+    }
+
+    /// Returns the bit allocated for the flag, or None if it was never registered
+    pub fn bits_of(&self, flag: &RegisteredType) -> Option<usize> {
+        self.flags
+            .iter()
+            .position(|registered| registered == flag)
+            .map(|index| 1 << index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.flags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    /// Iterates the name and bit of every registered flag, in the order they were registered
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.flags
+            .iter()
+            .enumerate()
+            .map(|(index, flag)| (flag.type_name(), 1 << index))
+    }
+
+    /// Names of the registered flags that are set in the given raw flags
+    pub fn names(&self, flags: usize) -> Vec<&'static str> {
+        self.iter()
+            .filter(|(_name, bits)| flags & bits != 0)
+            .map(|(name, _bits)| name)
+            .collect()
+    }
+
+    // Any bits that are set but weren't allocated for a registered flag
+    pub(super) fn unregistered_bits(&self, flags: usize) -> usize {
+        if self.flags.len() >= MAX_CONTEXT_FLAGS {
+            0
+        } else {
+            flags & !((1 << self.flags.len()) - 1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{DataRequirement, Write};
+    use crate::task::{TaskConfig, TaskContextFlags, TaskDependencyListBuildError};
+    use crate::{
+        DispatchControl, ResourceTask, ResourceTaskImpl, TaskDependencyListBuilder, WorldBuilder,
+    };
+
+    struct FlagA;
+    impl ContextFlag for FlagA {}
+
+    struct FlagB;
+    impl ContextFlag for FlagB {}
+
+    struct FlagC;
+    impl ContextFlag for FlagC {}
+
+    struct RunCount(u32);
+
+    // Runs if A is set, unless B is also set
+    struct RunIfA;
+    type RunIfATask = ResourceTask<RunIfA>;
+    impl ResourceTaskImpl for RunIfA {
+        type RequiredResources = (Write<RunCount>,);
+
+        fn configure(config: &mut TaskConfig) {
+            config.run_only_if_flag::<FlagA>();
+            config.skip_if_flag::<FlagB>();
+        }
+
+        fn run(
+            context_flags: &TaskContextFlags,
+            data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+            assert!(context_flags.is_set::<FlagA>());
+            assert!(!context_flags.is_set::<FlagB>());
+
+            let (mut run_count,) = data;
+            run_count.0 += 1;
+        }
+    }
+
+    struct FrameCount(u32);
+
+    // Changes the flags for the next frame: none, then A, then A and B, then B
+    struct SetFlags;
+    type SetFlagsTask = ResourceTask<SetFlags>;
+    impl ResourceTaskImpl for SetFlags {
+        type RequiredResources = (Write<FrameCount>, Write<DispatchControl>);
+
+        fn configure(_config: &mut TaskConfig) {}
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+            let (mut frame_count, mut dispatch_control) = data;
+            frame_count.0 += 1;
+
+            let (a, b) = match frame_count.0 {
+                1 => (true, false),
+                2 => (true, true),
+                _ => (false, true),
+            };
+
+            dispatch_control.set_context_flag_to::<FlagA>(a);
+            dispatch_control.set_context_flag_to::<FlagB>(b);
+        }
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = ContextFlagRegistry::new();
+        let a = registry.register::<FlagA>();
+        let b = registry.register::<FlagB>();
+        assert_eq!(a.bits(), 1);
+        assert_eq!(b.bits(), 2);
+
+        // Registering again returns the same bit
+        assert_eq!(registry.register::<FlagA>().bits(), 1);
+        assert_eq!(registry.len(), 2);
+
+        assert!(registry.handle::<FlagC>().is_none());
+        assert_eq!(
+            registry.names(a.bits() | 8),
+            vec![core::any::type_name::<FlagA>()]
+        );
+        assert_eq!(registry.unregistered_bits(a.bits() | 8), 8);
+    }
+
+    #[test]
+    fn test_filter_by_flag() {
+        let mut update_loop = WorldBuilder::new()
+            .with_resource(RunCount(0))
+            .with_resource(FrameCount(0))
+            .with_context_flag::<FlagA>()
+            .with_context_flag::<FlagB>()
+            .with_task::<RunIfATask>()
+            .with_task::<SetFlagsTask>()
+            .build_update_loop_single_threaded(0);
+
+        for _ in 0..4 {
+            update_loop.step();
+        }
+
+        let resource_map = update_loop.into_resource_map();
+        assert_eq!(resource_map.fetch::<RunCount>().0, 1);
+    }
+
+    #[test]
+    fn test_unregistered_flag() {
+        let mut builder = TaskDependencyListBuilder::new();
+        builder.add_context_flag::<FlagA>();
+        builder.add_task::<RunIfATask>();
+
+        match builder.try_build() {
+            Err(TaskDependencyListBuildError::UnregisteredContextFlag { task, flag }) => {
+                assert_eq!(task, core::any::type_name::<RunIfATask>());
+                assert_eq!(flag, core::any::type_name::<FlagB>());
+            }
+            _ => panic!("Expected an unregistered context flag error"),
+        }
+    }
+
+    #[test]
+    fn test_describe_active_flags() {
+        let mut registry = ContextFlagRegistry::new();
+        let a = registry.register::<FlagA>();
+        registry.register::<FlagB>();
+        let c = registry.register::<FlagC>();
+
+        let context_flags =
+            TaskContextFlags::with_registry(a.bits() | c.bits() | 64, registry.into());
+        assert_eq!(
+            context_flags.active_flag_names(),
+            vec![
+                core::any::type_name::<FlagA>(),
+                core::any::type_name::<FlagC>()
+            ]
+        );
+        assert_eq!(
+            format!("{:?}", context_flags),
+            format!(
+                "TaskContextFlags[{}, {}, 0x40]",
+                core::any::type_name::<FlagA>(),
+                core::any::type_name::<FlagC>()
+            )
+        );
+    }
+}
@@ -1,5 +1,11 @@
 use std::prelude::v1::*;
 
+use std::sync::Arc;
+
+use super::config::RunCriteria;
+use super::ContextFlag;
+use super::ContextFlagRegistry;
+use super::RegisteredType;
 use super::ResourceMap;
 use super::Task;
 use super::TaskConfig;
@@ -8,22 +14,78 @@ use super::TrustCell;
 /// Passed by ref to all tasks in a single update step
 pub struct TaskContextFlags {
     flags: usize,
+    registry: Arc<ContextFlagRegistry>,
 }
 
 impl TaskContextFlags {
+    /// Raw flags, without a registry. Typed flags can't be checked with `is_set()`.
     pub fn new(flags: usize) -> Self {
-        TaskContextFlags { flags }
+        Self::with_registry(flags, Arc::new(ContextFlagRegistry::new()))
+    }
+
+    pub fn with_registry(flags: usize, registry: Arc<ContextFlagRegistry>) -> Self {
+        TaskContextFlags { flags, registry }
     }
 
     pub fn flags(&self) -> usize {
         self.flags
     }
+
+    /// Returns true if the flag is set. Panics if the flag was never added to the world.
+    pub fn is_set<F: ContextFlag>(&self) -> bool {
+        match self.registry.handle::<F>() {
+            Some(handle) => handle.is_set(self.flags),
+            None => panic!(
+                "Context flag {} was never added to the world",
+                core::any::type_name::<F>()
+            ),
+        }
+    }
+
+    pub fn registry(&self) -> &ContextFlagRegistry {
+        &self.registry
+    }
+
+    /// Names of the registered flags that are set this frame
+    pub fn active_flag_names(&self) -> Vec<&'static str> {
+        self.registry.names(self.flags)
+    }
+}
+
+// Lists the flags by name, with any bits that don't belong to a registered flag in hex
+impl core::fmt::Debug for TaskContextFlags {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "TaskContextFlags[")?;
+
+        let mut first = true;
+        for name in self.active_flag_names() {
+            if !first {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", name)?;
+            first = false;
+        }
+
+        let unregistered_bits = self.registry.unregistered_bits(self.flags);
+        if unregistered_bits != 0 {
+            if !first {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:#x}", unregistered_bits)?;
+        }
+
+        write!(f, "]")
+    }
 }
 
 #[derive(Default, Debug)]
 pub struct TaskContextFlagsFilter {
     run_only_if: usize,
     skip_if: usize,
+
+    // Typed flags, converted to bits when the schedule is built
+    run_only_if_flags: Vec<RegisteredType>,
+    skip_if_flags: Vec<RegisteredType>,
 }
 
 impl TaskContextFlagsFilter {
@@ -35,7 +97,30 @@ impl TaskContextFlagsFilter {
         self.skip_if |= flags;
     }
 
+    pub fn run_only_if_flag<F: ContextFlag>(&mut self) {
+        self.run_only_if_flags.push(RegisteredType::of::<F>());
+    }
+
+    pub fn skip_if_flag<F: ContextFlag>(&mut self) {
+        self.skip_if_flags.push(RegisteredType::of::<F>());
+    }
+
+    // Converts typed flags to the bits allocated for them. Returns the name of the first flag that
+    // wasn't registered.
+    pub(super) fn resolve(&mut self, registry: &ContextFlagRegistry) -> Result<(), &'static str> {
+        for flag in self.run_only_if_flags.drain(..) {
+            self.run_only_if |= registry.bits_of(&flag).ok_or(flag.type_name())?;
+        }
+
+        for flag in self.skip_if_flags.drain(..) {
+            self.skip_if |= registry.bits_of(&flag).ok_or(flag.type_name())?;
+        }
+
+        Ok(())
+    }
+
     pub fn check_filter(&self, context_flags: &TaskContextFlags) -> bool {
+        debug_assert!(self.run_only_if_flags.is_empty() && self.skip_if_flags.is_empty());
         return (self.run_only_if & context_flags.flags) == self.run_only_if
             && (self.skip_if & context_flags.flags) == 0;
     }
@@ -44,23 +129,216 @@ impl TaskContextFlagsFilter {
 pub struct TaskWithFilter {
     task: Box<dyn Task>,
     context_flags_filter: TaskContextFlagsFilter,
+
+    // Predicates that must all pass for the task to run, checked after the context flags
+    run_criteria: Vec<RunCriteria>,
+
+    // The phase the task runs during, if any. Used to group task timings by phase
+    phase: Option<&'static str>,
+
+    // If set, the task may run several times per frame along with the rest of the phase
+    fixed_timestep_phase: Option<RegisteredType>,
+
+    // What the task declared it will fetch, enforced while the task runs
+    #[cfg(feature = "checked_access")]
+    access: std::sync::Arc<crate::resource::ResourceAccess>,
 }
 
 impl TaskWithFilter {
     pub fn new(task_config: TaskConfig) -> Self {
+        #[cfg(feature = "checked_access")]
+        let access = std::sync::Arc::new(crate::resource::ResourceAccess {
+            read_all: task_config.read_all,
+            write_all: task_config.write_all,
+            reads: task_config.reads,
+            writes: task_config.writes,
+        });
+
+        let phase = task_config
+            .require_run_during
+            .first()
+            .map(|phase| phase.type_name());
+
         TaskWithFilter {
             task: task_config.task.unwrap(),
             context_flags_filter: task_config.context_flags_filter,
+            run_criteria: task_config.run_criteria,
+            phase,
+            fixed_timestep_phase: task_config.fixed_timestep_phase,
+
+            #[cfg(feature = "checked_access")]
+            access,
         }
     }
 
+    pub fn name(&self) -> &'static str {
+        self.task.name()
+    }
+
+    pub fn phase(&self) -> Option<&'static str> {
+        self.phase
+    }
+
+    pub(super) fn fixed_timestep_phase(&self) -> Option<&RegisteredType> {
+        self.fixed_timestep_phase.as_ref()
+    }
+
+    /// Runs the task if the context flags pass its filter and all of its run criteria pass. Returns
+    /// true if the task ran.
     pub fn run_if_filter_passes(
         &self,
         context_flags: &TaskContextFlags,
         resource_map: &TrustCell<ResourceMap>,
-    ) {
-        if self.context_flags_filter.check_filter(context_flags) {
-            self.task.run(context_flags, resource_map);
+    ) -> bool {
+        if !self.context_flags_filter.check_filter(context_flags) {
+            return false;
         }
+
+        // Lets the resource map say which task holds a borrow if there is a conflict
+        #[cfg(feature = "borrow_tracking")]
+        let _current_task = crate::resource::CurrentTaskScope::new(self.task.name());
+
+        // Anything the task fetches that it didn't declare will panic
+        #[cfg(feature = "checked_access")]
+        let _access_scope =
+            crate::resource::ResourceAccessScope::new(self.task.name(), self.access.clone());
+
+        // The borrow on the resource map must end before the task runs, since the task might need
+        // to borrow it mutably
+        let run_criteria_pass = self.run_criteria.is_empty() || {
+            let resource_map = resource_map.borrow();
+            self.run_criteria
+                .iter()
+                .all(|run_criteria| run_criteria(context_flags, &resource_map))
+        };
+
+        if !run_criteria_pass {
+            return false;
+        }
+
+        self.task.run(context_flags, resource_map);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{DataRequirement, Read, ResourceId, Write};
+    use crate::{ResourceTask, ResourceTaskImpl, WorldBuilder};
+
+    struct FrameCount(u32);
+    struct Enabled(bool);
+    struct RunCount(u32);
+
+    struct CountFrames;
+    type CountFramesTask = ResourceTask<CountFrames>;
+    impl ResourceTaskImpl for CountFrames {
+        type RequiredResources = (Write<FrameCount>,);
+
+        fn configure(_config: &mut TaskConfig) {}
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+            let (mut frame_count,) = data;
+            frame_count.0 += 1;
+        }
+    }
+
+    // Runs every other frame, and only if enabled and the context flags allow it
+    struct EveryOtherFrame;
+    type EveryOtherFrameTask = ResourceTask<EveryOtherFrame>;
+    impl ResourceTaskImpl for EveryOtherFrame {
+        type RequiredResources = (Write<RunCount>,);
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_uses_data_from::<CountFramesTask>();
+            config.run_only_if(1);
+            config.run_if::<(Read<FrameCount>,), _>(|_context_flags, (frame_count,)| {
+                frame_count.0 % 2 == 0
+            });
+            config.run_if::<(Read<Enabled>,), _>(|_context_flags, (enabled,)| enabled.0);
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+            let (mut run_count,) = data;
+            run_count.0 += 1;
+        }
+    }
+
+    struct WritingRunCriteria;
+    type WritingRunCriteriaTask = ResourceTask<WritingRunCriteria>;
+    impl ResourceTaskImpl for WritingRunCriteria {
+        type RequiredResources = ();
+
+        fn configure(config: &mut TaskConfig) {
+            config.run_if::<(Write<FrameCount>,), _>(|_context_flags, _data| true);
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            _data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+        }
+    }
+
+    fn run_frames(enabled: bool, context_flags: usize) -> u32 {
+        let mut update_loop = WorldBuilder::new()
+            .with_resource(FrameCount(0))
+            .with_resource(Enabled(enabled))
+            .with_resource(RunCount(0))
+            .with_task::<CountFramesTask>()
+            .with_task::<EveryOtherFrameTask>()
+            .build_update_loop_multi_threaded(context_flags);
+
+        for _ in 0..6 {
+            update_loop.step();
+        }
+
+        let resource_map = update_loop.into_resource_map();
+        let run_count = resource_map.fetch::<RunCount>().0;
+        run_count
+    }
+
+    #[test]
+    fn test_run_criteria() {
+        assert_eq!(run_frames(true, 1), 3);
+    }
+
+    #[test]
+    fn test_all_run_criteria_must_pass() {
+        assert_eq!(run_frames(false, 1), 0);
+    }
+
+    #[test]
+    fn test_context_flags_must_pass() {
+        assert_eq!(run_frames(true, 0), 0);
+    }
+
+    #[test]
+    fn test_run_criteria_reads_are_declared() {
+        let world = WorldBuilder::new()
+            .with_resource(FrameCount(0))
+            .with_resource(Enabled(true))
+            .with_resource(RunCount(0))
+            .with_task::<CountFramesTask>()
+            .with_task::<EveryOtherFrameTask>()
+            .build();
+
+        let config = &world.task_list.execution_order[1];
+        assert!(config.reads.contains(&ResourceId::new::<FrameCount>()));
+        assert!(config.reads.contains(&ResourceId::new::<Enabled>()));
+        assert!(config.writes.contains(&ResourceId::new::<RunCount>()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Run criteria may only read resources")]
+    fn test_run_criteria_cant_write() {
+        WorldBuilder::new().with_task::<WritingRunCriteriaTask>();
     }
 }
@@ -1,9 +1,26 @@
 use std::prelude::v1::*;
 
 use hashbrown::HashMap;
+use hashbrown::HashSet;
 
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use std::fmt::{Display, Error as FormatError, Formatter};
+
+use std::sync::Arc;
+
+use super::ContextFlag;
+use super::ContextFlagHandle;
+use super::ContextFlagRegistry;
+use super::FnTask;
+use super::FnTaskFunction;
 use super::Phase;
 use super::RegisteredType;
+use super::ScheduleDescription;
+use super::ScheduleEdge;
+use super::ScheduleEdgeReason;
+use super::ScheduledTaskDescription;
 use super::TaskConfig;
 use super::TaskFactory;
 
@@ -13,46 +30,231 @@ use super::TaskFactory;
 // Process ordered tasks to produce a multi-thread friendly execution path
 //
 
-/// Used to construct a task schedule
+/// Reasons that `TaskDependencyListBuilder::try_build()` could not produce an ordering
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskDependencyListBuildError {
+    /// A task or phase must run before/after/during something that was never added (for example, a
+    /// phase that was not registered with `add_phase`)
+    MissingDependency {
+        task: &'static str,
+        dependency: &'static str,
+    },
+
+    /// The ordering requirements form a cycle. Each entry must run before the next one, and the
+    /// last entry must run before the first.
+    Cycle { cycle: Vec<&'static str> },
+
+    /// Tasks during a fixed timestep phase must run one after the other so that the phase can be
+    /// repeated, but the task depends on something outside the phase that depends on the phase
+    FixedTimestepPhaseSplit {
+        phase: &'static str,
+        task: &'static str,
+    },
+
+    /// A task filters on a context flag that was never registered with `add_context_flag`
+    UnregisteredContextFlag {
+        task: &'static str,
+        flag: &'static str,
+    },
+}
+
+impl Display for TaskDependencyListBuildError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FormatError> {
+        match self {
+            TaskDependencyListBuildError::MissingDependency { task, dependency } => write!(
+                f,
+                "{} must run before/after/during {}, but it was never added",
+                task, dependency
+            ),
+            TaskDependencyListBuildError::Cycle { cycle } => {
+                write!(f, "Task dependency cycle detected: ")?;
+                for task in cycle {
+                    write!(f, "{} -> ", task)?;
+                }
+                write!(f, "{}", cycle[0])
+            }
+            TaskDependencyListBuildError::FixedTimestepPhaseSplit { phase, task } => write!(
+                f,
+                "Tasks during fixed timestep phase {} must run together, but {} is waiting on a task outside of the phase",
+                phase, task
+            ),
+            TaskDependencyListBuildError::UnregisteredContextFlag { task, flag } => write!(
+                f,
+                "{} filters on context flag {}, but it was never added",
+                task, flag
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for TaskDependencyListBuildError {}
+
+// Creates the config for a task or phase that was added to the builder, including a new instance of
+// the task. Called every time the builder is built so that it can be built more than once.
+type TaskRegistration = Arc<dyn Fn() -> TaskConfig + Send + Sync>;
+
+/// Used to construct a task schedule. The builder can be cloned and built again with tasks/phases
+/// added or removed, i.e. to change the schedule of a running update loop.
 pub struct TaskDependencyListBuilder {
+    registrations: HashMap<RegisteredType, TaskRegistration>,
+    fixed_timestep_phases: HashSet<RegisteredType>,
+    context_flags: ContextFlagRegistry,
+
+    // Configs that haven't been built yet. Tasks are configured as soon as they're added, and any
+    // that are missing (i.e. after cloning) are recreated from the registrations when building.
     tasks: HashMap<RegisteredType, TaskConfig>,
 }
 
+impl Clone for TaskDependencyListBuilder {
+    fn clone(&self) -> Self {
+        TaskDependencyListBuilder {
+            registrations: self.registrations.clone(),
+            fixed_timestep_phases: self.fixed_timestep_phases.clone(),
+            context_flags: self.context_flags.clone(),
+            tasks: HashMap::default(),
+        }
+    }
+}
+
 impl TaskDependencyListBuilder {
     /// Create an empty schedule
     pub fn new() -> Self {
         TaskDependencyListBuilder {
+            registrations: HashMap::default(),
+            fixed_timestep_phases: HashSet::default(),
+            context_flags: ContextFlagRegistry::new(),
             tasks: HashMap::default(),
         }
     }
 
     /// Add a task to be scheduled when `build()` is called
     pub fn add_task<T: TaskFactory>(&mut self) {
-        let mut task_config = TaskConfig::new(Some(T::create()));
-        let registered_type = RegisteredType::of::<T>();
-        T::configure(&mut task_config);
+        self.register(
+            RegisteredType::of::<T>(),
+            Arc::new(|| {
+                let mut task_config = TaskConfig::new(Some(T::create()));
+                T::configure(&mut task_config);
+                task_config
+            }),
+        );
+    }
 
-        //println!("{:?}: {:?}", registered_type, task_config);
-        self.tasks.insert(registered_type, task_config);
+    /// Add a function as a task to be scheduled when `build()` is called. The task's reads/writes are
+    /// inferred from the function's arguments, and anything else (i.e. which phase it runs during)
+    /// can be set by `configure`. Each function can only be added once.
+    pub fn add_fn_task<Args, F, C>(&mut self, function: F, configure: C)
+    where
+        F: FnTaskFunction<Args> + Clone,
+        Args: 'static,
+        C: Fn(&mut TaskConfig) + Send + Sync + 'static,
+    {
+        self.register(
+            RegisteredType::of::<F>(),
+            Arc::new(move || {
+                let task = FnTask::new(function.clone());
+                let mut task_config = TaskConfig::new(Some(Box::new(task)));
+                configure(&mut task_config);
+                FnTask::<F, Args>::configure(&mut task_config);
+                task_config
+            }),
+        );
     }
 
     /// Add a phase to be scheduled when `build()` is called
     pub fn add_phase<T: Phase>(&mut self) {
-        let mut task_config = TaskConfig::new(None);
+        self.register(
+            RegisteredType::of::<T>(),
+            Arc::new(|| {
+                let mut task_config = TaskConfig::new(None);
+                T::configure(&mut task_config);
+                task_config
+            }),
+        );
+    }
+
+    /// Add a phase whose tasks run zero or more times per frame, depending on how much time has
+    /// been added to the `FixedTimestep` resource. Can also be used to mark a phase that was already
+    /// added.
+    pub fn add_fixed_timestep_phase<T: Phase>(&mut self) {
+        self.add_phase::<T>();
+        self.fixed_timestep_phases.insert(RegisteredType::of::<T>());
+    }
+
+    /// Remove a task that was added. Returns false if it wasn't added.
+    pub fn remove_task<T: TaskFactory>(&mut self) -> bool {
+        self.unregister(&RegisteredType::of::<T>())
+    }
+
+    /// Remove a function task that was added. Returns false if it wasn't added.
+    pub fn remove_fn_task<Args, F>(&mut self, _function: F) -> bool
+    where
+        F: FnTaskFunction<Args>,
+    {
+        self.unregister(&RegisteredType::of::<F>())
+    }
+
+    /// Remove a phase that was added. Any tasks that run relative to it must be removed too, or the
+    /// builder will fail to build. Returns false if it wasn't added.
+    pub fn remove_phase<T: Phase>(&mut self) -> bool {
         let registered_type = RegisteredType::of::<T>();
-        T::configure(&mut task_config);
+        self.fixed_timestep_phases.remove(&registered_type);
+        self.unregister(&registered_type)
+    }
 
-        //println!("{:?}: {:?}", registered_type, task_config);
-        self.tasks.insert(registered_type, task_config);
+    /// Returns true if the task or phase was added
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.registrations.contains_key(&RegisteredType::of::<T>())
+    }
+
+    /// Allocate a bit for a context flag so that tasks can filter on it. Adding the same flag twice
+    /// returns the same handle.
+    pub fn add_context_flag<F: ContextFlag>(&mut self) -> ContextFlagHandle<F> {
+        self.context_flags.register::<F>()
+    }
+
+    /// The context flags that were added so far
+    pub fn context_flag_registry(&self) -> &ContextFlagRegistry {
+        &self.context_flags
+    }
+
+    // Used to share one set of flags between all the schedules in a world
+    pub(crate) fn set_context_flag_registry(&mut self, context_flags: ContextFlagRegistry) {
+        self.context_flags = context_flags;
     }
 
     /// Examine all configuration for the task factories/phases that were added and try to produce
-    /// an update ordering that satisfies all requirements
-    pub fn build(mut self) -> TaskDependencyList {
+    /// an update ordering that satisfies all requirements. Panics if the requirements can't be
+    /// satisfied, see `try_build()`
+    pub fn build(self) -> TaskDependencyList {
+        match self.try_build() {
+            Ok(task_list) => task_list,
+            Err(e) => panic!("Could not produce schedule: {}", e),
+        }
+    }
+
+    /// Examine all configuration for the task factories/phases that were added and try to produce
+    /// an update ordering that satisfies all requirements. Returns an error if something is
+    /// required to run relative to a task/phase that wasn't added, or if the requirements form a
+    /// cycle
+    pub fn try_build(mut self) -> Result<TaskDependencyList, TaskDependencyListBuildError> {
+        for (registered_type, registration) in &self.registrations {
+            if !self.tasks.contains_key(registered_type) {
+                self.tasks.insert(registered_type.clone(), registration());
+            }
+        }
+
+        self.check_for_missing_dependencies()?;
+        self.resolve_context_flags()?;
+        self.assign_fixed_timestep_phases();
+
         // Contains dependences from before/after rules
         // i.e. Task A depends on tasks X, Y, Z finishing
         let mut before_after_dependencies = HashMap::<RegisteredType, Vec<RegisteredType>>::new();
 
+        // Every ordering requirement along with the reason it exists, kept for describe()
+        let mut edges = vec![];
+
         // Populate before_after_dependencies with require_run_before requirements
         for (task, config) in &self.tasks {
             let entry = before_after_dependencies
@@ -61,6 +263,11 @@ impl TaskDependencyListBuilder {
             for before_task in &config.require_run_before {
                 // task depends on before_task finishing
                 entry.push(before_task.clone());
+                edges.push(ScheduleEdge::new(
+                    before_task,
+                    task,
+                    ScheduleEdgeReason::After,
+                ));
             }
         }
 
@@ -72,6 +279,11 @@ impl TaskDependencyListBuilder {
                     .entry(after_task.clone())
                     .or_insert(vec![])
                     .push(task.clone());
+                edges.push(ScheduleEdge::new(
+                    task,
+                    after_task,
+                    ScheduleEdgeReason::Before,
+                ));
             }
         }
 
@@ -93,6 +305,11 @@ impl TaskDependencyListBuilder {
                         .entry(task.clone())
                         .or_insert(vec![])
                         .push(phase_dependency.clone());
+                    edges.push(ScheduleEdge::new(
+                        phase_dependency,
+                        task,
+                        ScheduleEdgeReason::During,
+                    ));
                 }
 
                 // during_phase depends on the task that executes within it
@@ -100,9 +317,17 @@ impl TaskDependencyListBuilder {
                     .entry(during_phase.clone())
                     .or_insert(vec![])
                     .push(task.clone());
+                edges.push(ScheduleEdge::new(
+                    task,
+                    during_phase,
+                    ScheduleEdgeReason::During,
+                ));
             }
         }
 
+        edges.sort();
+        edges.dedup();
+
         // Make sure every task is in the hashmap, even if it has no dependencies
         let mut combined_dependencies = before_after_dependencies;
         for (task, _config) in &self.tasks {
@@ -117,8 +342,28 @@ impl TaskDependencyListBuilder {
             }
         }
 
+        // Keep a copy of the full graph, draining the tasks will consume combined_dependencies
+        let dependency_graph = combined_dependencies.clone();
+
+        // Tasks in a fixed timestep phase may be run several times in a row, so they must be next to
+        // each other in the execution order. Count them so we know when each phase is finished.
+        let mut remaining_fixed_timestep_tasks = HashMap::<RegisteredType, usize>::new();
+        for config in self.tasks.values() {
+            if let Some(phase) = &config.fixed_timestep_phase {
+                *remaining_fixed_timestep_tasks
+                    .entry(phase.clone())
+                    .or_insert(0) += 1;
+            }
+        }
+
+        let mut current_fixed_timestep_phase = None;
+
         // Now, simulate draining the tasks.
         let mut execution_order = vec![];
+        let mut execution_order_types = vec![];
+        let mut execution_batches = vec![];
+        let mut phase_order = vec![];
+        let mut batch_index = 0;
         while !combined_dependencies.is_empty() {
             let mut ready_tasks = vec![];
 
@@ -129,32 +374,21 @@ impl TaskDependencyListBuilder {
                 }
             }
 
+            // Hash order is arbitrary, sort so that the same tasks always produce the same schedule
+            ready_tasks.sort_by_key(|x| x.type_name());
+
             // Check that something cleared. If nothing cleared, we have a cycle/deadlock.
             if ready_tasks.is_empty() {
-                // Cycle detected
-
-                let first_key;
-                for (key, _value) in &combined_dependencies {
-                    first_key = key;
-
-                    let mut key = first_key;
-                    let mut cycle = vec![first_key.clone()];
-                    loop {
-                        let values = &combined_dependencies[key];
-                        cycle.push(values[0].clone());
-                        key = &values[0];
-
-                        if cycle[0] == cycle[cycle.len() - 1] {
-                            for i in 0..cycle.len() - 1 {
-                                println!("{:?} depends on {:?}", cycle[i], cycle[i + 1]);
-                            }
-
-                            panic!(
-                                "Could not produce schedule, a task dependency cycle was detected"
-                            );
-                        }
-                    }
-                }
+                return Err(TaskDependencyListBuildError::Cycle {
+                    cycle: Self::find_cycle(&combined_dependencies),
+                });
+            }
+
+            let ready_tasks =
+                self.select_ready_tasks(ready_tasks, current_fixed_timestep_phase.as_ref())?;
+            if current_fixed_timestep_phase.is_none() {
+                current_fixed_timestep_phase =
+                    self.tasks[&ready_tasks[0]].fixed_timestep_phase.clone();
             }
 
             // Remove each ready task from the hashmap (both keys/values)
@@ -163,11 +397,10 @@ impl TaskDependencyListBuilder {
                 combined_dependencies.remove(&ready_task);
 
                 // Remove the task from all other tasks's values, wherever it exists
+                // (A task may list the same dependency more than once, i.e. a task that runs during a
+                // phase and also after that phase's dependency)
                 for (_task, dependencies) in combined_dependencies.iter_mut() {
-                    dependencies
-                        .iter()
-                        .position(|x| *x == *ready_task)
-                        .map(|i| dependencies.swap_remove(i));
+                    dependencies.retain(|x| *x != *ready_task);
                 }
             }
 
@@ -175,36 +408,630 @@ impl TaskDependencyListBuilder {
             for task in ready_tasks {
                 let task_config = self.tasks.remove(&task).unwrap();
 
+                if let Some(phase) = &task_config.fixed_timestep_phase {
+                    let remaining = remaining_fixed_timestep_tasks.get_mut(phase).unwrap();
+                    *remaining -= 1;
+                    if *remaining == 0 {
+                        current_fixed_timestep_phase = None;
+                    }
+                }
+
                 // Don't push phases.. they are only used for creating the dependency list
                 //TODO: Should push Tasks that aren't Option
                 if task_config.task.is_some() {
                     execution_order.push(task_config);
+                    execution_order_types.push(task);
+                    execution_batches.push(batch_index);
+                } else {
+                    phase_order.push(task);
                 }
             }
+
+            batch_index += 1;
         }
 
         assert!(self.tasks.is_empty());
 
         for task in &execution_order {
-            println!("task: {:?}", task);
+            trace!("task: {:?}", task);
         }
 
         //TODO: Produce a future or a vector of callbacks that can be run
         //TODO: Support for flags to turn things on/off
         //TODO: T param for global param that gets passed in to run()
 
-        let schedule = TaskDependencyList::new(execution_order);
-        schedule
+        // Phases aren't in the execution order, so resolve each task's dependencies down to the
+        // tasks they imply
+        let task_indices: HashMap<RegisteredType, usize> = execution_order_types
+            .iter()
+            .enumerate()
+            .map(|(index, task)| (task.clone(), index))
+            .collect();
+
+        let mut dependencies = vec![];
+        for task in &execution_order_types {
+            let mut task_dependencies = vec![];
+            let mut visited_phases = HashSet::new();
+            Self::collect_task_dependencies(
+                task,
+                &dependency_graph,
+                &task_indices,
+                &mut visited_phases,
+                &mut task_dependencies,
+            );
+            task_dependencies.sort();
+            dependencies.push(task_dependencies);
+        }
+
+        let schedule = TaskDependencyList::new(
+            execution_order,
+            execution_batches,
+            dependencies,
+            execution_order_types,
+            phase_order,
+            edges,
+            Arc::new(self.context_flags),
+        );
+        Ok(schedule)
+    }
+
+    // Every task/phase referred to by a before/after/during requirement must have been added
+    fn check_for_missing_dependencies(&self) -> Result<(), TaskDependencyListBuildError> {
+        // Sort so that the same error is reported every time
+        let mut tasks: Vec<_> = self.tasks.iter().collect();
+        tasks.sort_by_key(|(task, _config)| task.type_name());
+
+        for (task, config) in tasks {
+            let dependencies = config
+                .require_run_before
+                .iter()
+                .chain(&config.require_run_after)
+                .chain(&config.require_run_during);
+
+            for dependency in dependencies {
+                if !self.tasks.contains_key(dependency) {
+                    return Err(TaskDependencyListBuildError::MissingDependency {
+                        task: task.type_name(),
+                        dependency: dependency.type_name(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn register(&mut self, registered_type: RegisteredType, registration: TaskRegistration) {
+        self.tasks.insert(registered_type.clone(), registration());
+        self.registrations.insert(registered_type, registration);
+    }
+
+    fn unregister(&mut self, registered_type: &RegisteredType) -> bool {
+        self.tasks.remove(registered_type);
+        self.registrations.remove(registered_type).is_some()
+    }
+
+    // Converts the typed context flags that tasks filter on to the bits allocated for them
+    fn resolve_context_flags(&mut self) -> Result<(), TaskDependencyListBuildError> {
+        // Sort so that the same error is reported every time
+        let context_flags = &self.context_flags;
+        let mut tasks: Vec<_> = self.tasks.iter_mut().collect();
+        tasks.sort_by_key(|(task, _config)| task.type_name());
+
+        for (task, config) in tasks {
+            config
+                .context_flags_filter
+                .resolve(context_flags)
+                .map_err(
+                    |flag| TaskDependencyListBuildError::UnregisteredContextFlag {
+                        task: task.type_name(),
+                        flag,
+                    },
+                )?;
+        }
+
+        Ok(())
+    }
+
+    // Marks tasks that run during a fixed timestep phase
+    fn assign_fixed_timestep_phases(&mut self) {
+        let fixed_timestep_phases = &self.fixed_timestep_phases;
+        for config in self.tasks.values_mut() {
+            if config.task.is_some() {
+                config.fixed_timestep_phase = config
+                    .require_run_during
+                    .iter()
+                    .find(|phase| fixed_timestep_phases.contains(phase))
+                    .cloned();
+            }
+        }
+    }
+
+    // Picks which of the ready tasks to drain next so that the tasks in each fixed timestep phase
+    // stay together. Once a fixed timestep phase starts, only its tasks are drained until it's
+    // finished. Otherwise, tasks that aren't in a fixed timestep phase go first.
+    fn select_ready_tasks(
+        &self,
+        ready_tasks: Vec<RegisteredType>,
+        current_fixed_timestep_phase: Option<&RegisteredType>,
+    ) -> Result<Vec<RegisteredType>, TaskDependencyListBuildError> {
+        let fixed_timestep_phase =
+            |task: &RegisteredType| self.tasks[task].fixed_timestep_phase.as_ref();
+
+        if let Some(current_phase) = current_fixed_timestep_phase {
+            let in_current_phase: Vec<_> = ready_tasks
+                .into_iter()
+                .filter(|x| fixed_timestep_phase(x) == Some(current_phase))
+                .collect();
+
+            if in_current_phase.is_empty() {
+                // The rest of the phase is waiting on something outside of it
+                let waiting_task = self
+                    .tasks
+                    .iter()
+                    .filter(|(_, config)| {
+                        config.fixed_timestep_phase.as_ref() == Some(current_phase)
+                    })
+                    .map(|(task, _)| task.type_name())
+                    .min()
+                    .unwrap();
+
+                return Err(TaskDependencyListBuildError::FixedTimestepPhaseSplit {
+                    phase: current_phase.type_name(),
+                    task: waiting_task,
+                });
+            }
+
+            return Ok(in_current_phase);
+        }
+
+        let (fixed_timestep_tasks, other_tasks): (Vec<_>, Vec<_>) = ready_tasks
+            .into_iter()
+            .partition(|x| fixed_timestep_phase(x).is_some());
+
+        if !other_tasks.is_empty() {
+            return Ok(other_tasks);
+        }
+
+        // Start the phase of the first ready task. They are sorted, so this is always the same phase
+        let next_phase = fixed_timestep_phase(&fixed_timestep_tasks[0]);
+        Ok(fixed_timestep_tasks
+            .iter()
+            .filter(|x| fixed_timestep_phase(x) == next_phase)
+            .cloned()
+            .collect())
+    }
+
+    // Called when no remaining task is ready. Every remaining task is waiting on another remaining
+    // task, so following dependencies from any of them must eventually revisit one. Returns the
+    // cycle ordered such that each task must run before the next.
+    fn find_cycle(
+        remaining_dependencies: &HashMap<RegisteredType, Vec<RegisteredType>>,
+    ) -> Vec<&'static str> {
+        // Start from the first task by name and always follow the first dependency by name so
+        // that the same cycle is reported every time
+        let first_by_name = |tasks: &mut dyn Iterator<Item = &RegisteredType>| {
+            tasks.min_by_key(|x| x.type_name()).unwrap().clone()
+        };
+
+        let mut path = vec![first_by_name(&mut remaining_dependencies.keys())];
+        loop {
+            let current = &path[path.len() - 1];
+            let next = first_by_name(&mut remaining_dependencies[current].iter());
+
+            if let Some(cycle_start) = path.iter().position(|x| *x == next) {
+                // path[i + 1] is a dependency of path[i], so reverse to get run order
+                return path[cycle_start..]
+                    .iter()
+                    .rev()
+                    .map(|x| x.type_name())
+                    .collect();
+            }
+
+            path.push(next);
+        }
+    }
+
+    // Walks the dependencies of the given task or phase. Tasks are added to task_dependencies. Phases
+    // are walked through so that anything the phase waits on is added as well.
+    fn collect_task_dependencies(
+        task: &RegisteredType,
+        dependency_graph: &HashMap<RegisteredType, Vec<RegisteredType>>,
+        task_indices: &HashMap<RegisteredType, usize>,
+        visited_phases: &mut HashSet<RegisteredType>,
+        task_dependencies: &mut Vec<usize>,
+    ) {
+        let direct_dependencies = match dependency_graph.get(task) {
+            Some(direct_dependencies) => direct_dependencies,
+            None => return,
+        };
+
+        for dependency in direct_dependencies {
+            if let Some(index) = task_indices.get(dependency) {
+                if !task_dependencies.contains(index) {
+                    task_dependencies.push(*index);
+                }
+            } else if visited_phases.insert(dependency.clone()) {
+                Self::collect_task_dependencies(
+                    dependency,
+                    dependency_graph,
+                    task_indices,
+                    visited_phases,
+                    task_dependencies,
+                );
+            }
+        }
     }
 }
 
 /// A calculated task schedule. Can be used to call all tasks in the schedule
 pub struct TaskDependencyList {
     pub execution_order: Vec<TaskConfig>,
+
+    // Parallel to execution_order. Tasks that became ready at the same time while draining the
+    // dependencies share a batch index. Tasks in the same batch never depend on each other.
+    pub(super) execution_batches: Vec<usize>,
+
+    // Parallel to execution_order. Indices of the tasks that must finish before the task can start.
+    // Ordering requirements on phases are resolved down to the tasks that run during them.
+    pub(super) dependencies: Vec<Vec<usize>>,
+
+    // Parallel to execution_order. Only used for describe()
+    execution_order_types: Vec<RegisteredType>,
+
+    // Phases in the order they resolved. Only used for describe()
+    phase_order: Vec<RegisteredType>,
+
+    // Every ordering requirement, sorted. Only used for describe()
+    edges: Vec<ScheduleEdge>,
+
+    // The context flags that were added, shared with DispatchControl and TaskContextFlags
+    context_flags: Arc<ContextFlagRegistry>,
 }
 
 impl TaskDependencyList {
-    pub fn new(execution_order: Vec<TaskConfig>) -> Self {
-        TaskDependencyList { execution_order }
+    pub fn new(
+        execution_order: Vec<TaskConfig>,
+        execution_batches: Vec<usize>,
+        dependencies: Vec<Vec<usize>>,
+        execution_order_types: Vec<RegisteredType>,
+        phase_order: Vec<RegisteredType>,
+        edges: Vec<ScheduleEdge>,
+        context_flags: Arc<ContextFlagRegistry>,
+    ) -> Self {
+        assert_eq!(execution_order.len(), execution_batches.len());
+        assert_eq!(execution_order.len(), dependencies.len());
+        assert_eq!(execution_order.len(), execution_order_types.len());
+        TaskDependencyList {
+            execution_order,
+            execution_batches,
+            dependencies,
+            execution_order_types,
+            phase_order,
+            edges,
+            context_flags,
+        }
+    }
+
+    /// The context flags that were added, and the bits allocated for them
+    pub fn context_flag_registry(&self) -> &Arc<ContextFlagRegistry> {
+        &self.context_flags
+    }
+
+    /// Describes the resolved ordering and why it was resolved that way. Useful for debugging and
+    /// visualization (see `ScheduleDescription::to_dot()` and `ScheduleDescription::to_json()`)
+    pub fn describe(&self) -> ScheduleDescription {
+        let tasks = self
+            .execution_order
+            .iter()
+            .enumerate()
+            .map(|(index, config)| ScheduledTaskDescription {
+                name: self.execution_order_types[index].type_name(),
+                phase: config.require_run_during.first().map(|x| x.type_name()),
+                batch: self.execution_batches[index],
+                stage: None,
+                read_all: config.read_all,
+                write_all: config.write_all,
+                reads: config.reads.clone(),
+                writes: config.writes.clone(),
+            })
+            .collect();
+
+        ScheduleDescription {
+            tasks,
+            phases: self.phase_order.iter().map(|x| x.type_name()).collect(),
+            edges: self.edges.clone(),
+            stages: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::DataRequirement;
+    use crate::task::TaskContextFlags;
+    use crate::{ResourceTask, ResourceTaskImpl};
+
+    struct PhaseFirst;
+    impl Phase for PhaseFirst {
+        fn configure(_config: &mut TaskConfig) {}
+    }
+
+    struct PhaseSecond;
+    impl Phase for PhaseSecond {
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_after_phase::<PhaseFirst>();
+        }
+    }
+
+    struct UnregisteredPhase;
+    impl Phase for UnregisteredPhase {
+        fn configure(_config: &mut TaskConfig) {}
+    }
+
+    struct TaskA;
+    type TaskATask = ResourceTask<TaskA>;
+    impl ResourceTaskImpl for TaskA {
+        type RequiredResources = ();
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_uses_data_from::<TaskBTask>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            _data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+        }
+    }
+
+    struct TaskB;
+    type TaskBTask = ResourceTask<TaskB>;
+    impl ResourceTaskImpl for TaskB {
+        type RequiredResources = ();
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_uses_data_from::<TaskATask>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            _data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+        }
+    }
+
+    struct DuringUnregistered;
+    type DuringUnregisteredTask = ResourceTask<DuringUnregistered>;
+    impl ResourceTaskImpl for DuringUnregistered {
+        type RequiredResources = ();
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<UnregisteredPhase>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            _data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+        }
+    }
+
+    // Runs during the second phase, but also explicitly after the first phase, which the second
+    // phase already depends on
+    struct DuringSecondAfterFirst;
+    type DuringSecondAfterFirstTask = ResourceTask<DuringSecondAfterFirst>;
+    impl ResourceTaskImpl for DuringSecondAfterFirst {
+        type RequiredResources = ();
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<PhaseSecond>();
+            config.this_runs_after_phase::<PhaseFirst>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            _data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+        }
+    }
+
+    // Runs before the first phase, but during the second phase, which is impossible
+    struct BeforeFirstDuringSecond;
+    type BeforeFirstDuringSecondTask = ResourceTask<BeforeFirstDuringSecond>;
+    impl ResourceTaskImpl for BeforeFirstDuringSecond {
+        type RequiredResources = ();
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_before_phase::<PhaseFirst>();
+            config.this_runs_during_phase::<PhaseSecond>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            _data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+        }
+    }
+
+    struct FixedA;
+    type FixedATask = ResourceTask<FixedA>;
+    impl ResourceTaskImpl for FixedA {
+        type RequiredResources = ();
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<PhaseFirst>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            _data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+        }
+    }
+
+    struct FixedB;
+    type FixedBTask = ResourceTask<FixedB>;
+    impl ResourceTaskImpl for FixedB {
+        type RequiredResources = ();
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<PhaseFirst>();
+            config.this_uses_data_from::<FixedATask>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            _data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+        }
+    }
+
+    // Not in any phase, so it would normally be drained alongside FixedB
+    struct AfterFixedA;
+    type AfterFixedATask = ResourceTask<AfterFixedA>;
+    impl ResourceTaskImpl for AfterFixedA {
+        type RequiredResources = ();
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_uses_data_from::<FixedATask>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            _data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+        }
+    }
+
+    // Can't run until AfterFixedA is done, which can't run until FixedA is done
+    struct FixedAfterOutside;
+    type FixedAfterOutsideTask = ResourceTask<FixedAfterOutside>;
+    impl ResourceTaskImpl for FixedAfterOutside {
+        type RequiredResources = ();
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<PhaseFirst>();
+            config.this_uses_data_from::<AfterFixedATask>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            _data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+        }
+    }
+
+    #[test]
+    fn test_task_cycle() {
+        let mut builder = TaskDependencyListBuilder::new();
+        builder.add_task::<TaskATask>();
+        builder.add_task::<TaskBTask>();
+
+        let a = core::any::type_name::<TaskATask>();
+        let b = core::any::type_name::<TaskBTask>();
+        match builder.try_build() {
+            Err(TaskDependencyListBuildError::Cycle { cycle }) => {
+                assert_eq!(cycle.len(), 2);
+                assert!(cycle.contains(&a));
+                assert!(cycle.contains(&b));
+            }
+            _ => panic!("expected a cycle"),
+        }
+    }
+
+    #[test]
+    fn test_phase_cycle() {
+        let mut builder = TaskDependencyListBuilder::new();
+        builder.add_phase::<PhaseFirst>();
+        builder.add_phase::<PhaseSecond>();
+        builder.add_task::<BeforeFirstDuringSecondTask>();
+
+        let error = builder.try_build().err().unwrap();
+        let expected = TaskDependencyListBuildError::Cycle {
+            cycle: vec![
+                core::any::type_name::<BeforeFirstDuringSecondTask>(),
+                core::any::type_name::<PhaseFirst>(),
+            ],
+        };
+        assert_eq!(error, expected);
+        assert!(format!("{}", error).contains(core::any::type_name::<PhaseFirst>()));
+    }
+
+    #[test]
+    fn test_missing_phase() {
+        let mut builder = TaskDependencyListBuilder::new();
+        builder.add_phase::<PhaseFirst>();
+        builder.add_task::<DuringUnregisteredTask>();
+
+        let expected = TaskDependencyListBuildError::MissingDependency {
+            task: core::any::type_name::<DuringUnregisteredTask>(),
+            dependency: core::any::type_name::<UnregisteredPhase>(),
+        };
+        assert_eq!(builder.try_build().err(), Some(expected));
+    }
+
+    #[test]
+    fn test_redundant_requirements() {
+        let mut builder = TaskDependencyListBuilder::new();
+        builder.add_phase::<PhaseFirst>();
+        builder.add_phase::<PhaseSecond>();
+        builder.add_task::<DuringSecondAfterFirstTask>();
+
+        let task_list = builder.try_build().unwrap();
+        assert_eq!(task_list.execution_order.len(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_build_panics_on_cycle() {
+        let mut builder = TaskDependencyListBuilder::new();
+        builder.add_task::<TaskATask>();
+        builder.add_task::<TaskBTask>();
+        builder.build();
+    }
+
+    #[test]
+    fn test_fixed_timestep_phase_is_contiguous() {
+        let mut builder = TaskDependencyListBuilder::new();
+        builder.add_fixed_timestep_phase::<PhaseFirst>();
+        builder.add_task::<FixedATask>();
+        builder.add_task::<FixedBTask>();
+        builder.add_task::<AfterFixedATask>();
+
+        let task_list = builder.try_build().unwrap();
+        let order: Vec<_> = task_list
+            .execution_order_types
+            .iter()
+            .map(|x| x.type_name())
+            .collect();
+
+        let expected = vec![
+            core::any::type_name::<FixedATask>(),
+            core::any::type_name::<FixedBTask>(),
+            core::any::type_name::<AfterFixedATask>(),
+        ];
+        assert_eq!(order, expected);
+
+        let phase = Some(RegisteredType::of::<PhaseFirst>());
+        assert_eq!(task_list.execution_order[0].fixed_timestep_phase, phase);
+        assert_eq!(task_list.execution_order[1].fixed_timestep_phase, phase);
+        assert_eq!(task_list.execution_order[2].fixed_timestep_phase, None);
+    }
+
+    #[test]
+    fn test_fixed_timestep_phase_split() {
+        let mut builder = TaskDependencyListBuilder::new();
+        builder.add_fixed_timestep_phase::<PhaseFirst>();
+        builder.add_task::<FixedATask>();
+        builder.add_task::<AfterFixedATask>();
+        builder.add_task::<FixedAfterOutsideTask>();
+
+        let expected = TaskDependencyListBuildError::FixedTimestepPhaseSplit {
+            phase: core::any::type_name::<PhaseFirst>(),
+            task: core::any::type_name::<FixedAfterOutsideTask>(),
+        };
+        assert_eq!(builder.try_build().err(), Some(expected));
     }
 }
@@ -1,13 +1,51 @@
+use std::prelude::v1::*;
+
+use std::sync::Arc;
+
+use super::ContextFlag;
+use super::ContextFlagHandle;
+use super::ContextFlagRegistry;
+use super::Phase;
+use super::TaskContextFlags;
+use super::TaskDependencyListBuildError;
+use super::TaskDependencyListBuilder;
+use super::TaskFactory;
+
+/// A change to the registered tasks/phases, requested through `DispatchControl`
+pub type ScheduleChange = Box<dyn FnOnce(&mut TaskDependencyListBuilder) + Send + Sync>;
+
 pub struct DispatchControl {
     next_frame_context_flags: usize,
+    context_flag_registry: Arc<ContextFlagRegistry>,
     should_terminate: bool,
+
+    // Applied by the update loop at the end of the frame
+    schedule_changes: Vec<ScheduleChange>,
+    schedule_rebuild_requested: bool,
+    schedule_rebuild_error: Option<TaskDependencyListBuildError>,
 }
 
 impl DispatchControl {
     pub fn new(context_flags: usize) -> Self {
         DispatchControl {
             next_frame_context_flags: context_flags,
+            context_flag_registry: Arc::new(ContextFlagRegistry::new()),
             should_terminate: false,
+            schedule_changes: vec![],
+            schedule_rebuild_requested: false,
+            schedule_rebuild_error: None,
+        }
+    }
+
+    /// Used by the world builder so that flags can be set by type
+    pub fn with_context_flag_registry(context_flag_registry: Arc<ContextFlagRegistry>) -> Self {
+        DispatchControl {
+            next_frame_context_flags: 0,
+            context_flag_registry,
+            should_terminate: false,
+            schedule_changes: vec![],
+            schedule_rebuild_requested: false,
+            schedule_rebuild_error: None,
         }
     }
 
@@ -19,6 +57,48 @@ impl DispatchControl {
         &mut self.next_frame_context_flags
     }
 
+    pub fn context_flag_registry(&self) -> &ContextFlagRegistry {
+        &self.context_flag_registry
+    }
+
+    /// Set the flag, starting next frame. Panics if the flag was never added to the world.
+    pub fn set_context_flag<F: ContextFlag>(&mut self) {
+        self.next_frame_context_flags |= self.context_flag_handle::<F>().bits();
+    }
+
+    /// Clear the flag, starting next frame. Panics if the flag was never added to the world.
+    pub fn clear_context_flag<F: ContextFlag>(&mut self) {
+        self.next_frame_context_flags &= !self.context_flag_handle::<F>().bits();
+    }
+
+    pub fn set_context_flag_to<F: ContextFlag>(&mut self, value: bool) {
+        if value {
+            self.set_context_flag::<F>();
+        } else {
+            self.clear_context_flag::<F>();
+        }
+    }
+
+    /// Returns true if the flag will be set next frame
+    pub fn is_context_flag_set<F: ContextFlag>(&self) -> bool {
+        self.context_flag_handle::<F>()
+            .is_set(self.next_frame_context_flags)
+    }
+
+    /// Names of the registered flags that will be set next frame
+    pub fn next_frame_active_flag_names(&self) -> Vec<&'static str> {
+        self.context_flag_registry
+            .names(self.next_frame_context_flags)
+    }
+
+    /// The flags to pass to tasks for the next frame
+    pub fn next_frame_task_context_flags(&self) -> TaskContextFlags {
+        TaskContextFlags::with_registry(
+            self.next_frame_context_flags,
+            self.context_flag_registry.clone(),
+        )
+    }
+
     pub fn end_game_loop(&mut self) {
         self.should_terminate = true;
     }
@@ -26,4 +106,85 @@ impl DispatchControl {
     pub fn should_end_game_loop(&self) -> bool {
         self.should_terminate
     }
+
+    /// Add a task. It starts running next frame, once the schedule is rebuilt at the end of this
+    /// frame.
+    pub fn add_task<T: TaskFactory>(&mut self) {
+        self.modify_tasks(|tasks| tasks.add_task::<T>());
+    }
+
+    /// Remove a task. It stops running once the schedule is rebuilt at the end of the frame.
+    pub fn remove_task<T: TaskFactory>(&mut self) {
+        self.modify_tasks(|tasks| {
+            tasks.remove_task::<T>();
+        });
+    }
+
+    pub fn add_phase<P: Phase>(&mut self) {
+        self.modify_tasks(|tasks| tasks.add_phase::<P>());
+    }
+
+    pub fn remove_phase<P: Phase>(&mut self) {
+        self.modify_tasks(|tasks| {
+            tasks.remove_phase::<P>();
+        });
+    }
+
+    /// Make any change to the registered tasks/phases. Changes are applied in the order they were
+    /// requested, and the schedule is rebuilt at the end of the frame. If the new set of tasks can't
+    /// be ordered, none of the changes are kept and the error is available from
+    /// `schedule_rebuild_error()`.
+    pub fn modify_tasks<F>(&mut self, modify: F)
+    where
+        F: FnOnce(&mut TaskDependencyListBuilder) + Send + Sync + 'static,
+    {
+        self.schedule_changes.push(Box::new(modify));
+        self.schedule_rebuild_requested = true;
+    }
+
+    /// Rebuild the schedule at the end of the frame, even if no changes were requested. Tasks are
+    /// recreated when the schedule is rebuilt.
+    pub fn request_schedule_rebuild(&mut self) {
+        self.schedule_rebuild_requested = true;
+    }
+
+    /// The error from the most recent rebuild, if it failed
+    pub fn schedule_rebuild_error(&self) -> Option<&TaskDependencyListBuildError> {
+        self.schedule_rebuild_error.as_ref()
+    }
+
+    // Returns the requested changes if the schedule should be rebuilt
+    pub(crate) fn take_schedule_changes(&mut self) -> Option<Vec<ScheduleChange>> {
+        if self.schedule_rebuild_requested {
+            self.schedule_rebuild_requested = false;
+            Some(self.schedule_changes.drain(..).collect())
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn set_schedule_rebuild_result(
+        &mut self,
+        result: Result<(), TaskDependencyListBuildError>,
+    ) {
+        self.schedule_rebuild_error = result.err();
+    }
+
+    // Flags may be added when the schedule is rebuilt. Existing flags keep their bits.
+    pub(crate) fn set_context_flag_registry(
+        &mut self,
+        context_flag_registry: Arc<ContextFlagRegistry>,
+    ) {
+        self.context_flag_registry = context_flag_registry;
+    }
+
+    fn context_flag_handle<F: ContextFlag>(&self) -> ContextFlagHandle<F> {
+        match self.context_flag_registry.handle::<F>() {
+            Some(handle) => handle,
+            None => panic!(
+                "Context flag {} was never added to the world",
+                core::any::type_name::<F>()
+            ),
+        }
+    }
 }
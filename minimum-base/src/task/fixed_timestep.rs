@@ -0,0 +1,306 @@
+//! Support for phases that run at a fixed rate instead of once per frame. Tasks that run during a
+//! phase marked with `TaskDependencyListBuilder::add_fixed_timestep_phase` are run zero or more times
+//! each frame, depending on how much time has been added to the `FixedTimestep` resource.
+
+use std::prelude::v1::*;
+
+use core::ops::Range;
+use std::time::Duration;
+
+use super::RegisteredType;
+use super::ResourceMap;
+use super::TaskWithFilter;
+use super::TrustCell;
+
+/// Add this as a resource to control how often fixed timestep phases run. Something must call
+/// `add_time()` once per frame (i.e. with the frame's elapsed game time). The accumulated time is
+/// spent in steps of `step()`, and anything left over is exposed as `alpha()` so that rendering
+/// can interpolate between the previous and current fixed step.
+///
+/// All fixed timestep phases share this resource, so they run the same number of times per frame.
+/// If the resource is missing, fixed timestep phases run once per frame.
+pub struct FixedTimestep {
+    step: Duration,
+    max_substeps: u32,
+    accumulator: Duration,
+    substeps: u32,
+    alpha: f32,
+}
+
+impl FixedTimestep {
+    /// `max_substeps` limits how many steps can run in a single frame. If the game falls further
+    /// behind than that, the extra time is dropped rather than making the next frame even slower.
+    pub fn new(step: Duration, max_substeps: u32) -> Self {
+        assert!(step > Duration::from_secs(0));
+        FixedTimestep {
+            step,
+            max_substeps,
+            accumulator: Duration::from_secs(0),
+            substeps: 0,
+            alpha: 0.0,
+        }
+    }
+
+    /// Amount of time that passes in each step
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Amount of time that passes in each step, in seconds
+    pub fn step_secs(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    pub fn max_substeps(&self) -> u32 {
+        self.max_substeps
+    }
+
+    /// Accumulate time to be spent by fixed timestep phases. Call once per frame.
+    pub fn add_time(&mut self, elapsed: Duration) {
+        self.accumulator += elapsed;
+    }
+
+    /// Number of times fixed timestep phases ran in the current (or most recent) frame
+    pub fn substeps(&self) -> u32 {
+        self.substeps
+    }
+
+    /// How far (0..1) the current time is between the last fixed step and the next one. Tasks that
+    /// run after the fixed timestep phases (i.e. rendering) can use this to interpolate.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    // Spends as much accumulated time as possible and returns how many steps that is
+    fn consume_substeps(&mut self) -> u32 {
+        let mut substeps = 0;
+        while self.accumulator >= self.step && substeps < self.max_substeps {
+            self.accumulator -= self.step;
+            substeps += 1;
+        }
+
+        // Too far behind to catch up, drop the time we couldn't spend
+        if self.accumulator >= self.step {
+            let remainder = self.accumulator.as_nanos() % self.step.as_nanos();
+            self.accumulator = Duration::from_nanos(remainder as u64);
+        }
+
+        self.substeps = substeps;
+        self.alpha = self.accumulator.as_secs_f32() / self.step.as_secs_f32();
+        substeps
+    }
+}
+
+// A contiguous range of a schedule. Tasks in a fixed timestep phase are kept in their own segment
+// so that the segment can be run several times in a row.
+pub(super) struct ScheduleSegment {
+    pub(super) range: Range<usize>,
+    pub(super) fixed_timestep: bool,
+}
+
+impl ScheduleSegment {
+    // Splits a schedule into segments wherever the fixed timestep phase changes. The execution order
+    // keeps the tasks in each fixed timestep phase next to each other.
+    pub(super) fn split<'a, T, F>(items: &'a [T], first_task: F) -> Vec<ScheduleSegment>
+    where
+        F: Fn(&'a T) -> &'a TaskWithFilter,
+    {
+        let mut segments = vec![];
+        let mut segment_start = 0;
+        let mut segment_phase: Option<&RegisteredType> = None;
+
+        for (index, item) in items.iter().enumerate() {
+            let phase = first_task(item).fixed_timestep_phase();
+            if index > 0 && phase != segment_phase {
+                segments.push(ScheduleSegment {
+                    range: segment_start..index,
+                    fixed_timestep: segment_phase.is_some(),
+                });
+                segment_start = index;
+            }
+
+            segment_phase = phase;
+        }
+
+        if !items.is_empty() {
+            segments.push(ScheduleSegment {
+                range: segment_start..items.len(),
+                fixed_timestep: segment_phase.is_some(),
+            });
+        }
+
+        segments
+    }
+}
+
+// Used by the schedulers during a single step to decide how many times each segment runs. The
+// number of substeps is taken from the FixedTimestep resource the first time it's needed.
+pub(super) struct FixedTimestepSubsteps {
+    substeps: Option<u32>,
+}
+
+impl FixedTimestepSubsteps {
+    pub(super) fn new() -> Self {
+        FixedTimestepSubsteps { substeps: None }
+    }
+
+    pub(super) fn repetitions(
+        &mut self,
+        segment: &ScheduleSegment,
+        resource_map: &TrustCell<ResourceMap>,
+    ) -> u32 {
+        if !segment.fixed_timestep {
+            return 1;
+        }
+
+        *self.substeps.get_or_insert_with(|| {
+            match resource_map.borrow().try_fetch_mut::<FixedTimestep>() {
+                Some(mut fixed_timestep) => fixed_timestep.consume_substeps(),
+                None => 1,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{DataRequirement, Write};
+    use crate::task::{Phase, TaskConfig, TaskContextFlags};
+    use crate::{ResourceTask, ResourceTaskImpl, WorldBuilder};
+
+    struct FrameCount(u32);
+    struct SubstepCount(u32);
+
+    struct PhaseFirst;
+    impl Phase for PhaseFirst {
+        fn configure(_config: &mut TaskConfig) {}
+    }
+
+    struct PhaseFixed;
+    impl Phase for PhaseFixed {
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_after_phase::<PhaseFirst>();
+        }
+    }
+
+    // Adds 25ms to the fixed timestep each frame
+    struct AdvanceTime;
+    type AdvanceTimeTask = ResourceTask<AdvanceTime>;
+    impl ResourceTaskImpl for AdvanceTime {
+        type RequiredResources = (Write<FixedTimestep>, Write<FrameCount>);
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<PhaseFirst>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+            let (mut fixed_timestep, mut frame_count) = data;
+            fixed_timestep.add_time(Duration::from_millis(25));
+            frame_count.0 += 1;
+        }
+    }
+
+    struct CountSubsteps;
+    type CountSubstepsTask = ResourceTask<CountSubsteps>;
+    impl ResourceTaskImpl for CountSubsteps {
+        type RequiredResources = (Write<SubstepCount>,);
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<PhaseFixed>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+            let (mut substep_count,) = data;
+            substep_count.0 += 1;
+        }
+    }
+
+    fn build_world() -> WorldBuilder {
+        WorldBuilder::new()
+            .with_resource(FixedTimestep::new(Duration::from_millis(10), 4))
+            .with_resource(FrameCount(0))
+            .with_resource(SubstepCount(0))
+            .with_phase::<PhaseFirst>()
+            .with_fixed_timestep_phase::<PhaseFixed>()
+            .with_task::<AdvanceTimeTask>()
+            .with_task::<CountSubstepsTask>()
+    }
+
+    fn check_substeps(resource_map: ResourceMap) {
+        // 25ms is 2 steps with 5ms left over, then 30ms is 3 steps with nothing left over
+        assert_eq!(resource_map.fetch::<FrameCount>().0, 2);
+        assert_eq!(resource_map.fetch::<SubstepCount>().0, 5);
+        assert_eq!(resource_map.fetch::<FixedTimestep>().substeps(), 3);
+        assert_eq!(resource_map.fetch::<FixedTimestep>().alpha(), 0.0);
+    }
+
+    #[test]
+    fn test_consume_substeps() {
+        let mut fixed_timestep = FixedTimestep::new(Duration::from_millis(10), 4);
+        fixed_timestep.add_time(Duration::from_millis(25));
+        assert_eq!(fixed_timestep.consume_substeps(), 2);
+        assert!((fixed_timestep.alpha() - 0.5).abs() < 0.001);
+
+        fixed_timestep.add_time(Duration::from_millis(3));
+        assert_eq!(fixed_timestep.consume_substeps(), 0);
+        assert!((fixed_timestep.alpha() - 0.8).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_max_substeps() {
+        let mut fixed_timestep = FixedTimestep::new(Duration::from_millis(10), 4);
+        fixed_timestep.add_time(Duration::from_millis(105));
+        assert_eq!(fixed_timestep.consume_substeps(), 4);
+
+        // The time that couldn't be spent is dropped, except for the partial step
+        assert!((fixed_timestep.alpha() - 0.5).abs() < 0.001);
+        assert_eq!(fixed_timestep.consume_substeps(), 0);
+    }
+
+    #[test]
+    fn test_single_thread() {
+        let mut update_loop = build_world().build_update_loop_single_threaded(0);
+        update_loop.step();
+        update_loop.step();
+        check_substeps(update_loop.into_resource_map());
+    }
+
+    #[test]
+    fn test_multi_thread() {
+        let mut update_loop = build_world().build_update_loop_multi_threaded(0);
+        update_loop.step();
+        update_loop.step();
+        check_substeps(update_loop.into_resource_map());
+    }
+
+    #[test]
+    fn test_graph() {
+        let mut update_loop = build_world().build_update_loop_graph(0);
+        update_loop.step();
+        update_loop.step();
+        check_substeps(update_loop.into_resource_map());
+    }
+
+    #[test]
+    fn test_runs_once_without_resource() {
+        let mut update_loop = WorldBuilder::new()
+            .with_resource(SubstepCount(0))
+            .with_fixed_timestep_phase::<PhaseFixed>()
+            .with_phase::<PhaseFirst>()
+            .with_task::<CountSubstepsTask>()
+            .build_update_loop_single_threaded(0);
+
+        update_loop.step();
+        update_loop.step();
+
+        let resource_map = update_loop.into_resource_map();
+        assert_eq!(resource_map.fetch::<SubstepCount>().0, 2);
+    }
+}
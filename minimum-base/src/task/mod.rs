@@ -27,31 +27,71 @@ pub use traits::TaskFactory;
 mod stage;
 use stage::TaskStage;
 
+mod schedule_description;
+pub use schedule_description::ScheduleDescription;
+pub use schedule_description::ScheduleEdge;
+pub use schedule_description::ScheduleEdgeReason;
+pub use schedule_description::ScheduledTaskDescription;
+
 mod dependency_list;
 pub use dependency_list::TaskDependencyList;
+pub use dependency_list::TaskDependencyListBuildError;
 pub use dependency_list::TaskDependencyListBuilder;
 
 mod tasks;
+pub use tasks::fn_task::FnTask;
+pub use tasks::fn_task::FnTaskArg;
+pub use tasks::fn_task::FnTaskFunction;
 pub use tasks::read_all_task::ReadAllTask;
 pub use tasks::read_all_task::ReadAllTaskImpl;
 pub use tasks::resource_task::ResourceTask;
 pub use tasks::resource_task::ResourceTaskImpl;
+pub use tasks::sub_schedule_task::SubScheduleTask;
+pub use tasks::sub_schedule_task::SubScheduleTaskImpl;
 pub use tasks::write_all_task::WriteAllTask;
 pub use tasks::write_all_task::WriteAllTaskImpl;
 
+mod context_flag_registry;
+pub use context_flag_registry::ContextFlag;
+pub use context_flag_registry::ContextFlagHandle;
+pub use context_flag_registry::ContextFlagRegistry;
+pub use context_flag_registry::MAX_CONTEXT_FLAGS;
+
 mod context_flags;
 pub use context_flags::TaskContextFlags;
 pub use context_flags::TaskContextFlagsFilter;
 pub use context_flags::TaskWithFilter;
 
+mod fixed_timestep;
+pub use fixed_timestep::FixedTimestep;
+use fixed_timestep::FixedTimestepSubsteps;
+use fixed_timestep::ScheduleSegment;
+
+mod profiler;
+#[cfg(feature = "std")]
+pub use profiler::ProfiledFrame;
+#[cfg(feature = "std")]
+pub use profiler::ProfiledPhase;
+#[cfg(feature = "std")]
+pub use profiler::ProfiledTask;
+#[cfg(feature = "std")]
+pub use profiler::TaskProfiler;
+use profiler::TaskProfilerRecorder;
+#[cfg(feature = "std")]
+pub use profiler::TimingStats;
+
 mod schedulers;
+pub use schedulers::Scheduler;
+pub use schedulers::TaskScheduleBuilderGraph;
 pub use schedulers::TaskScheduleBuilderMultiThread;
 pub use schedulers::TaskScheduleBuilderSingleThread;
+pub use schedulers::TaskScheduleGraph;
 pub use schedulers::TaskScheduleMultiThread;
 pub use schedulers::TaskScheduleSingleThread;
 
 mod dispatch_control;
 pub use dispatch_control::DispatchControl;
+pub use dispatch_control::ScheduleChange;
 
 //TODO: Move default phases to framework
 
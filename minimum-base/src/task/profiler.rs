@@ -0,0 +1,695 @@
+//! Optional per-task timing. If a `TaskProfiler` resource has been added to the resource map and it
+//! is enabled, the schedulers time every task they run and hand the results to the profiler at the
+//! end of each step. Requires std for timing and threads.
+//!
+//! Schedules stepped from inside a task (see `SubScheduleTask`) are not recorded as frames of their
+//! own. Their time is part of the task that stepped them.
+
+use std::prelude::v1::*;
+
+use super::ResourceMap;
+use super::TaskContextFlags;
+use super::TaskWithFilter;
+use super::TrustCell;
+
+#[cfg(feature = "std")]
+use super::schedule_description::escape;
+
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::fmt::Write;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+/// A single run of a task
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct ProfiledTask {
+    pub name: &'static str,
+
+    /// The phase the task runs during, if any
+    pub phase: Option<&'static str>,
+
+    /// 0 if the task ran on the thread that stepped the schedule, otherwise the thread pool index + 1
+    pub thread: usize,
+
+    /// Relative to the start of the frame
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// The span of time from when the first task in a phase started to when the last one finished
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct ProfiledPhase {
+    pub name: &'static str,
+
+    /// Relative to the start of the frame
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// Everything that was timed during a single step of a schedule
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct ProfiledFrame {
+    pub frame_index: u64,
+
+    /// Relative to when the profiler was created
+    pub start: Duration,
+    pub duration: Duration,
+
+    /// Sorted by start time
+    pub tasks: Vec<ProfiledTask>,
+
+    /// Sorted by start time
+    pub phases: Vec<ProfiledPhase>,
+}
+
+/// Min/average/max of a duration over the frames in the profiler's history
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimingStats {
+    pub min: Duration,
+    pub average: Duration,
+    pub max: Duration,
+    pub sample_count: usize,
+}
+
+#[cfg(feature = "std")]
+impl TimingStats {
+    fn from_samples<I: Iterator<Item = Duration>>(samples: I) -> Self {
+        let mut stats = TimingStats::default();
+        let mut total = Duration::default();
+        for sample in samples {
+            if stats.sample_count == 0 || sample < stats.min {
+                stats.min = sample;
+            }
+
+            if sample > stats.max {
+                stats.max = sample;
+            }
+
+            total += sample;
+            stats.sample_count += 1;
+        }
+
+        if stats.sample_count > 0 {
+            stats.average = total / stats.sample_count as u32;
+        }
+
+        stats
+    }
+}
+
+/// Add this as a resource to record how long each task and phase takes every frame. Keeps a
+/// rolling history of frames that can be summarized or exported to the Chrome trace-event format
+/// (viewable in chrome://tracing or similar tools)
+#[cfg(feature = "std")]
+pub struct TaskProfiler {
+    enabled: bool,
+    max_frame_history: usize,
+    created: Instant,
+    next_frame_index: u64,
+    frames: VecDeque<ProfiledFrame>,
+
+    // Set while a TaskProfilerRecorder is recording a frame, so that nested schedule steps don't
+    // record frames of their own
+    recording: Arc<AtomicBool>,
+}
+
+#[cfg(feature = "std")]
+impl TaskProfiler {
+    /// Create an enabled profiler that keeps the given number of frames
+    pub fn new(max_frame_history: usize) -> Self {
+        TaskProfiler {
+            enabled: true,
+            max_frame_history,
+            created: Instant::now(),
+            next_frame_index: 0,
+            frames: VecDeque::with_capacity(max_frame_history),
+            recording: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// When disabled, tasks are not timed at all. History is kept.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn max_frame_history(&self) -> usize {
+        self.max_frame_history
+    }
+
+    /// Recorded frames, oldest first
+    pub fn frames(&self) -> impl Iterator<Item = &ProfiledFrame> {
+        self.frames.iter()
+    }
+
+    /// The most recently recorded frame
+    pub fn last_frame(&self) -> Option<&ProfiledFrame> {
+        self.frames.back()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Timing of whole frames over the recorded history
+    pub fn frame_stats(&self) -> TimingStats {
+        TimingStats::from_samples(self.frames.iter().map(|x| x.duration))
+    }
+
+    /// Timing of each task over the recorded history, sorted by name. Frames in which a task did
+    /// not run (i.e. it was filtered out by context flags) are not counted.
+    pub fn task_stats(&self) -> Vec<(&'static str, TimingStats)> {
+        let names = self
+            .frames
+            .iter()
+            .flat_map(|x| x.tasks.iter().map(|x| x.name));
+        self.stats_by_name(names, |frame, name| {
+            frame
+                .tasks
+                .iter()
+                .filter(|x| x.name == name)
+                .map(|x| x.duration)
+                .sum()
+        })
+    }
+
+    /// Timing of each phase over the recorded history, sorted by name
+    pub fn phase_stats(&self) -> Vec<(&'static str, TimingStats)> {
+        let names = self
+            .frames
+            .iter()
+            .flat_map(|x| x.phases.iter().map(|x| x.name));
+        self.stats_by_name(names, |frame, name| {
+            frame
+                .phases
+                .iter()
+                .filter(|x| x.name == name)
+                .map(|x| x.duration)
+                .sum()
+        })
+    }
+
+    fn stats_by_name<I, F>(
+        &self,
+        names: I,
+        duration_in_frame: F,
+    ) -> Vec<(&'static str, TimingStats)>
+    where
+        I: Iterator<Item = &'static str>,
+        F: Fn(&ProfiledFrame, &'static str) -> Duration,
+    {
+        let mut names: Vec<_> = names.collect();
+        names.sort();
+        names.dedup();
+
+        names
+            .into_iter()
+            .map(|name| {
+                let samples = self
+                    .frames
+                    .iter()
+                    .filter(|frame| {
+                        frame
+                            .tasks
+                            .iter()
+                            .any(|x| x.name == name || x.phase == Some(name))
+                    })
+                    .map(|frame| duration_in_frame(frame, name));
+
+                (name, TimingStats::from_samples(samples))
+            })
+            .collect()
+    }
+
+    /// Exports the recorded history in the Chrome trace-event JSON format. Frames and phases are
+    /// on the first track, and tasks are on a track per thread.
+    pub fn to_chrome_trace_json(&self) -> String {
+        let mut events = vec![];
+
+        let mut threads = vec![0];
+        for frame in &self.frames {
+            for task in &frame.tasks {
+                if !threads.contains(&task.thread) {
+                    threads.push(task.thread);
+                }
+            }
+        }
+        threads.sort();
+
+        for thread in threads {
+            let thread_name = if thread == 0 {
+                "Frames/Phases (main thread)".to_string()
+            } else {
+                format!("Worker {}", thread - 1)
+            };
+
+            events.push(format!(
+                "{{\"name\": \"thread_name\", \"ph\": \"M\", \"pid\": 1, \"tid\": {}, \"args\": {{ \"name\": \"{}\" }}}}",
+                thread, thread_name
+            ));
+        }
+
+        let complete_event = |name: &str, category: &str, thread: usize, start, duration| {
+            format!(
+                "{{\"name\": \"{}\", \"cat\": \"{}\", \"ph\": \"X\", \"pid\": 1, \"tid\": {}, \"ts\": {:.3}, \"dur\": {:.3}}}",
+                escape(name),
+                category,
+                thread,
+                micros(start),
+                micros(duration)
+            )
+        };
+
+        for frame in &self.frames {
+            let frame_name = format!("Frame {}", frame.frame_index);
+            events.push(complete_event(
+                &frame_name,
+                "frame",
+                0,
+                frame.start,
+                frame.duration,
+            ));
+
+            for phase in &frame.phases {
+                events.push(complete_event(
+                    phase.name,
+                    "phase",
+                    0,
+                    frame.start + phase.start,
+                    phase.duration,
+                ));
+            }
+
+            for task in &frame.tasks {
+                events.push(complete_event(
+                    task.name,
+                    "task",
+                    task.thread,
+                    frame.start + task.start,
+                    task.duration,
+                ));
+            }
+        }
+
+        let mut json = String::new();
+        writeln!(json, "{{\"traceEvents\": [").unwrap();
+        for (index, event) in events.iter().enumerate() {
+            let separator = if index + 1 < events.len() { "," } else { "" };
+            writeln!(json, "  {}{}", event, separator).unwrap();
+        }
+        writeln!(json, "]}}").unwrap();
+        json
+    }
+
+    // Called by the schedulers at the end of each step
+    fn push_frame(
+        &mut self,
+        frame_start: Instant,
+        duration: Duration,
+        mut tasks: Vec<ProfiledTask>,
+    ) {
+        tasks.sort_by_key(|x| x.start);
+
+        // Each phase spans from the first task in it starting to the last task in it finishing
+        let mut phases: Vec<ProfiledPhase> = vec![];
+        for task in &tasks {
+            let phase_name = match task.phase {
+                Some(phase_name) => phase_name,
+                None => continue,
+            };
+
+            let task_end = task.start + task.duration;
+            match phases.iter_mut().find(|x| x.name == phase_name) {
+                Some(phase) => {
+                    let phase_end = phase.start + phase.duration;
+                    if task_end > phase_end {
+                        phase.duration = task_end - phase.start;
+                    }
+                }
+                None => phases.push(ProfiledPhase {
+                    name: phase_name,
+                    start: task.start,
+                    duration: task.duration,
+                }),
+            }
+        }
+
+        let frame = ProfiledFrame {
+            frame_index: self.next_frame_index,
+            start: frame_start.duration_since(self.created),
+            duration,
+            tasks,
+            phases,
+        };
+
+        self.next_frame_index += 1;
+
+        while self.frames.len() >= self.max_frame_history.max(1) {
+            self.frames.pop_front();
+        }
+
+        self.frames.push_back(frame);
+    }
+}
+
+#[cfg(feature = "std")]
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+/// Used by the schedulers to time tasks during a single step. Only does work if there is an
+/// enabled TaskProfiler in the resource map.
+#[cfg(feature = "std")]
+pub(super) struct TaskProfilerRecorder {
+    frame_start: Instant,
+    tasks: Mutex<Vec<ProfiledTask>>,
+    _recording: RecordingGuard,
+}
+
+// Clears TaskProfiler::recording when the recorder is dropped, so that a task panicking during the
+// frame doesn't stop the profiler from recording later frames
+#[cfg(feature = "std")]
+struct RecordingGuard(Arc<AtomicBool>);
+
+#[cfg(feature = "std")]
+impl Drop for RecordingGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(feature = "std")]
+impl TaskProfilerRecorder {
+    pub(super) fn begin(resource_map: &TrustCell<ResourceMap>) -> Option<Self> {
+        let resource_map = resource_map.borrow();
+        let profiler = resource_map.try_fetch::<TaskProfiler>()?;
+        if !profiler.is_enabled() {
+            return None;
+        }
+
+        // A schedule being stepped from inside a task of the frame that is already being recorded
+        if profiler.recording.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+
+        Some(TaskProfilerRecorder {
+            frame_start: Instant::now(),
+            tasks: Mutex::new(vec![]),
+            _recording: RecordingGuard(profiler.recording.clone()),
+        })
+    }
+
+    pub(super) fn run_task(
+        recorder: Option<&Self>,
+        task: &TaskWithFilter,
+        context_flags: &TaskContextFlags,
+        resource_map: &TrustCell<ResourceMap>,
+    ) {
+        let recorder = match recorder {
+            Some(recorder) => recorder,
+            None => {
+                task.run_if_filter_passes(context_flags, resource_map);
+                return;
+            }
+        };
+
+        let start = Instant::now();
+        if task.run_if_filter_passes(context_flags, resource_map) {
+            let duration = start.elapsed();
+            recorder.tasks.lock().unwrap().push(ProfiledTask {
+                name: task.name(),
+                phase: task.phase(),
+                thread: rayon::current_thread_index().map_or(0, |x| x + 1),
+                start: start.duration_since(recorder.frame_start),
+                duration,
+            });
+        }
+    }
+
+    pub(super) fn finish(self, resource_map: &TrustCell<ResourceMap>) {
+        let duration = self.frame_start.elapsed();
+        let tasks = self.tasks.into_inner().unwrap();
+
+        // A task may have removed the profiler during the frame
+        if let Some(mut profiler) = resource_map.borrow().try_fetch_mut::<TaskProfiler>() {
+            profiler.push_frame(self.frame_start, duration, tasks);
+        }
+    }
+}
+
+// Without std there is no way to time tasks, so this only runs them
+#[cfg(not(feature = "std"))]
+pub(super) struct TaskProfilerRecorder;
+
+#[cfg(not(feature = "std"))]
+impl TaskProfilerRecorder {
+    pub(super) fn begin(_resource_map: &TrustCell<ResourceMap>) -> Option<Self> {
+        None
+    }
+
+    pub(super) fn run_task(
+        _recorder: Option<&Self>,
+        task: &TaskWithFilter,
+        context_flags: &TaskContextFlags,
+        resource_map: &TrustCell<ResourceMap>,
+    ) {
+        task.run_if_filter_passes(context_flags, resource_map);
+    }
+
+    pub(super) fn finish(self, _resource_map: &TrustCell<ResourceMap>) {}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::resource::{DataRequirement, Write};
+    use crate::task::TaskConfig;
+    use crate::test_tasks::*;
+    use crate::{
+        ResourceTask, ResourceTaskImpl, SubScheduleTask, SubScheduleTaskImpl,
+        TaskDependencyListBuilder, WorldBuilder,
+    };
+
+    // Never runs in these tests, the context flag is not set
+    struct IncrementBIfFlagged;
+    type IncrementBIfFlaggedTask = ResourceTask<IncrementBIfFlagged>;
+    impl ResourceTaskImpl for IncrementBIfFlagged {
+        type RequiredResources = (Write<CounterB>,);
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<PhaseFirst>();
+            config.run_only_if(1);
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+            let (mut counter,) = data;
+            counter.0 += 1;
+        }
+    }
+
+    // Steps the simulation schedule three times
+    struct Simulate;
+    type SimulateTask = SubScheduleTask<Simulate>;
+    impl SubScheduleTaskImpl for Simulate {
+        fn schedule_name() -> &'static str {
+            "simulation"
+        }
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<PhaseSecond>();
+        }
+
+        fn step_count(_context_flags: &TaskContextFlags, _resource_map: &ResourceMap) -> usize {
+            3
+        }
+    }
+
+    struct PanicNextFrame(bool);
+
+    // Panics the first time it runs after PanicNextFrame is set
+    struct PanicIfRequested;
+    type PanicIfRequestedTask = ResourceTask<PanicIfRequested>;
+    impl ResourceTaskImpl for PanicIfRequested {
+        type RequiredResources = (Write<PanicNextFrame>,);
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<PhaseSecond>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+            let (mut panic_next_frame,) = data;
+            if panic_next_frame.0 {
+                panic_next_frame.0 = false;
+                panic!("PanicIfRequested was asked to panic");
+            }
+        }
+    }
+
+    fn build_world(profiler: TaskProfiler) -> WorldBuilder {
+        crate::test_tasks::build_world()
+            .with_resource(profiler)
+            .with_task::<IncrementATask>()
+            .with_task::<IncrementBIfFlaggedTask>()
+    }
+
+    #[test]
+    fn test_records_tasks_and_phases() {
+        let mut update_loop = build_world(TaskProfiler::new(10)).build_update_loop_single_threaded(1);
+        update_loop.step();
+        update_loop.step();
+
+        let resource_map = update_loop.into_resource_map();
+        let profiler = resource_map.fetch::<TaskProfiler>();
+        assert_eq!(profiler.frames().count(), 2);
+
+        let frame = profiler.last_frame().unwrap();
+        assert_eq!(frame.frame_index, 1);
+        assert_eq!(frame.tasks.len(), 2);
+        assert_eq!(frame.phases.len(), 1);
+        assert_eq!(frame.phases[0].name, core::any::type_name::<PhaseFirst>());
+
+        let task_stats = profiler.task_stats();
+        assert_eq!(task_stats.len(), 2);
+        assert!(task_stats.iter().all(|(_, stats)| stats.sample_count == 2));
+        assert!(task_stats
+            .iter()
+            .all(|(_, stats)| stats.min <= stats.average && stats.average <= stats.max));
+
+        assert_eq!(profiler.phase_stats().len(), 1);
+        assert_eq!(profiler.frame_stats().sample_count, 2);
+    }
+
+    #[test]
+    fn test_skips_filtered_tasks() {
+        let mut update_loop = build_world(TaskProfiler::new(10)).build_update_loop_graph(0);
+        update_loop.step();
+
+        let resource_map = update_loop.into_resource_map();
+        let profiler = resource_map.fetch::<TaskProfiler>();
+        let frame = profiler.last_frame().unwrap();
+        assert_eq!(frame.tasks.len(), 1);
+        assert_eq!(
+            frame.tasks[0].name,
+            core::any::type_name::<IncrementATask>()
+        );
+    }
+
+    #[test]
+    fn test_history_is_limited() {
+        let mut update_loop = build_world(TaskProfiler::new(3)).build_update_loop_multi_threaded(1);
+        for _ in 0..5 {
+            update_loop.step();
+        }
+
+        let resource_map = update_loop.into_resource_map();
+        let profiler = resource_map.fetch::<TaskProfiler>();
+        let frame_indices: Vec<_> = profiler.frames().map(|x| x.frame_index).collect();
+        assert_eq!(frame_indices, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut profiler = TaskProfiler::new(10);
+        profiler.set_enabled(false);
+
+        let mut update_loop = build_world(profiler).build_update_loop_single_threaded(1);
+        update_loop.step();
+
+        let resource_map = update_loop.into_resource_map();
+        assert!(resource_map.fetch::<TaskProfiler>().last_frame().is_none());
+        assert_eq!(resource_map.fetch::<CounterA>().0, 1);
+    }
+
+    #[test]
+    fn test_chrome_trace_export() {
+        let mut update_loop = build_world(TaskProfiler::new(10)).build_update_loop_single_threaded(1);
+        update_loop.step();
+
+        let resource_map = update_loop.into_resource_map();
+        let json = resource_map.fetch::<TaskProfiler>().to_chrome_trace_json();
+        assert!(json.starts_with("{\"traceEvents\": ["));
+        assert!(json.contains("\"name\": \"Frame 0\", \"cat\": \"frame\", \"ph\": \"X\""));
+        assert!(json.contains("\"cat\": \"phase\""));
+        assert!(json.contains(&format!(
+            "\"name\": \"{}\", \"cat\": \"task\"",
+            core::any::type_name::<IncrementATask>()
+        )));
+        assert!(!json.contains(",\n]}"));
+    }
+
+    #[test]
+    fn test_sub_schedule_steps_are_part_of_the_frame() {
+        let mut simulation_schedule = TaskDependencyListBuilder::new();
+        simulation_schedule.add_phase::<PhaseFirst>();
+        simulation_schedule.add_task::<IncrementBTask>();
+
+        let mut update_loop = crate::test_tasks::build_world()
+            .with_resource(TaskProfiler::new(10))
+            .with_task::<IncrementATask>()
+            .with_schedule("simulation", simulation_schedule)
+            .with_task::<SimulateTask>()
+            .build_update_loop_multi_threaded(1);
+        update_loop.step();
+        update_loop.step();
+
+        let resource_map = update_loop.into_resource_map();
+        assert_eq!(resource_map.fetch::<CounterB>().0, 6);
+
+        let profiler = resource_map.fetch::<TaskProfiler>();
+        let frame_indices: Vec<_> = profiler.frames().map(|x| x.frame_index).collect();
+        assert_eq!(frame_indices, vec![0, 1]);
+
+        let task_names: Vec<_> = profiler
+            .last_frame()
+            .unwrap()
+            .tasks
+            .iter()
+            .map(|x| x.name)
+            .collect();
+        assert_eq!(
+            task_names,
+            vec![
+                core::any::type_name::<IncrementATask>(),
+                core::any::type_name::<SimulateTask>()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_records_frames_after_a_task_panics() {
+        let mut update_loop = build_world(TaskProfiler::new(10))
+            .with_resource(PanicNextFrame(true))
+            .with_task::<PanicIfRequestedTask>()
+            .build_update_loop_single_threaded(0);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| update_loop.step()));
+        assert!(result.is_err());
+
+        update_loop.step();
+
+        let resource_map = update_loop.into_resource_map();
+        let profiler = resource_map.fetch::<TaskProfiler>();
+        let frame_indices: Vec<_> = profiler.frames().map(|x| x.frame_index).collect();
+        assert_eq!(frame_indices, vec![0]);
+        assert_eq!(profiler.last_frame().unwrap().tasks.len(), 2);
+    }
+}
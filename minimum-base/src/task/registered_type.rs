@@ -1,6 +1,6 @@
 /// An ID for types, with type name for debug purposes
 /// Wrap in a struct just to make it easy to attach debug info (i.e. task name)
-#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+#[derive(Eq, PartialEq, Hash, Clone)]
 pub struct RegisteredType {
     type_id: core::any::TypeId,
     type_name: &'static str,
@@ -13,4 +13,14 @@ impl RegisteredType {
             type_name: core::any::type_name::<T>(),
         }
     }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl core::fmt::Debug for RegisteredType {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "RegisteredType({})", self.type_name)
+    }
 }
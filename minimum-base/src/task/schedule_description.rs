@@ -0,0 +1,398 @@
+use std::prelude::v1::*;
+
+use std::fmt::Write;
+
+use super::RegisteredType;
+use super::ResourceId;
+
+/// Why one task/phase must finish before another one can start
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum ScheduleEdgeReason {
+    /// The earlier task/phase asked to run before the later one (i.e. `this_runs_before_phase` or
+    /// `this_provides_data_to`)
+    Before,
+
+    /// The later task/phase asked to run after the earlier one (i.e. `this_runs_after_phase` or
+    /// `this_uses_data_from`)
+    After,
+
+    /// A task runs during a phase. The task waits on everything the phase waits on, and the phase
+    /// waits on the task.
+    During,
+}
+
+impl ScheduleEdgeReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScheduleEdgeReason::Before => "before",
+            ScheduleEdgeReason::After => "after",
+            ScheduleEdgeReason::During => "during",
+        }
+    }
+}
+
+/// A single ordering requirement between two tasks/phases, recorded while building the
+/// TaskDependencyList
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct ScheduleEdge {
+    /// Must finish first
+    pub before: &'static str,
+
+    /// Can't start until `before` finishes
+    pub after: &'static str,
+
+    pub reason: ScheduleEdgeReason,
+}
+
+impl ScheduleEdge {
+    pub(super) fn new(
+        before: &RegisteredType,
+        after: &RegisteredType,
+        reason: ScheduleEdgeReason,
+    ) -> Self {
+        ScheduleEdge {
+            before: before.type_name(),
+            after: after.type_name(),
+            reason,
+        }
+    }
+}
+
+/// Everything we know about a single task in a schedule
+#[derive(Debug, Clone)]
+pub struct ScheduledTaskDescription {
+    pub name: &'static str,
+
+    /// The phase the task runs during, if any
+    pub phase: Option<&'static str>,
+
+    /// Tasks that became ready at the same time while resolving the ordering share a batch
+    pub batch: usize,
+
+    /// The stage the task was placed in. Only schedules that group tasks into stages set this.
+    pub stage: Option<usize>,
+
+    pub read_all: bool,
+    pub write_all: bool,
+    pub reads: Vec<ResourceId>,
+    pub writes: Vec<ResourceId>,
+}
+
+/// A snapshot of how a schedule was resolved: the order tasks run in, which phase they belong to,
+/// what they access, and why each ordering requirement exists. Output is sorted so that it can be
+/// diffed between builds.
+#[derive(Debug, Clone)]
+pub struct ScheduleDescription {
+    /// All tasks, in the resolved execution order
+    pub tasks: Vec<ScheduledTaskDescription>,
+
+    /// All phases, in the resolved order
+    pub phases: Vec<&'static str>,
+
+    pub edges: Vec<ScheduleEdge>,
+
+    /// Indices into `tasks` for each stage. Empty if the schedule doesn't group tasks into stages.
+    pub stages: Vec<Vec<usize>>,
+}
+
+impl ScheduleDescription {
+    /// Group tasks into stages. The indices refer to `tasks`
+    pub(super) fn set_stages(&mut self, stages: Vec<Vec<usize>>) {
+        for (stage_index, stage) in stages.iter().enumerate() {
+            for task_index in stage {
+                self.tasks[*task_index].stage = Some(stage_index);
+            }
+        }
+
+        self.stages = stages;
+    }
+
+    /// Produces a graphviz digraph. Tasks are boxes, phases are ellipses, and each edge is
+    /// labeled with the reason it exists. Tasks are clustered by stage if stages are known.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph schedule {{").unwrap();
+        writeln!(dot, "    node [shape=box];").unwrap();
+
+        for phase in &self.phases {
+            writeln!(dot, "    \"{}\" [shape=ellipse];", escape(phase)).unwrap();
+        }
+
+        let write_task = |dot: &mut String, indent: &str, task: &ScheduledTaskDescription| {
+            writeln!(
+                dot,
+                "{}\"{}\" [label=\"{}\\nreads: {}\\nwrites: {}\"];",
+                indent,
+                escape(task.name),
+                escape(task.name),
+                escape(&access_list(task.read_all, &task.reads)),
+                escape(&access_list(task.write_all, &task.writes))
+            )
+            .unwrap();
+        };
+
+        if self.stages.is_empty() {
+            for task in &self.tasks {
+                write_task(&mut dot, "    ", task);
+            }
+        } else {
+            for (stage_index, stage) in self.stages.iter().enumerate() {
+                writeln!(dot, "    subgraph cluster_stage_{} {{", stage_index).unwrap();
+                writeln!(dot, "        label=\"stage {}\";", stage_index).unwrap();
+                for task_index in stage {
+                    write_task(&mut dot, "        ", &self.tasks[*task_index]);
+                }
+                writeln!(dot, "    }}").unwrap();
+            }
+        }
+
+        for edge in &self.edges {
+            writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                escape(edge.before),
+                escape(edge.after),
+                edge.reason.as_str()
+            )
+            .unwrap();
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+
+    /// Produces a JSON document with the same information as the description
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        writeln!(json, "{{").unwrap();
+
+        writeln!(json, "  \"tasks\": [").unwrap();
+        for (task_index, task) in self.tasks.iter().enumerate() {
+            let phase = match task.phase {
+                Some(phase) => format!("\"{}\"", escape(phase)),
+                None => "null".to_string(),
+            };
+
+            let stage = match task.stage {
+                Some(stage) => stage.to_string(),
+                None => "null".to_string(),
+            };
+
+            writeln!(json, "    {{").unwrap();
+            writeln!(json, "      \"name\": \"{}\",", escape(task.name)).unwrap();
+            writeln!(json, "      \"phase\": {},", phase).unwrap();
+            writeln!(json, "      \"batch\": {},", task.batch).unwrap();
+            writeln!(json, "      \"stage\": {},", stage).unwrap();
+            writeln!(json, "      \"read_all\": {},", task.read_all).unwrap();
+            writeln!(json, "      \"write_all\": {},", task.write_all).unwrap();
+            writeln!(
+                json,
+                "      \"reads\": {},",
+                json_resource_list(&task.reads)
+            )
+            .unwrap();
+            writeln!(
+                json,
+                "      \"writes\": {}",
+                json_resource_list(&task.writes)
+            )
+            .unwrap();
+            writeln!(json, "    }}{}", separator(task_index, self.tasks.len())).unwrap();
+        }
+        writeln!(json, "  ],").unwrap();
+
+        writeln!(json, "  \"phases\": [").unwrap();
+        for (phase_index, phase) in self.phases.iter().enumerate() {
+            writeln!(
+                json,
+                "    \"{}\"{}",
+                escape(phase),
+                separator(phase_index, self.phases.len())
+            )
+            .unwrap();
+        }
+        writeln!(json, "  ],").unwrap();
+
+        writeln!(json, "  \"edges\": [").unwrap();
+        for (edge_index, edge) in self.edges.iter().enumerate() {
+            writeln!(
+                json,
+                "    {{ \"before\": \"{}\", \"after\": \"{}\", \"reason\": \"{}\" }}{}",
+                escape(edge.before),
+                escape(edge.after),
+                edge.reason.as_str(),
+                separator(edge_index, self.edges.len())
+            )
+            .unwrap();
+        }
+        writeln!(json, "  ],").unwrap();
+
+        writeln!(json, "  \"stages\": [").unwrap();
+        for (stage_index, stage) in self.stages.iter().enumerate() {
+            let task_indices: Vec<String> = stage.iter().map(|x| x.to_string()).collect();
+            writeln!(
+                json,
+                "    [{}]{}",
+                task_indices.join(", "),
+                separator(stage_index, self.stages.len())
+            )
+            .unwrap();
+        }
+        writeln!(json, "  ]").unwrap();
+
+        writeln!(json, "}}").unwrap();
+        json
+    }
+}
+
+fn access_list(all: bool, resources: &[ResourceId]) -> String {
+    if all {
+        return "*".to_string();
+    }
+
+    let names: Vec<_> = resources.iter().map(|x| x.type_name()).collect();
+    names.join(", ")
+}
+
+fn json_resource_list(resources: &[ResourceId]) -> String {
+    let names: Vec<String> = resources
+        .iter()
+        .map(|x| format!("\"{}\"", escape(x.type_name())))
+        .collect();
+    format!("[{}]", names.join(", "))
+}
+
+fn separator(index: usize, len: usize) -> &'static str {
+    if index + 1 < len {
+        ","
+    } else {
+        ""
+    }
+}
+
+// Escapes a string so it can be placed within double quotes. The rules for DOT and JSON are the
+// same for everything a type name could contain.
+pub(super) fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_tasks::*;
+    use crate::TaskScheduleBuilderMultiThread;
+
+    fn build_world() -> crate::World {
+        crate::test_tasks::build_world()
+            .with_task::<IncrementATask>()
+            .with_task::<CopyAToBTask>()
+            .build()
+    }
+
+    fn has_edge(
+        description: &ScheduleDescription,
+        before: &'static str,
+        after: &'static str,
+        reason: ScheduleEdgeReason,
+    ) -> bool {
+        description.edges.contains(&ScheduleEdge {
+            before,
+            after,
+            reason,
+        })
+    }
+
+    #[test]
+    fn test_describe_dependency_list() {
+        let world = build_world();
+        let description = world.task_list.describe();
+
+        let increment_a = core::any::type_name::<IncrementATask>();
+        let copy_a_to_b = core::any::type_name::<CopyAToBTask>();
+        let phase_first = core::any::type_name::<PhaseFirst>();
+        let phase_second = core::any::type_name::<PhaseSecond>();
+
+        let names: Vec<_> = description.tasks.iter().map(|x| x.name).collect();
+        assert_eq!(names, vec![increment_a, copy_a_to_b]);
+        assert_eq!(description.phases, vec![phase_first, phase_second]);
+
+        assert_eq!(description.tasks[0].phase, Some(phase_first));
+        assert_eq!(
+            description.tasks[1].reads,
+            vec![ResourceId::new::<CounterA>()]
+        );
+        assert_eq!(
+            description.tasks[1].writes,
+            vec![ResourceId::new::<CounterB>()]
+        );
+        assert!(description.stages.is_empty());
+
+        assert!(has_edge(
+            &description,
+            phase_first,
+            phase_second,
+            ScheduleEdgeReason::After
+        ));
+        assert!(has_edge(
+            &description,
+            increment_a,
+            phase_first,
+            ScheduleEdgeReason::During
+        ));
+        assert!(has_edge(
+            &description,
+            phase_first,
+            copy_a_to_b,
+            ScheduleEdgeReason::During
+        ));
+    }
+
+    #[test]
+    fn test_describe_stages() {
+        let world = build_world();
+        let schedule = TaskScheduleBuilderMultiThread::new(world.task_list).build();
+        let description = schedule.describe();
+
+        assert_eq!(description.stages, vec![vec![0], vec![1]]);
+        assert_eq!(description.tasks[1].stage, Some(1));
+    }
+
+    #[test]
+    fn test_export() {
+        let world = build_world();
+        let schedule = TaskScheduleBuilderMultiThread::new(world.task_list).build();
+        let description = schedule.describe();
+
+        let dot = description.to_dot();
+        assert!(dot.starts_with("digraph schedule {"));
+        assert!(dot.contains("subgraph cluster_stage_1 {"));
+        assert!(dot.contains(&format!(
+            "\"{}\" -> \"{}\" [label=\"after\"];",
+            core::any::type_name::<PhaseFirst>(),
+            core::any::type_name::<PhaseSecond>()
+        )));
+
+        let json = description.to_json();
+        assert!(json.contains(&format!(
+            "\"name\": \"{}\"",
+            core::any::type_name::<CopyAToBTask>()
+        )));
+        assert!(json.contains("\"reason\": \"during\""));
+        assert!(json.contains("[0],"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a\"b\\c\n"), "a\\\"b\\\\c\\n");
+    }
+}
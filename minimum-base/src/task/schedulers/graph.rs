@@ -0,0 +1,322 @@
+use std::prelude::v1::*;
+
+use super::FixedTimestepSubsteps;
+use super::ResourceMap;
+use super::ScheduleDescription;
+use super::ScheduleSegment;
+use super::Scheduler;
+use super::TaskConfig;
+use super::TaskContextFlags;
+use super::TaskDependencyList;
+use super::TaskProfilerRecorder;
+use super::TaskWithFilter;
+use super::TrustCell;
+
+#[cfg(feature = "std")]
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+pub struct TaskScheduleBuilderGraph {
+    execution_order: Vec<TaskConfig>,
+    dependencies: Vec<Vec<usize>>,
+    description: ScheduleDescription,
+}
+
+impl TaskScheduleBuilderGraph {
+    pub fn new(execution_order: TaskDependencyList) -> Self {
+        TaskScheduleBuilderGraph {
+            description: execution_order.describe(),
+            execution_order: execution_order.execution_order,
+            dependencies: execution_order.dependencies,
+        }
+    }
+
+    pub fn build(self) -> TaskScheduleGraph {
+        let task_count = self.execution_order.len();
+
+        // Find every pair of tasks that can't run at the same time because of their reads/writes
+        let mut conflicts = vec![vec![]; task_count];
+        for i in 0..task_count {
+            for j in (i + 1)..task_count {
+                if self.execution_order[i].conflicts_with(&self.execution_order[j]) {
+                    conflicts[i].push(j);
+                    conflicts[j].push(i);
+                }
+            }
+        }
+
+        // Invert the dependencies so that when a task finishes we know who is waiting on it
+        let mut dependents = vec![vec![]; task_count];
+        for (task_index, task_dependencies) in self.dependencies.iter().enumerate() {
+            for dependency in task_dependencies {
+                dependents[*dependency].push(task_index);
+            }
+        }
+
+        let tasks: Vec<_> = self
+            .execution_order
+            .into_iter()
+            .map(TaskWithFilter::new)
+            .collect();
+
+        let segments = ScheduleSegment::split(&tasks, |task| task);
+
+        TaskScheduleGraph {
+            tasks,
+            segments,
+            dependents,
+            conflicts,
+            description: self.description,
+        }
+    }
+}
+
+/// Runs each task as soon as everything it depends on has finished and no running task conflicts
+/// with its reads/writes. Unlike `TaskScheduleMultiThread`, this is not limited to running adjacent
+/// tasks together, so independent tasks can overlap across phase boundaries. The exception is fixed
+/// timestep phases, which must finish every repetition before anything after them starts.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub struct TaskScheduleGraph {
+    // Indexed the same as the TaskDependencyList's execution order
+    tasks: Vec<TaskWithFilter>,
+
+    // Each segment runs to completion before the next one starts. Fixed timestep phases are split
+    // into their own segments so they can be repeated
+    segments: Vec<ScheduleSegment>,
+
+    // The tasks that are waiting on the task at the same index to finish
+    dependents: Vec<Vec<usize>>,
+
+    // The tasks that may not run at the same time as the task at the same index
+    conflicts: Vec<Vec<usize>>,
+
+    description: ScheduleDescription,
+}
+
+impl TaskScheduleGraph {
+    /// Describes the order tasks will run in. Tasks may overlap with any task they don't depend on
+    /// and don't conflict with.
+    pub fn describe(&self) -> &ScheduleDescription {
+        &self.description
+    }
+
+    pub fn step(&self, context_flags: &TaskContextFlags, resource_map: &TrustCell<ResourceMap>) {
+        let recorder = TaskProfilerRecorder::begin(resource_map);
+
+        let mut substeps = FixedTimestepSubsteps::new();
+        for segment in &self.segments {
+            for _ in 0..substeps.repetitions(segment, resource_map) {
+                self.run_segment(segment, recorder.as_ref(), context_flags, resource_map);
+            }
+        }
+
+        if let Some(recorder) = recorder {
+            recorder.finish(resource_map);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn run_segment(
+        &self,
+        segment: &ScheduleSegment,
+        recorder: Option<&TaskProfilerRecorder>,
+        context_flags: &TaskContextFlags,
+        resource_map: &TrustCell<ResourceMap>,
+    ) {
+        let state = Mutex::new(TaskScheduleGraphState::new(self, segment));
+        rayon::scope(|scope| {
+            self.start_ready_tasks(scope, &state, recorder, context_flags, resource_map);
+        });
+
+        debug_assert!(state.lock().unwrap().is_complete());
+    }
+
+    // Without std there is no thread pool. The execution order already satisfies all
+    // dependencies, so just run everything serially.
+    #[cfg(not(feature = "std"))]
+    fn run_segment(
+        &self,
+        segment: &ScheduleSegment,
+        recorder: Option<&TaskProfilerRecorder>,
+        context_flags: &TaskContextFlags,
+        resource_map: &TrustCell<ResourceMap>,
+    ) {
+        for task in &self.tasks[segment.range.clone()] {
+            TaskProfilerRecorder::run_task(recorder, task, context_flags, resource_map);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn start_ready_tasks<'s>(
+        &'s self,
+        scope: &rayon::Scope<'s>,
+        state: &'s Mutex<TaskScheduleGraphState>,
+        recorder: Option<&'s TaskProfilerRecorder>,
+        context_flags: &'s TaskContextFlags,
+        resource_map: &'s TrustCell<ResourceMap>,
+    ) {
+        let tasks_to_start = state.lock().unwrap().start_ready_tasks(self);
+
+        for task_index in tasks_to_start {
+            scope.spawn(move |scope| {
+                let task = &self.tasks[task_index];
+                TaskProfilerRecorder::run_task(recorder, task, context_flags, resource_map);
+
+                // Finishing a task may unblock its dependents, or tasks that conflicted with it
+                state.lock().unwrap().finish_task(self, task_index);
+                self.start_ready_tasks(scope, state, recorder, context_flags, resource_map);
+            });
+        }
+    }
+}
+
+impl Scheduler for TaskScheduleGraph {
+    fn build(task_list: TaskDependencyList) -> Self {
+        TaskScheduleBuilderGraph::new(task_list).build()
+    }
+
+    fn describe(&self) -> &ScheduleDescription {
+        TaskScheduleGraph::describe(self)
+    }
+
+    fn step(&self, context_flags: &TaskContextFlags, resource_map: &TrustCell<ResourceMap>) {
+        TaskScheduleGraph::step(self, context_flags, resource_map)
+    }
+}
+
+// Book-keeping for running a single segment of a TaskScheduleGraph
+#[cfg(feature = "std")]
+struct TaskScheduleGraphState {
+    // Only tasks in this range are run. Anything before it has already finished.
+    range: Range<usize>,
+
+    // How many tasks must still finish before the task at the same index can start
+    remaining_dependency_counts: Vec<usize>,
+
+    // Tasks that have no remaining dependencies but have not started, sorted by execution order
+    ready: Vec<usize>,
+
+    // Tasks that have started but not finished
+    running: Vec<usize>,
+
+    finished_count: usize,
+}
+
+#[cfg(feature = "std")]
+impl TaskScheduleGraphState {
+    fn new(schedule: &TaskScheduleGraph, segment: &ScheduleSegment) -> Self {
+        let range = segment.range.clone();
+
+        // Dependencies on earlier segments are already satisfied
+        let mut remaining_dependency_counts = vec![0; schedule.tasks.len()];
+        for task_index in range.clone() {
+            for dependent in &schedule.dependents[task_index] {
+                if range.contains(dependent) {
+                    remaining_dependency_counts[*dependent] += 1;
+                }
+            }
+        }
+
+        let ready = range
+            .clone()
+            .filter(|task_index| remaining_dependency_counts[*task_index] == 0)
+            .collect();
+
+        TaskScheduleGraphState {
+            range,
+            remaining_dependency_counts,
+            ready,
+            running: vec![],
+            finished_count: 0,
+        }
+    }
+
+    // Moves every ready task that doesn't conflict with a running task to running, returning them.
+    // Tasks earlier in the execution order get priority.
+    fn start_ready_tasks(&mut self, schedule: &TaskScheduleGraph) -> Vec<usize> {
+        let mut started = vec![];
+        let mut still_ready = vec![];
+
+        for task_index in self.ready.drain(..) {
+            let conflicts = &schedule.conflicts[task_index];
+            if self
+                .running
+                .iter()
+                .any(|running| conflicts.contains(running))
+            {
+                still_ready.push(task_index);
+            } else {
+                self.running.push(task_index);
+                started.push(task_index);
+            }
+        }
+
+        self.ready = still_ready;
+        started
+    }
+
+    fn finish_task(&mut self, schedule: &TaskScheduleGraph, task_index: usize) {
+        let position = self.running.iter().position(|x| *x == task_index).unwrap();
+        self.running.swap_remove(position);
+        self.finished_count += 1;
+
+        for dependent in &schedule.dependents[task_index] {
+            // Later segments will be run once this one is complete
+            if !self.range.contains(dependent) {
+                continue;
+            }
+
+            self.remaining_dependency_counts[*dependent] -= 1;
+            if self.remaining_dependency_counts[*dependent] == 0 {
+                self.ready.push(*dependent);
+            }
+        }
+
+        self.ready.sort();
+    }
+
+    fn is_complete(&self) -> bool {
+        self.finished_count == self.range.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_tasks::*;
+
+    fn build_world() -> crate::World {
+        crate::test_tasks::build_world()
+            .with_task::<IncrementATask>()
+            .with_task::<IncrementBTask>()
+            .with_task::<CopyAToBTask>()
+            .build()
+    }
+
+    #[test]
+    fn test_dependencies_resolved_through_phases() {
+        let world = build_world();
+        let task_list = &world.task_list;
+
+        // CopyAToB runs during the second phase, so it must wait on both tasks in the first phase
+        let copy_index = task_list.execution_order.len() - 1;
+        assert_eq!(task_list.dependencies[copy_index].len(), 2);
+        assert!(task_list.dependencies[0].is_empty());
+        assert!(task_list.dependencies[1].is_empty());
+    }
+
+    #[test]
+    fn test_step_respects_dependencies() {
+        let world = build_world();
+        let mut update_loop = crate::UpdateLoopGraph::new(world, 0);
+
+        for _ in 0..3 {
+            update_loop.step();
+        }
+
+        let resource_map = update_loop.into_resource_map();
+        assert_eq!(resource_map.fetch::<CounterA>().0, 3);
+        assert_eq!(resource_map.fetch::<CounterB>().0, 3);
+    }
+}
@@ -1,3 +1,7 @@
+mod graph;
+pub use graph::TaskScheduleBuilderGraph;
+pub use graph::TaskScheduleGraph;
+
 mod multi_thread;
 pub use multi_thread::TaskScheduleBuilderMultiThread;
 pub use multi_thread::TaskScheduleMultiThread;
@@ -6,12 +10,28 @@ mod single_thread;
 pub use single_thread::TaskScheduleBuilderSingleThread;
 pub use single_thread::TaskScheduleSingleThread;
 
-//TODO: Implement future_graph
-
+use super::FixedTimestepSubsteps;
 use super::ResourceMap;
+use super::ScheduleDescription;
+use super::ScheduleSegment;
 use super::TaskConfig;
 use super::TaskContextFlags;
 use super::TaskDependencyList;
+use super::TaskProfilerRecorder;
 use super::TaskStage;
 use super::TaskWithFilter;
 use super::TrustCell;
+
+/// Implemented by each of the schedules so that an update loop can be built around any of them
+pub trait Scheduler: Send + Sync + 'static {
+    /// Build the schedule from the list of tasks in the order they need to run
+    fn build(task_list: TaskDependencyList) -> Self
+    where
+        Self: Sized;
+
+    /// Describes the order tasks will run in
+    fn describe(&self) -> &ScheduleDescription;
+
+    /// Run all the tasks once
+    fn step(&self, context_flags: &TaskContextFlags, resource_map: &TrustCell<ResourceMap>);
+}
@@ -1,64 +1,272 @@
 use std::prelude::v1::*;
 
+use super::FixedTimestepSubsteps;
 use super::ResourceMap;
+use super::ScheduleDescription;
+use super::ScheduleSegment;
+use super::Scheduler;
 use super::TaskConfig;
 use super::TaskContextFlags;
 use super::TaskDependencyList;
+use super::TaskProfilerRecorder;
 use super::TaskStage;
 use super::TrustCell;
 
 pub struct TaskScheduleBuilderMultiThread {
     execution_order: Vec<TaskConfig>,
+    execution_batches: Vec<usize>,
+    description: ScheduleDescription,
 }
 
 impl TaskScheduleBuilderMultiThread {
     pub fn new(execution_order: TaskDependencyList) -> Self {
         TaskScheduleBuilderMultiThread {
+            description: execution_order.describe(),
             execution_order: execution_order.execution_order,
+            execution_batches: execution_order.execution_batches,
         }
     }
 
-    pub fn build(self) -> TaskScheduleMultiThread {
+    pub fn build(mut self) -> TaskScheduleMultiThread {
         let mut all_stages = vec![];
         let mut current_stage = TaskStage::new();
+        let mut current_batch = None;
 
-        for task in self.execution_order {
-            if current_stage.can_add_task(&task) {
-                println!("add to stage");
-                current_stage.add_task(task);
-            } else {
-                println!("push stage");
+        // Indices into the execution order for each stage, used to describe the schedule
+        let mut stage_task_indices = vec![];
+        let mut current_stage_task_indices = vec![];
+
+        let tasks = self.execution_order.into_iter().zip(self.execution_batches);
+        for (task_index, (task, batch)) in tasks.enumerate() {
+            // Tasks from different batches may depend on each other, so they can't share a stage.
+            // This also keeps tasks in fixed timestep phases out of stages with other tasks, since
+            // they are never in the same batch.
+            let fits_in_current_stage =
+                current_batch == Some(batch) && current_stage.can_add_task(&task);
+
+            if !fits_in_current_stage && !current_stage.is_empty() {
                 all_stages.push(current_stage);
                 current_stage = TaskStage::new();
-                current_stage.add_task(task);
+                stage_task_indices.push(current_stage_task_indices);
+                current_stage_task_indices = vec![];
             }
+
+            current_batch = Some(batch);
+            current_stage.add_task(task);
+            current_stage_task_indices.push(task_index);
         }
 
         if !current_stage.is_empty() {
-            println!("push stage");
             all_stages.push(current_stage);
+            stage_task_indices.push(current_stage_task_indices);
         }
 
-        TaskScheduleMultiThread::new(all_stages)
+        self.description.set_stages(stage_task_indices);
+        TaskScheduleMultiThread::new(all_stages, self.description)
     }
 }
 
 pub struct TaskScheduleMultiThread {
     stages: Vec<TaskStage>,
+
+    // Fixed timestep phases are split into their own segments so they can be repeated
+    segments: Vec<ScheduleSegment>,
+
+    description: ScheduleDescription,
 }
 
 impl TaskScheduleMultiThread {
-    pub fn new(stages: Vec<TaskStage>) -> Self {
-        TaskScheduleMultiThread { stages }
+    pub fn new(stages: Vec<TaskStage>, description: ScheduleDescription) -> Self {
+        let segments = ScheduleSegment::split(&stages, |stage| &stage.tasks()[0]);
+        TaskScheduleMultiThread {
+            stages,
+            segments,
+            description,
+        }
+    }
+
+    /// Describes the order tasks will run in, including which tasks share a stage
+    pub fn describe(&self) -> &ScheduleDescription {
+        &self.description
+    }
+
+    /// Number of stages. Every task in a stage may run at the same time, and stages run one after
+    /// the other
+    pub fn stage_count(&self) -> usize {
+        self.stages.len()
     }
 
     pub fn step(&self, context_flags: &TaskContextFlags, resource_map: &TrustCell<ResourceMap>) {
-        for stage in &self.stages {
-            println!("----------------------");
-            //TODO: Use rayon or something to make this actually MT
+        let recorder = TaskProfilerRecorder::begin(resource_map);
+        let mut substeps = FixedTimestepSubsteps::new();
+        for segment in &self.segments {
+            for _ in 0..substeps.repetitions(segment, resource_map) {
+                for stage in &self.stages[segment.range.clone()] {
+                    Self::run_stage(stage, recorder.as_ref(), context_flags, resource_map);
+                }
+            }
+        }
+
+        if let Some(recorder) = recorder {
+            recorder.finish(resource_map);
+        }
+    }
+
+    // TaskStage::can_add_task guarantees that no two tasks in a stage touch the same resource in
+    // a conflicting way, so everything in the stage can be run on the thread pool at once
+    #[cfg(feature = "std")]
+    fn run_stage(
+        stage: &TaskStage,
+        recorder: Option<&TaskProfilerRecorder>,
+        context_flags: &TaskContextFlags,
+        resource_map: &TrustCell<ResourceMap>,
+    ) {
+        // Avoid the overhead of going through the thread pool when there is nothing to overlap
+        if stage.tasks().len() == 1 {
+            let task = &stage.tasks()[0];
+            TaskProfilerRecorder::run_task(recorder, task, context_flags, resource_map);
+            return;
+        }
+
+        rayon::scope(|scope| {
             for task in stage.tasks() {
-                task.run_if_filter_passes(context_flags, resource_map);
+                scope.spawn(move |_| {
+                    TaskProfilerRecorder::run_task(recorder, task, context_flags, resource_map)
+                });
             }
+        });
+    }
+
+    // Without std there is no thread pool, so fall back to running the stage serially
+    #[cfg(not(feature = "std"))]
+    fn run_stage(
+        stage: &TaskStage,
+        recorder: Option<&TaskProfilerRecorder>,
+        context_flags: &TaskContextFlags,
+        resource_map: &TrustCell<ResourceMap>,
+    ) {
+        for task in stage.tasks() {
+            TaskProfilerRecorder::run_task(recorder, task, context_flags, resource_map);
+        }
+    }
+}
+
+impl Scheduler for TaskScheduleMultiThread {
+    fn build(task_list: TaskDependencyList) -> Self {
+        TaskScheduleBuilderMultiThread::new(task_list).build()
+    }
+
+    fn describe(&self) -> &ScheduleDescription {
+        TaskScheduleMultiThread::describe(self)
+    }
+
+    fn step(&self, context_flags: &TaskContextFlags, resource_map: &TrustCell<ResourceMap>) {
+        TaskScheduleMultiThread::step(self, context_flags, resource_map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{DataRequirement, Write};
+    use crate::test_tasks::*;
+    use crate::{ResourceTask, ResourceTaskImpl};
+
+    struct IncrementAAgain;
+    type IncrementAAgainTask = ResourceTask<IncrementAAgain>;
+    impl ResourceTaskImpl for IncrementAAgain {
+        type RequiredResources = (Write<CounterA>,);
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<PhaseFirst>();
         }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+            let (mut counter,) = data;
+            counter.0 += 1;
+        }
+    }
+
+    #[test]
+    fn test_independent_tasks_share_stage() {
+        let world = build_world()
+            .with_task::<IncrementATask>()
+            .with_task::<IncrementBTask>()
+            .build();
+
+        let schedule = TaskScheduleBuilderMultiThread::new(world.task_list).build();
+        assert_eq!(schedule.stage_count(), 1);
+    }
+
+    #[test]
+    fn test_conflicting_tasks_split_stage() {
+        let world = build_world()
+            .with_task::<IncrementATask>()
+            .with_task::<IncrementAAgainTask>()
+            .build();
+
+        let schedule = TaskScheduleBuilderMultiThread::new(world.task_list).build();
+        assert_eq!(schedule.stage_count(), 2);
+    }
+
+    // Takes a mutable borrow on the whole resource map
+    struct WriteAll;
+    type WriteAllTask = crate::WriteAllTask<WriteAll>;
+    impl crate::WriteAllTaskImpl for WriteAll {
+        fn configure(_config: &mut TaskConfig) {}
+
+        fn run(_context_flags: &TaskContextFlags, _resource_map: &mut ResourceMap) {}
+    }
+
+    // Doesn't fetch anything, but running it still borrows the resource map
+    struct NoResources;
+    type NoResourcesTask = ResourceTask<NoResources>;
+    impl ResourceTaskImpl for NoResources {
+        type RequiredResources = ();
+
+        fn configure(_config: &mut TaskConfig) {}
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            _data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+        }
+    }
+
+    #[test]
+    fn test_write_all_runs_alone() {
+        let world = build_world()
+            .with_task::<WriteAllTask>()
+            .with_task::<NoResourcesTask>()
+            .build();
+
+        let schedule = TaskScheduleBuilderMultiThread::new(world.task_list).build();
+        assert_eq!(schedule.stage_count(), 2);
+
+        // Stepping would panic on the resource map borrow if they shared a stage
+        let mut update_loop = build_world()
+            .with_task::<WriteAllTask>()
+            .with_task::<NoResourcesTask>()
+            .build_update_loop_multi_threaded(0);
+
+        update_loop.step();
+    }
+
+    #[test]
+    fn test_step_respects_dependencies() {
+        let mut update_loop = build_world()
+            .with_task::<IncrementATask>()
+            .with_task::<CopyAToBTask>()
+            .build_update_loop_multi_threaded(0);
+
+        update_loop.step();
+        update_loop.step();
+
+        let resource_map = update_loop.into_resource_map();
+        assert_eq!(resource_map.fetch::<CounterA>().0, 2);
+        assert_eq!(resource_map.fetch::<CounterB>().0, 2);
     }
 }
@@ -1,19 +1,26 @@
 use std::prelude::v1::*;
 
+use super::FixedTimestepSubsteps;
 use super::ResourceMap;
+use super::ScheduleDescription;
+use super::ScheduleSegment;
+use super::Scheduler;
 use super::TaskConfig;
 use super::TaskContextFlags;
 use super::TaskDependencyList;
+use super::TaskProfilerRecorder;
 use super::TaskWithFilter;
 use super::TrustCell;
 
 pub struct TaskScheduleBuilderSingleThread {
     execution_order: Vec<TaskConfig>,
+    description: ScheduleDescription,
 }
 
 impl TaskScheduleBuilderSingleThread {
     pub fn new(execution_order: TaskDependencyList) -> Self {
         TaskScheduleBuilderSingleThread {
+            description: execution_order.describe(),
             execution_order: execution_order.execution_order,
         }
     }
@@ -24,22 +31,66 @@ impl TaskScheduleBuilderSingleThread {
             tasks.push(TaskWithFilter::new(task_config));
         }
 
-        TaskScheduleSingleThread::new(tasks)
+        TaskScheduleSingleThread::new(tasks, self.description)
     }
 }
 
 pub struct TaskScheduleSingleThread {
     tasks: Vec<TaskWithFilter>,
+
+    // Fixed timestep phases are split into their own segments so they can be repeated
+    segments: Vec<ScheduleSegment>,
+
+    description: ScheduleDescription,
 }
 
 impl TaskScheduleSingleThread {
-    pub fn new(tasks: Vec<TaskWithFilter>) -> Self {
-        TaskScheduleSingleThread { tasks }
+    pub fn new(tasks: Vec<TaskWithFilter>, description: ScheduleDescription) -> Self {
+        let segments = ScheduleSegment::split(&tasks, |task| task);
+        TaskScheduleSingleThread {
+            tasks,
+            segments,
+            description,
+        }
+    }
+
+    /// Describes the order tasks will run in
+    pub fn describe(&self) -> &ScheduleDescription {
+        &self.description
     }
 
     pub fn step(&self, context_flags: &TaskContextFlags, resource_map: &TrustCell<ResourceMap>) {
-        for task in &self.tasks {
-            task.run_if_filter_passes(context_flags, resource_map);
+        let recorder = TaskProfilerRecorder::begin(resource_map);
+        let mut substeps = FixedTimestepSubsteps::new();
+        for segment in &self.segments {
+            for _ in 0..substeps.repetitions(segment, resource_map) {
+                for task in &self.tasks[segment.range.clone()] {
+                    TaskProfilerRecorder::run_task(
+                        recorder.as_ref(),
+                        task,
+                        context_flags,
+                        resource_map,
+                    );
+                }
+            }
+        }
+
+        if let Some(recorder) = recorder {
+            recorder.finish(resource_map);
         }
     }
 }
+
+impl Scheduler for TaskScheduleSingleThread {
+    fn build(task_list: TaskDependencyList) -> Self {
+        TaskScheduleBuilderSingleThread::new(task_list).build()
+    }
+
+    fn describe(&self) -> &ScheduleDescription {
+        TaskScheduleSingleThread::describe(self)
+    }
+
+    fn step(&self, context_flags: &TaskContextFlags, resource_map: &TrustCell<ResourceMap>) {
+        TaskScheduleSingleThread::step(self, context_flags, resource_map)
+    }
+}
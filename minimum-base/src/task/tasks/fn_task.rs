@@ -0,0 +1,215 @@
+use std::prelude::v1::*;
+
+use super::DataRequirement;
+use super::RequiresResources;
+use super::ResourceId;
+use super::ResourceMap;
+use super::Task;
+use super::TaskConfig;
+use super::TaskContextFlags;
+use super::TrustCell;
+
+use crate::resource::{Read, ReadBorrow, Resource, Write, WriteBorrow};
+
+use std::marker::PhantomData;
+
+/// Implemented for the types a function task may take as arguments (`ReadBorrow<T>`,
+/// `WriteBorrow<T>`, and optional versions of them). Maps the argument back to the requirement that
+/// fetches it, so the task's reads/writes can be inferred from the function's signature.
+pub trait FnTaskArg {
+    type Requirement: for<'a> DataRequirement<'a> + RequiresResources<ResourceId> + Send + 'static;
+}
+
+impl<'b, T: Resource> FnTaskArg for ReadBorrow<'b, T> {
+    type Requirement = Read<T>;
+}
+
+impl<'b, T: Resource> FnTaskArg for WriteBorrow<'b, T> {
+    type Requirement = Write<T>;
+}
+
+impl<'b, T: Resource> FnTaskArg for Option<ReadBorrow<'b, T>> {
+    type Requirement = Option<Read<T>>;
+}
+
+impl<'b, T: Resource> FnTaskArg for Option<WriteBorrow<'b, T>> {
+    type Requirement = Option<Write<T>>;
+}
+
+// The type a function task argument is fetched as, for a particular borrow of the resource map
+type FnTaskArgBorrow<'a, A> = <<A as FnTaskArg>::Requirement as DataRequirement<'a>>::Borrow;
+
+/// Implemented for functions that can be registered with `WorldBuilder::with_fn_task`. `Args` is a
+/// tuple of the function's argument types, and only exists so that functions with different
+/// arguments get different implementations.
+pub trait FnTaskFunction<Args>: Send + Sync + 'static {
+    type RequiredResources: for<'a> DataRequirement<'a>
+        + RequiresResources<ResourceId>
+        + Send
+        + 'static;
+
+    fn run(&self, data: <Self::RequiredResources as DataRequirement>::Borrow);
+}
+
+macro_rules! impl_fn_task_function {
+    ( $($arg:ident),* ) => {
+        impl<Func, $($arg),*> FnTaskFunction<( $( $arg, )* )> for Func
+        where
+            Func: Fn($($arg),*) + for<'a> Fn($(FnTaskArgBorrow<'a, $arg>),*) + Send + Sync + 'static,
+            $( $arg: FnTaskArg ),*
+        {
+            type RequiredResources = ( $( $arg::Requirement, )* );
+
+            #[allow(non_snake_case)]
+            fn run(&self, data: <Self::RequiredResources as DataRequirement>::Borrow) {
+                // Func implements Fn for two sets of argument types, so call it through a function
+                // that only knows about one of them
+                #[allow(clippy::too_many_arguments)]
+                fn call<$($arg),*>(f: impl Fn($($arg),*), $($arg: $arg),*) {
+                    f($($arg),*)
+                }
+
+                let ( $( $arg, )* ) = data;
+                call(self, $($arg),*)
+            }
+        }
+    }
+}
+
+mod impl_fn_task_function {
+    #![cfg_attr(rustfmt, rustfmt_skip)]
+
+    use super::*;
+
+    impl_fn_task_function!();
+    impl_fn_task_function!(A);
+    impl_fn_task_function!(A, B);
+    impl_fn_task_function!(A, B, C);
+    impl_fn_task_function!(A, B, C, D);
+    impl_fn_task_function!(A, B, C, D, E);
+    impl_fn_task_function!(A, B, C, D, E, F);
+    impl_fn_task_function!(A, B, C, D, E, F, G);
+    impl_fn_task_function!(A, B, C, D, E, F, G, H);
+    impl_fn_task_function!(A, B, C, D, E, F, G, H, I);
+    impl_fn_task_function!(A, B, C, D, E, F, G, H, I, J);
+    impl_fn_task_function!(A, B, C, D, E, F, G, H, I, J, K);
+    impl_fn_task_function!(A, B, C, D, E, F, G, H, I, J, K, L);
+}
+
+/// Wraps a function so that it can be run as a task. Created by `WorldBuilder::with_fn_task`
+pub struct FnTask<F, Args> {
+    function: F,
+    phantom_data: PhantomData<fn() -> Args>,
+}
+
+impl<F, Args> FnTask<F, Args>
+where
+    F: FnTaskFunction<Args>,
+    Args: 'static,
+{
+    pub fn new(function: F) -> Self {
+        FnTask {
+            function,
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Adds the reads/writes inferred from the function's arguments
+    pub fn configure(config: &mut TaskConfig) {
+        for read in F::RequiredResources::reads() {
+            config.add_read(read);
+        }
+
+        for write in F::RequiredResources::writes() {
+            config.add_write(write);
+        }
+    }
+}
+
+impl<F, Args> Task for FnTask<F, Args>
+where
+    F: FnTaskFunction<Args>,
+    Args: 'static,
+{
+    fn run(&self, _context_flags: &TaskContextFlags, resource_map: &TrustCell<ResourceMap>) {
+        let resource_map_borrowed = resource_map.borrow();
+        let fetched = F::RequiredResources::fetch(&resource_map_borrowed);
+        self.function.run(fetched);
+    }
+
+    // The wrapper's name isn't useful, use the function's
+    fn name(&self) -> &'static str {
+        core::any::type_name::<F>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resource::{ReadBorrow, WriteBorrow};
+    use crate::task::{PhaseEndFrame, PhaseFrameBegin};
+    use crate::WorldBuilder;
+
+    struct Counter(u32);
+    struct Total(u32);
+    struct Missing;
+
+    fn increment(mut counter: WriteBorrow<Counter>) {
+        counter.0 += 1;
+    }
+
+    fn accumulate(
+        counter: ReadBorrow<Counter>,
+        mut total: WriteBorrow<Total>,
+        missing: Option<ReadBorrow<Missing>>,
+    ) {
+        assert!(missing.is_none());
+        total.0 += counter.0;
+    }
+
+    fn build_world() -> WorldBuilder {
+        WorldBuilder::new()
+            .with_resource(Counter(0))
+            .with_resource(Total(0))
+            .with_default_phases()
+            .with_fn_task(increment, |config| {
+                config.this_runs_during_phase::<PhaseFrameBegin>();
+            })
+            .with_fn_task(accumulate, |config| {
+                config.this_runs_during_phase::<PhaseEndFrame>();
+            })
+    }
+
+    #[test]
+    fn test_fn_task_resources() {
+        let world = build_world().build();
+        let task_list = &world.task_list;
+        let accumulate = task_list
+            .execution_order
+            .iter()
+            .find(|config| config.reads.len() == 2)
+            .unwrap();
+
+        assert_eq!(accumulate.writes.len(), 1);
+    }
+
+    #[test]
+    fn test_fn_task_run() {
+        let mut update_loop = build_world().build_update_loop_single_threaded(0);
+        for _ in 0..3 {
+            update_loop.step();
+        }
+
+        // The total is added up after each increment: 1 + 2 + 3
+        let resource_map = update_loop.into_resource_map();
+        assert_eq!(resource_map.fetch::<Counter>().0, 3);
+        assert_eq!(resource_map.fetch::<Total>().0, 6);
+    }
+
+    #[test]
+    fn test_fn_task_name() {
+        let update_loop = build_world().build_update_loop_single_threaded(0);
+        let description = update_loop.describe_schedule().to_json();
+        assert!(description.contains("fn_task::tests::increment"));
+        assert!(description.contains("fn_task::tests::accumulate"));
+    }
+}
@@ -1,5 +1,7 @@
+pub mod fn_task;
 pub mod read_all_task;
 pub mod resource_task;
+pub mod sub_schedule_task;
 pub mod write_all_task;
 
 use super::DataRequirement;
@@ -15,7 +15,7 @@ use std::marker::PhantomData;
 
 /// Simple trait that can be wrapped in a ReadResourceMapTask to get immutable access on the resource
 /// map.
-pub trait ReadAllTaskImpl: 'static + Send {
+pub trait ReadAllTaskImpl: 'static + Send + Sync {
     fn configure(config: &mut TaskConfig);
     fn run(context_flags: &TaskContextFlags, resource_map: &ResourceMap);
 }
@@ -14,7 +14,7 @@ use std::marker::PhantomData;
 
 /// A trait that can be implemented and wrapped inside a ResourceTask<T> for typical tasks that fetch
 /// a few resources
-pub trait ResourceTaskImpl: 'static + Send {
+pub trait ResourceTaskImpl: 'static + Send + Sync {
     type RequiredResources: for<'a> DataRequirement<'a>
         + RequiresResources<ResourceId>
         + Send
@@ -0,0 +1,67 @@
+use std::prelude::v1::*;
+
+use super::ResourceMap;
+use super::Task;
+use super::TaskConfig;
+use super::TaskContextFlags;
+use super::TaskFactory;
+use super::TrustCell;
+
+use crate::world::NamedSchedules;
+
+use std::marker::PhantomData;
+
+/// Simple trait that can be wrapped in a SubScheduleTask to step a named schedule (see
+/// `WorldBuilder::with_schedule`) from inside another schedule
+pub trait SubScheduleTaskImpl: 'static + Send + Sync {
+    /// The name the schedule was added to the world with
+    fn schedule_name() -> &'static str;
+
+    fn configure(config: &mut TaskConfig);
+
+    /// How many times to step the schedule each time the task runs (i.e. to resimulate several
+    /// frames after a rollback). Defaults to once.
+    fn step_count(_context_flags: &TaskContextFlags, _resource_map: &ResourceMap) -> usize {
+        1
+    }
+}
+
+/// Helper struct that steps the named schedule. The resources the schedule's tasks use aren't known
+/// when this task is configured, so it is configured to write all resources.
+#[derive(Default)]
+pub struct SubScheduleTask<T: SubScheduleTaskImpl> {
+    phantom_data: PhantomData<T>,
+}
+
+impl<T: SubScheduleTaskImpl> SubScheduleTask<T> {
+    fn new() -> Self {
+        SubScheduleTask {
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<T: SubScheduleTaskImpl> TaskFactory for SubScheduleTask<T> {
+    fn configure(config: &mut TaskConfig) {
+        T::configure(config);
+
+        config.write_all();
+    }
+
+    fn create() -> Box<dyn Task> {
+        Box::new(Self::new())
+    }
+}
+
+impl<T: SubScheduleTaskImpl + Send> Task for SubScheduleTask<T> {
+    fn run(&self, context_flags: &TaskContextFlags, resource_map: &TrustCell<ResourceMap>) {
+        // The schedule's tasks borrow resources from the map themselves, so it is only borrowed
+        // for reading here
+        let resource_map_borrowed = resource_map.borrow();
+        let step_count = T::step_count(context_flags, &resource_map_borrowed);
+        let named_schedules = resource_map_borrowed.fetch::<NamedSchedules>();
+        for _ in 0..step_count {
+            named_schedules.step(T::schedule_name(), context_flags, resource_map);
+        }
+    }
+}
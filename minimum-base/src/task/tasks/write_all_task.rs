@@ -11,7 +11,7 @@ use std::marker::PhantomData;
 
 /// Simple trait that can be wrapped in a WriteAllTask to get mutable access on the resource
 /// map.
-pub trait WriteAllTaskImpl: 'static + Send {
+pub trait WriteAllTaskImpl: 'static + Send + Sync {
     fn configure(config: &mut TaskConfig);
     fn run(context_flags: &TaskContextFlags, resource_map: &mut ResourceMap);
 }
@@ -25,8 +25,14 @@ pub trait Phase: 'static {
     fn configure(config: &mut TaskConfig);
 }
 
-/// Minimum interface required to call functions on a task
-pub trait Task: 'static + Send {
+/// Minimum interface required to call functions on a task. Tasks may be run from any thread in
+/// the multi-threaded scheduler, so they must be Send + Sync
+pub trait Task: 'static + Send + Sync {
     /// Called when the task should be run
     fn run(&self, context_flags: &TaskContextFlags, resource_map: &TrustCell<ResourceMap>);
+
+    /// Name of the task for debug purposes
+    fn name(&self) -> &'static str {
+        core::any::type_name::<Self>()
+    }
 }
@@ -0,0 +1,87 @@
+//! Resources, phases and tasks shared by the unit tests. Tests that need something more specific
+//! define it next to the test.
+
+use crate::resource::{DataRequirement, Read, Write};
+use crate::task::{Phase, TaskConfig, TaskContextFlags};
+use crate::{ResourceTask, ResourceTaskImpl, WorldBuilder};
+
+pub(crate) struct CounterA(pub u32);
+pub(crate) struct CounterB(pub u32);
+
+pub(crate) struct PhaseFirst;
+impl Phase for PhaseFirst {
+    fn configure(_config: &mut TaskConfig) {}
+}
+
+pub(crate) struct PhaseSecond;
+impl Phase for PhaseSecond {
+    fn configure(config: &mut TaskConfig) {
+        config.this_runs_after_phase::<PhaseFirst>();
+    }
+}
+
+/// Adds both counters and phases
+pub(crate) fn build_world() -> WorldBuilder {
+    WorldBuilder::new()
+        .with_resource(CounterA(0))
+        .with_resource(CounterB(0))
+        .with_phase::<PhaseFirst>()
+        .with_phase::<PhaseSecond>()
+}
+
+// Increments CounterA during PhaseFirst
+pub(crate) struct IncrementA;
+pub(crate) type IncrementATask = ResourceTask<IncrementA>;
+impl ResourceTaskImpl for IncrementA {
+    type RequiredResources = (Write<CounterA>,);
+
+    fn configure(config: &mut TaskConfig) {
+        config.this_runs_during_phase::<PhaseFirst>();
+    }
+
+    fn run(
+        _context_flags: &TaskContextFlags,
+        data: <Self::RequiredResources as DataRequirement>::Borrow,
+    ) {
+        let (mut counter,) = data;
+        counter.0 += 1;
+    }
+}
+
+// Increments CounterB during PhaseFirst
+pub(crate) struct IncrementB;
+pub(crate) type IncrementBTask = ResourceTask<IncrementB>;
+impl ResourceTaskImpl for IncrementB {
+    type RequiredResources = (Write<CounterB>,);
+
+    fn configure(config: &mut TaskConfig) {
+        config.this_runs_during_phase::<PhaseFirst>();
+    }
+
+    fn run(
+        _context_flags: &TaskContextFlags,
+        data: <Self::RequiredResources as DataRequirement>::Borrow,
+    ) {
+        let (mut counter,) = data;
+        counter.0 += 1;
+    }
+}
+
+// Copies CounterA into CounterB during PhaseSecond
+pub(crate) struct CopyAToB;
+pub(crate) type CopyAToBTask = ResourceTask<CopyAToB>;
+impl ResourceTaskImpl for CopyAToB {
+    type RequiredResources = (Read<CounterA>, Write<CounterB>);
+
+    fn configure(config: &mut TaskConfig) {
+        config.this_runs_during_phase::<PhaseSecond>();
+    }
+
+    fn run(
+        _context_flags: &TaskContextFlags,
+        data: <Self::RequiredResources as DataRequirement>::Borrow,
+    ) {
+        let (counter_a, mut counter_b) = data;
+        counter_b.0 = counter_a.0;
+    }
+}
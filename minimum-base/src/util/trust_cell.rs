@@ -23,7 +23,6 @@ use std::{
     usize,
 };
 
-#[cfg(feature = "nightly")]
 macro_rules! borrow_panic {
     ($s:expr) => {{
         panic!(
@@ -34,18 +33,6 @@ macro_rules! borrow_panic {
     }};
 }
 
-#[cfg(not(feature = "nightly"))]
-macro_rules! borrow_panic {
-    ($s:expr) => {{
-        panic!(
-            "Tried to fetch data, but it was already borrowed{}.\n\
-             You can get the type name of the incorrectly borrowed data by enabling `shred`'s \
-             `nightly` feature.",
-            $s,
-        )
-    }};
-}
-
 /// Marker struct for an invalid borrow error
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct InvalidBorrow;
@@ -1,23 +1,30 @@
 //! Stitches together all components of minimum.
+use std::prelude::v1::*;
+
 use crate::resource::{Resource, ResourceMap};
 
 use crate::component::{
     Component, ComponentCreateQueueFlushListener, ComponentFreeHandler, ComponentRegistry,
-    ComponentStorage,
+    ComponentRemoveQueue, ComponentStorage,
 };
 
 use crate::entity::{EntityFactory, EntitySet, PendingDeleteComponent};
 use crate::task::Phase;
-use crate::task::{TaskContextFlags, TaskFactory};
+use crate::task::ScheduleChange;
+use crate::task::TaskContextFlags;
+use crate::task::{ContextFlag, ContextFlagHandle, FnTaskFunction, TaskConfig};
+use crate::task::{ScheduleDescription, TaskFactory};
 use crate::{
-    DispatchControl, TaskDependencyList, TaskDependencyListBuilder,
-    TaskScheduleBuilderSingleThread, TaskScheduleSingleThread, TrustCell,
+    DispatchControl, Scheduler, TaskDependencyList, TaskDependencyListBuildError,
+    TaskDependencyListBuilder, TaskScheduleGraph, TaskScheduleMultiThread,
+    TaskScheduleSingleThread, TrustCell,
 };
 
 /// A builder for setting up a `World`
 pub struct WorldBuilder {
     resource_map: ResourceMap,
     tasks: TaskDependencyListBuilder,
+    schedules: Vec<(&'static str, TaskDependencyListBuilder)>,
     default_component_registry: ComponentRegistry,
 }
 
@@ -28,6 +35,7 @@ impl WorldBuilder {
         WorldBuilder {
             resource_map: ResourceMap::new(),
             tasks: TaskDependencyListBuilder::new(),
+            schedules: vec![],
             default_component_registry: ComponentRegistry::new(),
         }
     }
@@ -87,6 +95,19 @@ impl WorldBuilder {
         self
     }
 
+    /// Add a function as a task. Its arguments must be `ReadBorrow<T>`/`WriteBorrow<T>` (or optional
+    /// versions of them), which determine what it reads/writes. `configure` can set anything else,
+    /// like which phase it runs during.
+    pub fn with_fn_task<Args, F, C>(mut self, function: F, configure: C) -> Self
+    where
+        F: FnTaskFunction<Args> + Clone,
+        Args: 'static,
+        C: Fn(&mut TaskConfig) + Send + Sync + 'static,
+    {
+        self.add_fn_task(function, configure);
+        self
+    }
+
     /// Add the standard phases
     pub fn with_default_phases(mut self) -> Self {
         self.add_default_phases();
@@ -102,6 +123,33 @@ impl WorldBuilder {
         self
     }
 
+    /// Add a phase that runs its tasks zero or more times per frame, depending on the time added to
+    /// the `FixedTimestep` resource. Can also be used to mark a phase that was already added.
+    pub fn with_fixed_timestep_phase<P>(mut self) -> Self
+    where
+        P: Phase,
+    {
+        self.add_fixed_timestep_phase::<P>();
+        self
+    }
+
+    /// Add a context flag, allocating a bit for it. Tasks may only filter on flags that were added.
+    pub fn with_context_flag<F>(mut self) -> Self
+    where
+        F: ContextFlag,
+    {
+        self.add_context_flag::<F>();
+        self
+    }
+
+    /// Add a schedule that runs over the same resources as the main schedule, but only when it is
+    /// stepped by name (see `NamedSchedules`). It has its own tasks and phases, and may filter on any
+    /// context flag added to the world.
+    pub fn with_schedule(mut self, name: &'static str, tasks: TaskDependencyListBuilder) -> Self {
+        self.add_schedule(name, tasks);
+        self
+    }
+
     /// Adds a resource type/instance
     pub fn add_resource<R>(&mut self, r: R)
     where
@@ -127,6 +175,15 @@ impl WorldBuilder {
         self.tasks.add_task::<T>();
     }
 
+    pub fn add_fn_task<Args, F, C>(&mut self, function: F, configure: C)
+    where
+        F: FnTaskFunction<Args> + Clone,
+        Args: 'static,
+        C: Fn(&mut TaskConfig) + Send + Sync + 'static,
+    {
+        self.tasks.add_fn_task(function, configure);
+    }
+
     pub fn add_default_phases(&mut self) {
         self.add_phase::<crate::task::PhaseFrameBegin>();
         self.add_phase::<crate::task::PhaseGatherInput>();
@@ -146,23 +203,76 @@ impl WorldBuilder {
         self.tasks.add_phase::<P>();
     }
 
+    pub fn add_fixed_timestep_phase<P>(&mut self)
+    where
+        P: Phase,
+    {
+        self.tasks.add_fixed_timestep_phase::<P>();
+    }
+
+    pub fn add_context_flag<F>(&mut self) -> ContextFlagHandle<F>
+    where
+        F: ContextFlag,
+    {
+        self.tasks.add_context_flag::<F>()
+    }
+
+    pub fn add_schedule(&mut self, name: &'static str, tasks: TaskDependencyListBuilder) {
+        assert!(
+            self.schedules.iter().all(|(existing, _)| *existing != name),
+            "Schedule {} was already added to the world",
+            name
+        );
+
+        self.schedules.push((name, tasks));
+    }
+
     /// Constructs a resource map with all minimum types properly set up
     pub fn build(mut self) -> World {
         self.add_resource(EntityFactory::new());
-        self.add_resource(DispatchControl::new(0));
+        self.add_resource(ComponentRemoveQueue::new());
+
+        // Registered after every other component so that it is freed last. Free handlers can check
+        // it to tell whether the entity is being destroyed or just losing the component.
         self.add_component(<PendingDeleteComponent as Component>::Storage::new());
 
         // Give the component registry to an entity set and add the entity set to the resources
         let entity_set = EntitySet::new(self.default_component_registry);
         self.resource_map.insert(entity_set);
 
-        // Build the task dependency list
-        let task_list = self.tasks.build();
+        // All schedules share one set of context flags, so a flag has the same bit in each of them
+        for (_name, tasks) in &self.schedules {
+            let mut context_flags = self.tasks.context_flag_registry().clone();
+            context_flags.extend(tasks.context_flag_registry());
+            self.tasks.set_context_flag_registry(context_flags);
+        }
+
+        // Build the task dependency list. The builder is kept so that the schedule can be changed
+        // after the update loop starts.
+        let task_list = self.tasks.clone().build();
+
+        let context_flags = task_list.context_flag_registry();
+        let named_task_lists = self
+            .schedules
+            .into_iter()
+            .map(|(name, mut tasks)| {
+                tasks.set_context_flag_registry((**context_flags).clone());
+                (name, tasks.build())
+            })
+            .collect();
+
+        // Flags can be set by type once the bits for them have been allocated
+        self.resource_map
+            .insert(DispatchControl::with_context_flag_registry(
+                task_list.context_flag_registry().clone(),
+            ));
 
         //TODO: Should conversion to dependency list happen later?
         World {
             resource_map: self.resource_map,
             task_list,
+            task_list_builder: self.tasks,
+            named_task_lists,
         }
     }
 
@@ -173,6 +283,19 @@ impl WorldBuilder {
         let world = self.build();
         UpdateLoopSingleThreaded::new(world, initial_context_flags)
     }
+
+    pub fn build_update_loop_multi_threaded(
+        self,
+        initial_context_flags: usize,
+    ) -> UpdateLoopMultiThreaded {
+        let world = self.build();
+        UpdateLoopMultiThreaded::new(world, initial_context_flags)
+    }
+
+    pub fn build_update_loop_graph(self, initial_context_flags: usize) -> UpdateLoopGraph {
+        let world = self.build();
+        UpdateLoopGraph::new(world, initial_context_flags)
+    }
 }
 
 /// This is an intermediate data structure between the world builder and an update loop. Generally,
@@ -180,37 +303,181 @@ impl WorldBuilder {
 pub struct World {
     pub resource_map: ResourceMap,
     pub task_list: TaskDependencyList,
+
+    // Used to rebuild task_list when tasks/phases are added or removed
+    pub task_list_builder: TaskDependencyListBuilder,
+
+    // Schedules added with WorldBuilder::with_schedule. They become the NamedSchedules resource when
+    // the update loop is created.
+    pub named_task_lists: Vec<(&'static str, TaskDependencyList)>,
+}
+
+// A named schedule, built with the same scheduler as the update loop that owns it
+type NamedSchedule = Box<dyn Scheduler>;
+
+/// Schedules that were added to the world by name (i.e. editor tools that only run while the editor
+/// is open, or a simulation that is stepped several times to catch up after a rollback). Each one
+/// runs over the same resources as the main schedule, but only when it is stepped, either with
+/// `step_schedule` on the update loop or from a task with `SubScheduleTask`. Available as a resource.
+///
+/// The schedule holds a read borrow of the resource map while it is stepped, so tasks that use
+/// `WriteAllTask` can't be part of a named schedule.
+pub struct NamedSchedules {
+    schedules: Vec<(&'static str, NamedSchedule)>,
+}
+
+impl NamedSchedules {
+    fn new<S: Scheduler>(task_lists: Vec<(&'static str, TaskDependencyList)>) -> Self {
+        NamedSchedules {
+            schedules: task_lists
+                .into_iter()
+                .map(|(name, task_list)| (name, Box::new(S::build(task_list)) as NamedSchedule))
+                .collect(),
+        }
+    }
+
+    /// Names of the schedules, in the order they were added
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.schedules.iter().map(|(name, _schedule)| *name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Describes the order the schedule's tasks will run in, or None if there is no schedule with
+    /// this name
+    pub fn describe(&self, name: &str) -> Option<&ScheduleDescription> {
+        self.get(name).map(|schedule| schedule.describe())
+    }
+
+    /// Runs all the schedule's tasks once. Panics if there is no schedule with this name.
+    pub fn step(
+        &self,
+        name: &str,
+        context_flags: &TaskContextFlags,
+        resource_map: &TrustCell<ResourceMap>,
+    ) {
+        match self.get(name) {
+            Some(schedule) => schedule.step(context_flags, resource_map),
+            None => panic!("Schedule {} was never added to the world", name),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&NamedSchedule> {
+        self.schedules
+            .iter()
+            .find(|(existing, _schedule)| *existing == name)
+            .map(|(_name, schedule)| schedule)
+    }
+}
+
+// Steps a named schedule with the flags that are set for the next frame of the main schedule
+fn step_named_schedule(resource_map: &TrustCell<ResourceMap>, name: &str) {
+    let resource_map_borrowed = resource_map.borrow();
+    let context = resource_map_borrowed
+        .fetch::<DispatchControl>()
+        .next_frame_task_context_flags();
+
+    resource_map_borrowed
+        .fetch::<NamedSchedules>()
+        .step(name, &context, resource_map);
+}
+
+// Builds a new task list with the changes applied. The changes are only kept if the new task list
+// could be built.
+fn rebuild_task_list<F>(
+    resource_map: &TrustCell<ResourceMap>,
+    task_list_builder: &mut TaskDependencyListBuilder,
+    modify: F,
+) -> Result<TaskDependencyList, TaskDependencyListBuildError>
+where
+    F: FnOnce(&mut TaskDependencyListBuilder),
+{
+    let mut new_task_list_builder = task_list_builder.clone();
+    modify(&mut new_task_list_builder);
+    let task_list = new_task_list_builder.clone().try_build()?;
+    *task_list_builder = new_task_list_builder;
+
+    resource_map
+        .borrow()
+        .fetch_mut::<DispatchControl>()
+        .set_context_flag_registry(task_list.context_flag_registry().clone());
+
+    Ok(task_list)
+}
+
+// Returns the changes requested through DispatchControl if the schedule should be rebuilt
+fn take_schedule_changes(resource_map: &TrustCell<ResourceMap>) -> Option<Vec<ScheduleChange>> {
+    resource_map
+        .borrow()
+        .fetch_mut::<DispatchControl>()
+        .take_schedule_changes()
 }
 
-pub struct UpdateLoopSingleThreaded {
+// Remembers whether rebuilding the schedule for changes requested through DispatchControl worked
+fn set_schedule_rebuild_result(
+    resource_map: &TrustCell<ResourceMap>,
+    result: &Result<(), TaskDependencyListBuildError>,
+) {
+    if let Err(e) = result {
+        log::error!("Could not rebuild schedule, it will not be changed: {}", e);
+    }
+
+    resource_map
+        .borrow()
+        .fetch_mut::<DispatchControl>()
+        .set_schedule_rebuild_result(result.clone());
+}
+
+/// Owns the resources and steps a schedule built from the world's tasks once per frame. `S` decides
+/// how the tasks are run (see the type aliases below).
+pub struct UpdateLoop<S: Scheduler> {
     resource_map: TrustCell<ResourceMap>,
-    schedule: TaskScheduleSingleThread,
+    schedule: S,
+    task_list_builder: TaskDependencyListBuilder,
 }
 
-impl UpdateLoopSingleThreaded {
+/// Runs every task one after the other on the calling thread
+pub type UpdateLoopSingleThreaded = UpdateLoop<TaskScheduleSingleThread>;
+
+/// Same as `UpdateLoopSingleThreaded`, but tasks that don't conflict on any resources will be run
+/// at the same time on a thread pool
+pub type UpdateLoopMultiThreaded = UpdateLoop<TaskScheduleMultiThread>;
+
+/// Same as `UpdateLoopSingleThreaded`, but each task is started on a thread pool as soon as the
+/// tasks it depends on have finished and it doesn't conflict with any running task
+pub type UpdateLoopGraph = UpdateLoop<TaskScheduleGraph>;
+
+impl<S: Scheduler> UpdateLoop<S> {
     pub fn new(world: World, initial_context_flags: usize) -> Self {
         *world
             .resource_map
             .fetch_mut::<DispatchControl>()
             .next_frame_context_flags_mut() = initial_context_flags;
 
-        UpdateLoopSingleThreaded {
-            resource_map: TrustCell::new(world.resource_map),
-            schedule: TaskScheduleBuilderSingleThread::new(world.task_list).build(),
+        let mut resource_map = world.resource_map;
+        resource_map.insert(NamedSchedules::new::<S>(world.named_task_lists));
+
+        UpdateLoop {
+            resource_map: TrustCell::new(resource_map),
+            schedule: S::build(world.task_list),
+            task_list_builder: world.task_list_builder,
         }
     }
 
-    pub fn step(&self) {
-        let context_flags = self
+    pub fn step(&mut self) {
+        let context = self
             .resource_map
             .borrow()
             .fetch::<DispatchControl>()
-            .next_frame_context_flags();
-        let context = TaskContextFlags::new(context_flags);
+            .next_frame_task_context_flags();
         self.schedule.step(&context, &self.resource_map);
+
+        self.apply_schedule_changes();
     }
 
-    pub fn run(&self) {
+    pub fn run(&mut self) {
         loop {
             self.step();
 
@@ -225,7 +492,330 @@ impl UpdateLoopSingleThreaded {
         }
     }
 
+    /// Describes the order tasks will run in
+    pub fn describe_schedule(&self) -> &ScheduleDescription {
+        self.schedule.describe()
+    }
+
+    /// Run all the tasks of a schedule that was added with `WorldBuilder::with_schedule` once,
+    /// without running the main schedule. Panics if there is no schedule with this name.
+    pub fn step_schedule(&mut self, name: &str) {
+        step_named_schedule(&self.resource_map, name);
+    }
+
+    /// Add/remove tasks and phases, then rebuild the schedule. If the new set of tasks can't be
+    /// ordered, the schedule is not changed and the error is returned. Tasks are recreated when the
+    /// schedule is rebuilt.
+    pub fn modify_tasks<F>(&mut self, modify: F) -> Result<(), TaskDependencyListBuildError>
+    where
+        F: FnOnce(&mut TaskDependencyListBuilder),
+    {
+        let task_list = rebuild_task_list(&self.resource_map, &mut self.task_list_builder, modify)?;
+        self.schedule = S::build(task_list);
+        Ok(())
+    }
+
+    // Rebuild the schedule if it was requested through DispatchControl
+    fn apply_schedule_changes(&mut self) {
+        if let Some(changes) = take_schedule_changes(&self.resource_map) {
+            let result = self.modify_tasks(|task_list_builder| {
+                for change in changes {
+                    change(task_list_builder);
+                }
+            });
+
+            set_schedule_rebuild_result(&self.resource_map, &result);
+        }
+    }
+
+    /// The resources the schedule runs over, i.e. to inspect or change them between frames
+    pub fn resource_map(&self) -> &TrustCell<ResourceMap> {
+        &self.resource_map
+    }
+
     pub fn into_resource_map(self) -> ResourceMap {
         self.resource_map.into_inner()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{DataRequirement, Write};
+    use crate::task::TaskContextFlags;
+    use crate::test_tasks::*;
+    use crate::{ResourceTask, ResourceTaskImpl, SubScheduleTask, SubScheduleTaskImpl};
+
+    struct MissingPhase;
+    impl Phase for MissingPhase {
+        fn configure(_config: &mut TaskConfig) {}
+    }
+
+    struct DuringMissingPhase;
+    type DuringMissingPhaseTask = ResourceTask<DuringMissingPhase>;
+    impl ResourceTaskImpl for DuringMissingPhase {
+        type RequiredResources = ();
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<MissingPhase>();
+        }
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            _data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+        }
+    }
+
+    // Swaps IncrementA for IncrementB on the first frame, and asks for an impossible change on the
+    // second frame
+    struct SwapTasks;
+    type SwapTasksTask = ResourceTask<SwapTasks>;
+    impl ResourceTaskImpl for SwapTasks {
+        type RequiredResources = (Write<DispatchControl>,);
+
+        fn configure(_config: &mut TaskConfig) {}
+
+        fn run(
+            _context_flags: &TaskContextFlags,
+            data: <Self::RequiredResources as DataRequirement>::Borrow,
+        ) {
+            let (mut dispatch_control,) = data;
+            if dispatch_control.schedule_rebuild_error().is_some() {
+                return;
+            }
+
+            let swapped = dispatch_control.is_context_flag_set::<Swapped>();
+            if !swapped {
+                dispatch_control.remove_task::<IncrementATask>();
+                dispatch_control.add_task::<IncrementBTask>();
+                dispatch_control.set_context_flag::<Swapped>();
+            } else {
+                dispatch_control.add_task::<DuringMissingPhaseTask>();
+            }
+        }
+    }
+
+    struct Swapped;
+    impl ContextFlag for Swapped {}
+
+    struct SimulationSteps(usize);
+
+    // Steps the simulation schedule as many times as SimulationSteps says
+    struct Simulate;
+    type SimulateTask = SubScheduleTask<Simulate>;
+    impl SubScheduleTaskImpl for Simulate {
+        fn schedule_name() -> &'static str {
+            "simulation"
+        }
+
+        fn configure(config: &mut TaskConfig) {
+            config.this_runs_during_phase::<PhaseFirst>();
+        }
+
+        fn step_count(_context_flags: &TaskContextFlags, resource_map: &ResourceMap) -> usize {
+            resource_map.fetch::<SimulationSteps>().0
+        }
+    }
+
+    fn build_simulation_schedule() -> TaskDependencyListBuilder {
+        let mut tasks = TaskDependencyListBuilder::new();
+        tasks.add_phase::<PhaseFirst>();
+        tasks.add_task::<IncrementBTask>();
+        tasks
+    }
+
+    fn build_world() -> WorldBuilder {
+        crate::test_tasks::build_world().with_task::<IncrementATask>()
+    }
+
+    struct FlaggedComponent;
+
+    impl Component for FlaggedComponent {
+        type Storage = crate::component::VecComponentStorage<Self>;
+    }
+
+    // Whether each freed FlaggedComponent's entity was still pending delete when it was freed
+    struct PendingDeleteWhenFreed(Vec<bool>);
+
+    struct FlaggedComponentFreeHandler;
+
+    impl ComponentFreeHandler<FlaggedComponent> for FlaggedComponentFreeHandler {
+        fn on_entities_free(
+            resource_map: &ResourceMap,
+            entity_handles: &[crate::EntityHandle],
+            _storage: &mut <FlaggedComponent as Component>::Storage,
+        ) {
+            let delete_components =
+                resource_map.fetch::<<PendingDeleteComponent as Component>::Storage>();
+            let mut pending_delete_when_freed = resource_map.fetch_mut::<PendingDeleteWhenFreed>();
+            for entity_handle in entity_handles {
+                pending_delete_when_freed
+                    .0
+                    .push(delete_components.exists(entity_handle));
+            }
+        }
+    }
+
+    #[test]
+    fn test_pending_delete_is_freed_last() {
+        let world = WorldBuilder::new()
+            .with_resource(PendingDeleteWhenFreed(vec![]))
+            .with_component_and_free_handler::<_, _, FlaggedComponentFreeHandler>(
+                <FlaggedComponent as Component>::Storage::new(),
+            )
+            .build();
+
+        let resource_map = world.resource_map;
+        let mut entity_set = resource_map.fetch_mut::<EntitySet>();
+        let entity_handles: Vec<_> = (0..2).map(|_| entity_set.allocate()).collect();
+        for entity_handle in &entity_handles {
+            resource_map
+                .fetch_mut::<<FlaggedComponent as Component>::Storage>()
+                .allocate(entity_handle, FlaggedComponent)
+                .unwrap();
+        }
+
+        // Removing the component doesn't flag the entity, destroying it does
+        entity_set.remove_component::<FlaggedComponent>(&resource_map, &entity_handles[0]);
+        entity_set.enqueue_free(
+            &entity_handles[1],
+            &mut resource_map.fetch_mut::<<PendingDeleteComponent as Component>::Storage>(),
+        );
+        entity_set.update(&resource_map);
+
+        assert_eq!(
+            resource_map.fetch::<PendingDeleteWhenFreed>().0,
+            vec![false, true]
+        );
+    }
+
+    #[test]
+    fn test_modify_tasks() {
+        let mut update_loop = build_world().build_update_loop_single_threaded(0);
+        update_loop.step();
+
+        update_loop
+            .modify_tasks(|tasks| tasks.add_task::<IncrementBTask>())
+            .unwrap();
+        update_loop.step();
+
+        update_loop
+            .modify_tasks(|tasks| {
+                assert!(tasks.remove_task::<IncrementATask>());
+            })
+            .unwrap();
+        update_loop.step();
+
+        let resource_map = update_loop.into_resource_map();
+        assert_eq!(resource_map.fetch::<CounterA>().0, 2);
+        assert_eq!(resource_map.fetch::<CounterB>().0, 2);
+    }
+
+    #[test]
+    fn test_modify_tasks_error() {
+        let mut update_loop = build_world().build_update_loop_multi_threaded(0);
+        update_loop.step();
+
+        // Neither change is kept
+        let result = update_loop.modify_tasks(|tasks| {
+            tasks.add_task::<IncrementBTask>();
+            tasks.add_task::<DuringMissingPhaseTask>();
+        });
+
+        match result {
+            Err(TaskDependencyListBuildError::MissingDependency { task, dependency }) => {
+                assert_eq!(task, core::any::type_name::<DuringMissingPhaseTask>());
+                assert_eq!(dependency, core::any::type_name::<MissingPhase>());
+            }
+            _ => panic!("Expected a missing dependency error"),
+        }
+
+        update_loop.step();
+
+        let resource_map = update_loop.into_resource_map();
+        assert_eq!(resource_map.fetch::<CounterA>().0, 2);
+        assert_eq!(resource_map.fetch::<CounterB>().0, 0);
+    }
+
+    #[test]
+    fn test_dispatch_control_modify_tasks() {
+        let mut update_loop = build_world()
+            .with_context_flag::<Swapped>()
+            .with_task::<SwapTasksTask>()
+            .build_update_loop_graph(0);
+
+        for _ in 0..4 {
+            update_loop.step();
+        }
+
+        // A runs on the first frame, then B for the rest. The change on the second frame fails, so
+        // nothing changes after that.
+        let resource_map = update_loop.into_resource_map();
+        assert_eq!(resource_map.fetch::<CounterA>().0, 1);
+        assert_eq!(resource_map.fetch::<CounterB>().0, 3);
+        assert!(resource_map
+            .fetch::<DispatchControl>()
+            .schedule_rebuild_error()
+            .is_some());
+    }
+
+    #[test]
+    fn test_step_named_schedule() {
+        let mut simulation_schedule = build_simulation_schedule();
+        simulation_schedule.add_context_flag::<Swapped>();
+
+        let mut update_loop = build_world()
+            .with_schedule("simulation", simulation_schedule)
+            .build_update_loop_single_threaded(0);
+
+        update_loop.step();
+        for _ in 0..3 {
+            update_loop.step_schedule("simulation");
+        }
+
+        let resource_map = update_loop.into_resource_map();
+        assert_eq!(resource_map.fetch::<CounterA>().0, 1);
+        assert_eq!(resource_map.fetch::<CounterB>().0, 3);
+
+        let named_schedules = resource_map.fetch::<NamedSchedules>();
+        assert_eq!(
+            named_schedules.names().collect::<Vec<_>>(),
+            vec!["simulation"]
+        );
+        assert!(named_schedules.describe("simulation").is_some());
+
+        // Flags added to a named schedule are shared with the rest of the world
+        assert!(resource_map
+            .fetch::<DispatchControl>()
+            .context_flag_registry()
+            .handle::<Swapped>()
+            .is_some());
+    }
+
+    #[test]
+    fn test_sub_schedule_task() {
+        let mut update_loop = build_world()
+            .with_resource(SimulationSteps(3))
+            .with_schedule("simulation", build_simulation_schedule())
+            .with_task::<SimulateTask>()
+            .build_update_loop_multi_threaded(0);
+
+        update_loop.step();
+        update_loop.step();
+
+        let resource_map = update_loop.into_resource_map();
+        assert_eq!(resource_map.fetch::<CounterA>().0, 2);
+        assert_eq!(resource_map.fetch::<CounterB>().0, 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "Schedule tools was never added to the world")]
+    fn test_step_missing_schedule() {
+        let mut update_loop = build_world()
+            .with_schedule("simulation", build_simulation_schedule())
+            .build_update_loop_graph(0);
+
+        update_loop.step_schedule("tools");
+    }
+}
@@ -1,11 +1,13 @@
 mod bullet;
 mod physics_body;
 mod player;
+mod switch;
 
 pub use bullet::BulletComponent;
 pub use physics_body::PhysicsBodyComponent;
 pub use physics_body::PhysicsBodyComponentFreeHandler;
 pub use player::PlayerComponent;
+pub use switch::SwitchComponent;
 
 pub use physics_body::PhysicsBodyComponentDesc;
 pub use physics_body::PhysicsBodyComponentFactory;
@@ -11,6 +11,7 @@ use nphysics::object::BodyHandle;
 use nphysics::object::ColliderDesc;
 use nphysics::object::RigidBodyDesc;
 
+use crate::framework::components::ParentComponent;
 use crate::framework::components::TransformComponent;
 use crate::framework::FrameworkComponentPrototypeDyn;
 use crate::framework::FrameworkComponentPrototype;
@@ -394,13 +395,14 @@ impl ComponentCreateQueueFlushListener for PhysicsBodyComponentFactory {
 
         //TODO: Either need two-phase entity construction or deterministic construct order.
         let transform = resource_map.fetch::<<TransformComponent as Component>::Storage>();
+        let parent = resource_map.fetch::<<ParentComponent as Component>::Storage>();
 
         let mut physics = resource_map.fetch_mut::<crate::resources::PhysicsManager>();
         let mut storage = resource_map.fetch_mut::<<PhysicsBodyComponent as Component>::Storage>();
         for (entity_handle, data) in self.prototypes.drain(..) {
             if let Some(entity) = entity_set.get_entity_ref(&entity_handle) {
                 let (center, scale, rotation) : (transform::Position, transform::Scale, transform::Rotation) =
-                    if let Some(p) = entity.get_component::<TransformComponent>(&*transform) {
+                    if let Some(p) = transform::world_transform(&entity_handle, &*transform, &*parent) {
                         (p.position(), p.scale(), p.rotation())
                     } else {
                         (transform::default_position(), transform::default_scale(), transform::default_rotation())
@@ -0,0 +1,31 @@
+#[cfg(feature = "editor")]
+use imgui_inspect_derive::Inspect;
+use crate::base::component::SlabComponentStorage;
+use crate::framework::EntityRefPrototype;
+
+// Opens the door (by freeing it) when a player comes within the radius of the switch. The door is
+// a persistent entity, so resetting the level closes it again. UpdateSwitchesTask finds the door by
+// its ID, so the reference doesn't need to be resolved when the switch is created.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, Inspect)]
+pub struct SwitchComponent {
+    door: EntityRefPrototype,
+    radius: f32,
+}
+
+impl SwitchComponent {
+    pub fn new(door: EntityRefPrototype, radius: f32) -> Self {
+        SwitchComponent { door, radius }
+    }
+
+    pub fn door(&self) -> &EntityRefPrototype {
+        &self.door
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+impl crate::base::Component for SwitchComponent {
+    type Storage = SlabComponentStorage<Self>;
+}
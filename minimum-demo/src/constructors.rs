@@ -1,7 +1,8 @@
 use crate::components;
-use crate::framework::{CloneComponentPrototype, FrameworkEntityPersistencePolicy};
+use crate::framework::{CloneComponentPrototype, EntityRefPrototype, FrameworkEntityPersistencePolicy};
 use crate::framework::components::transform;
 use crate::framework::FrameworkEntityPrototype;
+use crate::framework::persist::PersistentEntityId;
 
 use crate::framework::components::TransformComponent;
 use crate::framework::components::VelocityComponent;
@@ -38,7 +39,7 @@ pub fn create_wall(
 
     let pec = FrameworkEntityPrototype::new(
         std::path::PathBuf::from("testpath"),
-        FrameworkEntityPersistencePolicy::Persistent,
+        FrameworkEntityPersistencePolicy::Persistent(PersistentEntityId::new()),
         vec![
             Box::new(CloneComponentPrototype::new(
                 TransformComponent::new(center, transform::default_scale(), transform::default_rotation()),
@@ -59,6 +60,68 @@ pub fn create_wall(
     entity_factory.enqueue_create(Box::new(pec));
 }
 
+// Left as an example, but this is being loaded from file. The switch refers to the door by its
+// PersistentEntityId.
+pub fn create_door_and_switch(
+    door_center: glm::Vec2,
+    door_size: glm::Vec2,
+    switch_center: glm::Vec2,
+    entity_factory: &mut crate::base::EntityFactory,
+) {
+    #[cfg(feature = "dim3")]
+    let door_center = glm::vec2_to_vec3(&door_center);
+    #[cfg(feature = "dim3")]
+    let door_size = glm::vec2_to_vec3(&door_size);
+    #[cfg(feature = "dim3")]
+    let switch_center = glm::vec2_to_vec3(&switch_center);
+
+    let door_color = glm::Vec4::new(1.0, 0.5, 0.0, 1.0);
+    let switch_color = glm::Vec4::new(1.0, 0.5, 0.0, 1.0);
+    let switch_radius = 10.0;
+    let door_id = PersistentEntityId::new();
+
+    let door = FrameworkEntityPrototype::new(
+        std::path::PathBuf::from("door"),
+        FrameworkEntityPersistencePolicy::Persistent(door_id),
+        vec![
+            Box::new(CloneComponentPrototype::new(
+                TransformComponent::new(door_center, transform::default_scale(), transform::default_rotation()),
+            )),
+            Box::new(CloneComponentPrototype::new(
+                crate::framework::components::DebugDrawRectComponent::new(door_size, door_color),
+            )),
+            Box::new(PhysicsBodyComponentPrototypeBox::new(
+                door_size,
+                0.0,
+                COLLISION_GROUP_WALL_MASK,
+                COLLISION_GROUP_ALL_MASK,
+                0,
+            )),
+        ],
+    );
+
+    let switch = FrameworkEntityPrototype::new(
+        std::path::PathBuf::from("switch"),
+        FrameworkEntityPersistencePolicy::Persistent(PersistentEntityId::new()),
+        vec![
+            Box::new(CloneComponentPrototype::new(
+                TransformComponent::new(switch_center, transform::default_scale(), transform::default_rotation()),
+            )),
+            Box::new(CloneComponentPrototype::new(
+                crate::framework::components::DebugDrawCircleComponent::new(switch_radius, switch_color),
+            )),
+            Box::new(CloneComponentPrototype::new(components::SwitchComponent::new(
+                EntityRefPrototype::new(Some(door_id)),
+                // The player's radius is 15
+                switch_radius + 15.0,
+            ))),
+        ],
+    );
+
+    entity_factory.enqueue_create(Box::new(switch));
+    entity_factory.enqueue_create(Box::new(door));
+}
+
 // Left as an example, but this is being loaded from file
 pub fn create_player(entity_factory: &mut crate::base::EntityFactory) {
     let position = glm::zero();
@@ -68,7 +131,7 @@ pub fn create_player(entity_factory: &mut crate::base::EntityFactory) {
 
     let entity_prototype = FrameworkEntityPrototype::new(
         std::path::PathBuf::from("player"),
-        FrameworkEntityPersistencePolicy::Persistent,
+        FrameworkEntityPersistencePolicy::Persistent(PersistentEntityId::new()),
         vec![
             Box::new(CloneComponentPrototype::new(
                 components::PlayerComponent::new(),
@@ -163,7 +226,7 @@ pub fn create_bullet(
             )),
             Box::new(CloneComponentPrototype::new(
                 crate::framework::components::FreeAtTimeComponent::new(
-                    time_state.playing().frame_start_instant + lifetime,
+                    time_state.playing().total_time + lifetime,
                 ),
             )),
         ],
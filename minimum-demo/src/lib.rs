@@ -44,6 +44,12 @@ use crate::base::component::Component;
 use crate::base::{DispatchControl, UpdateLoopSingleThreaded, WorldBuilder};
 use rendy::wsi::winit;
 
+// Physics runs during a fixed timestep phase at this rate
+const PHYSICS_STEP_SIZE: f64 = 1.0 / 60.0;
+
+// If a frame takes long enough to need more physics steps than this, the extra time is dropped
+const PHYSICS_MAX_SUBSTEPS: u32 = 8;
+
 pub fn run_the_game() -> Result<(), Box<dyn std::error::Error>> {
     // Setup logging
     env_logger::Builder::from_default_env()
@@ -85,23 +91,35 @@ pub fn run_the_game() -> Result<(), Box<dyn std::error::Error>> {
         .with_resource(crate::framework::resources::InputState::new())
         .with_resource(crate::framework::resources::TimeState::new())
         .with_resource(resources::PhysicsManager::new())
+        .with_resource(crate::framework::resources::FixedTimestep::new(
+            std::time::Duration::from_secs_f64(PHYSICS_STEP_SIZE),
+            PHYSICS_MAX_SUBSTEPS,
+        ))
         .with_resource(window)
         .with_resource(resources::RenderState::empty())
         .with_resource(crate::framework::resources::CameraState::empty())
         .with_resource(crate::framework::resources::FrameworkOptions::new(keybinds))
+        .with_resource(crate::framework::resources::TaskProfiler::new(120))
+        .with_resource(crate::framework::resources::EntityHierarchy::new())
+        .with_resource(crate::framework::resources::PersistentEntityLookup::new())
         .with_component(<crate::framework::components::TransformComponent as Component>::Storage::new())
+        .with_component(<crate::framework::components::GlobalTransformComponent as Component>::Storage::new())
         .with_component(<crate::framework::components::VelocityComponent as Component>::Storage::new())
         .with_component(<crate::framework::components::DebugDrawCircleComponent as Component>::Storage::new())
         .with_component(<crate::framework::components::DebugDrawRectComponent as Component>::Storage::new())
         .with_component(<components::PlayerComponent as Component>::Storage::new())
         .with_component(<components::BulletComponent as Component>::Storage::new())
+        .with_component(<components::SwitchComponent as Component>::Storage::new())
         .with_component(<crate::framework::components::FreeAtTimeComponent as Component>::Storage::new())
-        .with_component(
+        .with_component_and_free_handler::<_, _, crate::framework::components::PersistentEntityComponentFreeHandler>(
             <crate::framework::components::PersistentEntityComponent as Component>::Storage::new(),
         )
         .with_component_and_free_handler::<_, _, components::PhysicsBodyComponentFreeHandler>(
             <components::PhysicsBodyComponent as Component>::Storage::new(),
         )
+        .with_component_and_free_handler::<_, _, crate::framework::components::ParentComponentFreeHandler>(
+            <crate::framework::components::ParentComponent as Component>::Storage::new(),
+        )
         //TODO: Ideally we don't need to register the factory in addition to the component itself
         .with_component_factory(CloneComponentFactory::<crate::framework::components::TransformComponent>::new())
         .with_component_factory(CloneComponentFactory::<crate::framework::components::VelocityComponent>::new())
@@ -111,7 +129,10 @@ pub fn run_the_game() -> Result<(), Box<dyn std::error::Error>> {
         .with_component_factory(CloneComponentFactory::<crate::framework::components::DebugDrawRectComponent>::new())
         .with_component_factory(CloneComponentFactory::<components::PlayerComponent>::new())
         .with_component_factory(CloneComponentFactory::<components::BulletComponent>::new())
+        .with_component_factory(CloneComponentFactory::<components::SwitchComponent>::new())
         .with_component_factory(CloneComponentFactory::<crate::framework::components::FreeAtTimeComponent>::new())
+        // Physics bodies are placed using the parent's transform, so parents must be set first
+        .with_component_factory(crate::framework::components::ParentComponentFactory::new())
         .with_component_factory(components::PhysicsBodyComponentFactory::new())
         .with_component_factory(CloneComponentFactory::<
             crate::framework::components::PersistentEntityComponent,
@@ -162,6 +183,7 @@ pub fn run_the_game() -> Result<(), Box<dyn std::error::Error>> {
         inspect_registry
             .register_component::<components::PhysicsBodyComponent>("Physics Body Circle");
         inspect_registry.register_component::<components::PlayerComponent>("Player");
+        inspect_registry.register_component::<components::SwitchComponent>("Switch");
 
         inspect_registry.register_component_prototype::<crate::framework::CloneComponentPrototype<crate::framework::components::TransformComponent>>("Position");
         inspect_registry.register_component_prototype::<crate::framework::CloneComponentPrototype<crate::framework::components::VelocityComponent>>("Velocity");
@@ -180,6 +202,7 @@ pub fn run_the_game() -> Result<(), Box<dyn std::error::Error>> {
                 "Physics Body Circle",
             );
         inspect_registry.register_component_prototype::<crate::framework::CloneComponentPrototype<components::PlayerComponent>>("Player");
+        inspect_registry.register_component_prototype::<crate::framework::CloneComponentPrototype<components::SwitchComponent>>("Switch");
 
         world_builder.add_resource(inspect_registry);
     }
@@ -198,6 +221,8 @@ pub fn run_the_game() -> Result<(), Box<dyn std::error::Error>> {
             "Physics Body Circle",
         );
     persist_registry.register_component_prototype::<crate::framework::CloneComponentPrototype<components::PlayerComponent>>("Player");
+    persist_registry.register_component_prototype::<crate::framework::CloneComponentPrototype<components::SwitchComponent>>("Switch");
+    persist_registry.register_component_prototype::<crate::framework::CloneComponentPrototype<crate::framework::components::FreeAtTimeComponent>>("Free At Time");
     world_builder.add_resource(persist_registry);
 
     register_tasks(&mut world_builder);
@@ -243,11 +268,14 @@ pub fn run_the_game() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn register_tasks(world_builder: &mut WorldBuilder) {
+    // Tasks filter on these, so they must be added first
+    crate::framework::context_flags::register_context_flags(world_builder);
+
     // Add the default phases
     world_builder.add_phase::<crate::base::task::PhaseFrameBegin>();
     world_builder.add_phase::<crate::base::task::PhaseGatherInput>();
     world_builder.add_phase::<crate::base::task::PhasePrePhysicsGameplay>();
-    world_builder.add_phase::<crate::base::task::PhasePhysics>();
+    world_builder.add_fixed_timestep_phase::<crate::base::task::PhasePhysics>();
     world_builder.add_phase::<crate::base::task::PhasePostPhysicsGameplay>();
     world_builder.add_phase::<crate::base::task::PhasePreRender>();
     world_builder.add_phase::<crate::base::task::PhaseRender>();
@@ -268,6 +296,7 @@ fn register_tasks(world_builder: &mut WorldBuilder) {
         world_builder.add_task::<crate::framework::tasks::editor::EditorHandleInputTask>();
         world_builder.add_task::<crate::framework::tasks::editor::EditorDrawSelectionShapesTask>();
         world_builder.add_task::<tasks::imgui::RenderImguiInspectorTask>();
+        world_builder.add_task::<tasks::imgui::RenderImguiTaskProfilerTask>();
 
         // This get run at end of frame
         world_builder.add_task::<crate::framework::tasks::editor::EditorUpdateActionQueueTask>();
@@ -284,13 +313,21 @@ fn register_tasks(world_builder: &mut WorldBuilder) {
     // Pre Physics Gameplay
     world_builder.add_task::<tasks::ControlPlayerEntityTask>();
     world_builder.add_task::<crate::framework::tasks::HandleFreeAtTimeComponentsTask>();
-    world_builder.add_task::<tasks::UpdatePositionWithVelocityTask>();
+    world_builder.add_fn_task(tasks::update_position_with_velocity, |config| {
+        config.this_runs_during_phase::<crate::base::task::PhasePrePhysicsGameplay>();
+        config.this_provides_data_to::<tasks::PhysicsSyncPreTask>();
+        config.run_only_if_flag::<crate::framework::context_flags::PlayModePlaying>();
+    });
 
     // Physics
     world_builder.add_task::<tasks::PhysicsSyncPreTask>();
     world_builder.add_task::<tasks::UpdatePhysicsTask>();
     world_builder.add_task::<tasks::PhysicsSyncPostTask>();
 
+    // Post Physics Gameplay
+    world_builder.add_task::<crate::framework::tasks::UpdateGlobalTransformsTask>();
+    world_builder.add_task::<tasks::UpdateSwitchesTask>();
+
     // Pre-Render
     world_builder.add_task::<crate::framework::tasks::DebugDrawComponentsTask>();
 
@@ -306,31 +343,33 @@ fn register_tasks(world_builder: &mut WorldBuilder) {
 fn dispatcher_thread(world: crate::base::World) -> crate::base::resource::ResourceMap {
     info!("dispatch thread started");
 
-    // If editing, start paused
-    #[cfg(feature = "editor")]
-    let context_flags = crate::framework::context_flags::AUTHORITY_CLIENT
-        | crate::framework::context_flags::AUTHORITY_SERVER
-        | crate::framework::context_flags::PLAYMODE_SYSTEM;
-
-    // If not editing, start in playing mode
-    #[cfg(not(feature = "editor"))]
-    let context_flags = crate::framework::context_flags::AUTHORITY_CLIENT
-        | crate::framework::context_flags::AUTHORITY_SERVER
-        | crate::framework::context_flags::PLAYMODE_PLAYING
-        | crate::framework::context_flags::PLAYMODE_PAUSED
-        | crate::framework::context_flags::PLAYMODE_SYSTEM;
+    let context_flags = {
+        use crate::framework::context_flags::*;
+        let mut dispatch_control = world.resource_map.fetch_mut::<DispatchControl>();
+        dispatch_control.set_context_flag::<AuthorityClient>();
+        dispatch_control.set_context_flag::<AuthorityServer>();
+        dispatch_control.set_context_flag::<PlayModeSystem>();
+
+        // If editing, start paused. Otherwise start in playing mode
+        #[cfg(not(feature = "editor"))]
+        {
+            dispatch_control.set_context_flag::<PlayModePaused>();
+            dispatch_control.set_context_flag::<PlayModePlaying>();
+        }
 
-    *world
-        .resource_map
-        .fetch_mut::<DispatchControl>()
-        .next_frame_context_flags_mut() = context_flags;
+        info!(
+            "initial context flags: {:?}",
+            dispatch_control.next_frame_active_flag_names()
+        );
+        dispatch_control.next_frame_context_flags()
+    };
 
     world
         .resource_map
         .fetch_mut::<FrameworkActionQueue>()
         .enqueue_load_level(std::path::PathBuf::from("test_save"));
 
-    let update_loop = UpdateLoopSingleThreaded::new(world, context_flags);
+    let mut update_loop = UpdateLoopSingleThreaded::new(world, context_flags);
     update_loop.run();
 
     let mut resource_map = update_loop.into_resource_map();
@@ -1,12 +1,14 @@
-const GRAVITY: f32 = 0.0;
+use crate::base::component::ChangeTick;
 
-//TODO: Are steps really necessary? Appropriate size?
-const STEP_SIZE: f32 = 1.0 / 60.0;
+const GRAVITY: f32 = 0.0;
 
 pub struct PhysicsManager {
     world: nphysics::world::World<f32>,
 
-    time_accumulator: f32,
+    // Change ticks of the global transform/velocity storages when PhysicsSyncPost last copied the
+    // physics world into them. Anything changed after that was edited outside of physics.
+    transform_sync_tick: ChangeTick,
+    velocity_sync_tick: ChangeTick,
 }
 
 impl PhysicsManager {
@@ -17,35 +19,26 @@ impl PhysicsManager {
         #[cfg(feature = "dim3")]
         let gravity = glm::Vec3::y() * GRAVITY;
         world.set_gravity(gravity);
-        world.integration_parameters_mut().dt = STEP_SIZE;
 
         PhysicsManager {
             world,
-            time_accumulator: 0.0,
+            transform_sync_tick: 0,
+            velocity_sync_tick: 0,
         }
     }
 
-    pub fn update(&mut self, time_state: &crate::framework::resources::TimeState) {
-        let dt = time_state.playing().previous_frame_dt;
-        self.time_accumulator += dt;
-
-        let mut steps = 0;
-        let accumulated_time = self.time_accumulator;
+    // Called once per fixed timestep, so this may run several times per frame (or not at all)
+    pub fn step(&mut self, dt: f32) {
+        self.world.integration_parameters_mut().dt = dt;
 
         let t0 = std::time::Instant::now();
-        while self.time_accumulator > STEP_SIZE {
-            steps += 1;
-            self.world.step();
-            self.time_accumulator -= STEP_SIZE;
-        }
+        self.world.step();
         let t1 = std::time::Instant::now();
 
         trace!(
-            "update physics took {} in {} steps. Last frame dt: {} time accumulator: {}",
+            "physics step took {}ms (dt: {})",
             (t1 - t0).as_micros() as f64 / 1000.0,
-            steps,
-            dt,
-            accumulated_time
+            dt
         );
     }
 
@@ -56,4 +49,20 @@ impl PhysicsManager {
     pub fn world_mut(&mut self) -> &mut nphysics::world::World<f32> {
         &mut self.world
     }
+
+    pub fn transform_sync_tick(&self) -> ChangeTick {
+        self.transform_sync_tick
+    }
+
+    pub fn set_transform_sync_tick(&mut self, transform_sync_tick: ChangeTick) {
+        self.transform_sync_tick = transform_sync_tick;
+    }
+
+    pub fn velocity_sync_tick(&self) -> ChangeTick {
+        self.velocity_sync_tick
+    }
+
+    pub fn set_velocity_sync_tick(&mut self, velocity_sync_tick: ChangeTick) {
+        self.velocity_sync_tick = velocity_sync_tick;
+    }
 }
@@ -31,7 +31,7 @@ impl ResourceTaskImpl for ControlPlayerEntity {
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<crate::base::task::PhasePrePhysicsGameplay>();
-        config.run_only_if(crate::framework::context_flags::PLAYMODE_PLAYING);
+        config.run_only_if_flag::<crate::framework::context_flags::PlayModePlaying>();
     }
 
     fn run(
@@ -31,8 +31,8 @@ impl ResourceTaskImpl for GatherInput {
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<crate::base::task::PhaseGatherInput>();
-        config.run_only_if(crate::framework::context_flags::AUTHORITY_CLIENT);
-        config.run_only_if(crate::framework::context_flags::PLAYMODE_SYSTEM);
+        config.run_only_if_flag::<crate::framework::context_flags::AuthorityClient>();
+        config.run_only_if_flag::<crate::framework::context_flags::PlayModeSystem>();
     }
 
     fn run(
@@ -11,7 +11,7 @@ impl ResourceTaskImpl for ImguiBeginFrame {
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<crate::base::task::PhaseFrameBegin>();
-        config.run_only_if(crate::framework::context_flags::AUTHORITY_CLIENT);
+        config.run_only_if_flag::<crate::framework::context_flags::AuthorityClient>();
     }
 
     fn run(
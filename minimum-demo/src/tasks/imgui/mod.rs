@@ -9,3 +9,6 @@ pub use render_imgui_entity_list::RenderImguiEntityListTask;
 
 mod render_imgui_inspector;
 pub use render_imgui_inspector::RenderImguiInspectorTask;
+
+mod render_imgui_task_profiler;
+pub use render_imgui_task_profiler::RenderImguiTaskProfilerTask;
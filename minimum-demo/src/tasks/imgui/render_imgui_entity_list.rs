@@ -1,6 +1,7 @@
 use crate::base::resource::{DataRequirement, Read, Write};
 use crate::base::{
-    ComponentStorage, EntitySet, ResourceTaskImpl, TaskConfig, TaskContextFlags, WriteComponent,
+    ComponentStorage, EntitySet, ReadComponent, ResourceTaskImpl, TaskConfig, TaskContextFlags,
+    WriteComponent,
 };
 
 use crate::framework::resources::InputState;
@@ -9,6 +10,8 @@ use crate::resources::ImguiManager;
 use crate::framework::resources::editor::{EditorActionQueue, EditorUiState};
 use crate::framework::resources::TimeState;
 use crate::framework::resources::FrameworkOptions;
+use crate::framework::resources::EntityHierarchy;
+use crate::framework::components::ParentComponent;
 
 #[cfg(feature = "editor")]
 use crate::framework::components::editor::EditorSelectedComponent;
@@ -24,12 +27,14 @@ impl ResourceTaskImpl for RenderImguiEntityList {
         WriteComponent<EditorSelectedComponent>,
         Read<InputState>,
         Write<EditorActionQueue>,
-        Read<FrameworkOptions>
+        Read<FrameworkOptions>,
+        Read<EntityHierarchy>,
+        ReadComponent<ParentComponent>,
     );
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<crate::base::task::PhasePreRender>();
-        config.run_only_if(crate::framework::context_flags::AUTHORITY_CLIENT);
+        config.run_only_if_flag::<crate::framework::context_flags::AuthorityClient>();
     }
 
     fn run(
@@ -44,7 +49,9 @@ impl ResourceTaskImpl for RenderImguiEntityList {
             mut editor_selected_components,
             input_state,
             mut editor_action_queue,
-            framework_options
+            framework_options,
+            entity_hierarchy,
+            parent_components,
         ) = data;
 
         imgui_manager.with_ui(|ui: &mut imgui::Ui| {
@@ -76,37 +83,59 @@ impl ResourceTaskImpl for RenderImguiEntityList {
                                 imgui_sys::ImVec2 { x: -1.0, y: -1.0 },
                             )
                         } {
+                            // Show root entities, each followed by its descendants indented below it
+                            let mut rows = vec![];
                             for entity in entity_set.iter() {
+                                if !parent_components.exists(&entity.handle()) {
+                                    rows.push((entity.handle(), 0));
+                                    rows.extend(entity_hierarchy.descendants(&entity.handle()));
+                                }
+                            }
+
+                            for (entity_handle, depth) in rows {
                                 let is_selected = if let Some(_selected_component) =
-                                    editor_selected_components.get(&entity.handle())
+                                    editor_selected_components.get(&entity_handle)
                                 {
                                     true
                                 } else {
                                     false
                                 };
 
-                                let s = im_str!("{:?}", entity.handle());
+                                let indent = depth as f32 * 16.0;
+                                if depth > 0 {
+                                    unsafe {
+                                        imgui_sys::igIndent(indent);
+                                    }
+                                }
+
+                                let s = im_str!("{:?}", entity_handle);
                                 let clicked =
                                     imgui::Selectable::new(&s).selected(is_selected).build(ui);
 
+                                if depth > 0 {
+                                    unsafe {
+                                        imgui_sys::igUnindent(indent);
+                                    }
+                                }
+
                                 if clicked {
                                     let is_control_held =
                                         input_state.is_key_down(framework_options.keybinds.modify_imgui_entity_list_modify_selection_add1) ||
                                         input_state.is_key_down(framework_options.keybinds.modify_imgui_entity_list_modify_selection_add2);
                                     if is_control_held {
-                                        if !editor_selected_components.exists(&entity.handle()) {
+                                        if !editor_selected_components.exists(&entity_handle) {
                                             editor_selected_components.allocate(
-                                                &entity.handle(),
+                                                &entity_handle,
                                                 EditorSelectedComponent::new(),
                                             ).unwrap();
                                         } else {
-                                            editor_selected_components.free(&entity.handle());
+                                            editor_selected_components.free(&entity_handle);
                                         }
                                     } else {
-                                        editor_selected_components.free_all();
-                                        if !editor_selected_components.exists(&entity.handle()) {
+                                        editor_selected_components.free_all(&*entity_set);
+                                        if !editor_selected_components.exists(&entity_handle) {
                                             editor_selected_components.allocate(
-                                                &entity.handle(),
+                                                &entity_handle,
                                                 EditorSelectedComponent::new(),
                                             ).unwrap();
                                         }
@@ -46,11 +46,11 @@ impl ResourceTaskImpl for RenderImguiMainMenu {
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<crate::base::task::PhasePreRender>();
-        config.run_only_if(crate::framework::context_flags::AUTHORITY_CLIENT);
+        config.run_only_if_flag::<crate::framework::context_flags::AuthorityClient>();
     }
 
     fn run(
-        _context_flags: &TaskContextFlags,
+        context_flags: &TaskContextFlags,
         data: <Self::RequiredResources as DataRequirement>::Borrow,
     ) {
         let (
@@ -134,6 +134,10 @@ impl ResourceTaskImpl for RenderImguiMainMenu {
                         &mut window_settings.show_entity_list,
                     );
                     ui.checkbox(im_str!("Inspector"), &mut window_settings.show_inspector);
+                    ui.checkbox(
+                        im_str!("Task Profiler"),
+                        &mut window_settings.show_task_profiler,
+                    );
                 });
 
                 ui.separator();
@@ -142,6 +146,17 @@ impl ResourceTaskImpl for RenderImguiMainMenu {
                     ui.checkbox(im_str!("Debug Window"), &mut debug_options.show_debug_info);
                 });
 
+                // Read-only listing of which context flags are set this frame
+                ui.menu(im_str!("Context Flags"), true, || {
+                    for (name, bits) in context_flags.registry().iter() {
+                        let short_name = name.rsplit("::").next().unwrap_or(name);
+                        imgui::MenuItem::new(&im_str!("{}", short_name))
+                            .selected(context_flags.flags() & bits != 0)
+                            .enabled(false)
+                            .build(ui);
+                    }
+                });
+
                 ui.separator();
 
                 if is_edit_mode {
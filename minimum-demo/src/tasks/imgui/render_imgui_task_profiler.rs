@@ -0,0 +1,125 @@
+use crate::base::resource::{DataRequirement, Read, Write};
+use crate::base::{ResourceTaskImpl, TaskConfig, TaskContextFlags};
+
+use crate::framework::resources::editor::EditorUiState;
+use crate::framework::resources::{TaskProfiler, TimeState};
+use crate::resources::ImguiManager;
+
+use imgui::im_str;
+
+const CHROME_TRACE_PATH: &str = "task_profile.json";
+
+pub struct RenderImguiTaskProfiler;
+pub type RenderImguiTaskProfilerTask = crate::base::ResourceTask<RenderImguiTaskProfiler>;
+
+impl RenderImguiTaskProfiler {
+    // Task names are full type paths, which are too long to show. Strip the module paths, keeping
+    // generic parameters (i.e. ResourceTask<ControlPlayerEntity>)
+    fn short_name(name: &str) -> String {
+        let mut short_name = String::with_capacity(name.len());
+        for c in name.chars() {
+            short_name.push(c);
+            if short_name.ends_with("::") {
+                while short_name.ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == ':') {
+                    short_name.pop();
+                }
+            }
+        }
+
+        short_name
+    }
+
+    fn draw_stats(ui: &imgui::Ui, name: &str, stats: &crate::base::task::TimingStats) {
+        ui.text(im_str!("{}", Self::short_name(name)));
+        ui.next_column();
+        ui.text(im_str!("{:.3}", stats.min.as_secs_f64() * 1000.0));
+        ui.next_column();
+        ui.text(im_str!("{:.3}", stats.average.as_secs_f64() * 1000.0));
+        ui.next_column();
+        ui.text(im_str!("{:.3}", stats.max.as_secs_f64() * 1000.0));
+        ui.next_column();
+    }
+}
+
+impl ResourceTaskImpl for RenderImguiTaskProfiler {
+    type RequiredResources = (
+        Read<TimeState>,
+        Write<ImguiManager>,
+        Read<EditorUiState>,
+        Write<TaskProfiler>,
+    );
+
+    fn configure(config: &mut TaskConfig) {
+        config.this_runs_during_phase::<crate::base::task::PhasePreRender>();
+        config.run_only_if_flag::<crate::framework::context_flags::AuthorityClient>();
+    }
+
+    fn run(
+        _context_flags: &TaskContextFlags,
+        data: <Self::RequiredResources as DataRequirement>::Borrow,
+    ) {
+        let (time_state, mut imgui_manager, editor_ui_state, mut task_profiler) = data;
+
+        let window_options = editor_ui_state.window_options(time_state.play_mode);
+        if !window_options.show_task_profiler {
+            return;
+        }
+
+        imgui_manager.with_ui(|ui: &mut imgui::Ui| {
+            imgui::Window::new(im_str!("Task Profiler"))
+                .position([360.0, 50.0], imgui::Condition::Once)
+                .size([550.0, 400.0], imgui::Condition::Once)
+                .build(ui, || {
+                    let mut enabled = task_profiler.is_enabled();
+                    if ui.checkbox(im_str!("Record"), &mut enabled) {
+                        task_profiler.set_enabled(enabled);
+                    }
+
+                    ui.same_line_with_spacing(80.0, 10.0);
+                    if ui.button(im_str!("Clear"), [80.0, 0.0]) {
+                        task_profiler.clear();
+                    }
+
+                    ui.same_line_with_spacing(170.0, 10.0);
+                    if ui.button(im_str!("Export Chrome Trace"), [160.0, 0.0]) {
+                        let json = task_profiler.to_chrome_trace_json();
+                        match std::fs::write(CHROME_TRACE_PATH, json) {
+                            Ok(_) => info!("Wrote task profile to {}", CHROME_TRACE_PATH),
+                            Err(e) => warn!("Failed to write task profile: {}", e),
+                        }
+                    }
+
+                    let frame_stats = task_profiler.frame_stats();
+                    ui.text(im_str!(
+                        "{} frames recorded, frame time (ms) min {:.3} avg {:.3} max {:.3}",
+                        frame_stats.sample_count,
+                        frame_stats.min.as_secs_f64() * 1000.0,
+                        frame_stats.average.as_secs_f64() * 1000.0,
+                        frame_stats.max.as_secs_f64() * 1000.0
+                    ));
+
+                    ui.separator();
+
+                    ui.columns(4, im_str!("task_profiler_columns"), true);
+                    for header in &["Phase/Task", "Min (ms)", "Avg (ms)", "Max (ms)"] {
+                        ui.text(im_str!("{}", header));
+                        ui.next_column();
+                    }
+
+                    ui.separator();
+
+                    for (name, stats) in task_profiler.phase_stats() {
+                        Self::draw_stats(ui, name, &stats);
+                    }
+
+                    ui.separator();
+
+                    for (name, stats) in task_profiler.task_stats() {
+                        Self::draw_stats(ui, name, &stats);
+                    }
+
+                    ui.columns(1, im_str!(""), false);
+                });
+        })
+    }
+}
@@ -12,7 +12,10 @@ mod control_player_entity;
 pub use control_player_entity::ControlPlayerEntityTask;
 
 mod update_position_with_velocity;
-pub use update_position_with_velocity::UpdatePositionWithVelocityTask;
+pub use update_position_with_velocity::update_position_with_velocity;
+
+mod update_switches;
+pub use update_switches::UpdateSwitchesTask;
 
 mod update_renderer;
 pub use update_renderer::UpdateRendererTask;
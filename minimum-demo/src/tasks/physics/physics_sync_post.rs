@@ -1,8 +1,9 @@
-use crate::base::resource::{DataRequirement, Read};
+use crate::base::resource::{DataRequirement, Read, Write};
 
 use crate::resources::PhysicsManager;
 
 use crate::components;
+use crate::framework::components::global_transform::parent_global_transform;
 use crate::base::component::{ReadComponent, WriteComponent};
 use crate::base::{
     ComponentStorage, EntitySet, ResourceTask, ResourceTaskImpl, TaskConfig, TaskContextFlags,
@@ -13,16 +14,18 @@ pub type PhysicsSyncPostTask = ResourceTask<PhysicsSyncPost>;
 impl ResourceTaskImpl for PhysicsSyncPost {
     type RequiredResources = (
         Read<EntitySet>,
-        Read<PhysicsManager>,
+        Write<PhysicsManager>,
         ReadComponent<components::PhysicsBodyComponent>,
         WriteComponent<crate::framework::components::TransformComponent>,
+        WriteComponent<crate::framework::components::GlobalTransformComponent>,
+        ReadComponent<crate::framework::components::ParentComponent>,
         WriteComponent<crate::framework::components::VelocityComponent>,
     );
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<crate::base::task::PhasePhysics>();
         config.this_uses_data_from::<crate::tasks::UpdatePhysicsTask>();
-        config.run_only_if(crate::framework::context_flags::PLAYMODE_PLAYING);
+        config.run_only_if_flag::<crate::framework::context_flags::PlayModePlaying>();
     }
 
     fn run(
@@ -31,9 +34,11 @@ impl ResourceTaskImpl for PhysicsSyncPost {
     ) {
         let (
             entity_set,
-            physics_manager,
+            mut physics_manager,
             physics_body_components,
             mut pos_components,
+            mut global_transform_components,
+            parent_components,
             mut vel_components,
         ) = data;
 
@@ -43,14 +48,34 @@ impl ResourceTaskImpl for PhysicsSyncPost {
                 .rigid_body(body_component.body_handle())
                 .unwrap();
 
+            // Physics works in world space, but the transform of a child is relative to its parent
+            let position = body.position().translation.vector;
+            let local_position = match parent_global_transform(
+                &entity,
+                &parent_components,
+                &global_transform_components,
+            ) {
+                Some(parent_transform) => parent_transform.transform().to_local_position(position),
+                None => position,
+            };
+
             if let Some(pos_component) = pos_components.get_mut(&entity) {
-                let position = body.position().translation.vector;
-                *pos_component.position_mut() = position;
+                *pos_component.position_mut() = local_position;
+            }
+
+            // Update the global transform too, so that it isn't seen as an edit that needs to be
+            // pushed back to physics
+            if let Some(global_transform_component) = global_transform_components.get_mut(&entity) {
+                *global_transform_component.transform_mut().position_mut() = position;
             }
 
             if let Some(vel_component) = vel_components.get_mut(&entity) {
                 *vel_component.velocity_mut() = body.velocity().linear;
             }
         }
+
+        // Copying from physics isn't an edit that needs to be pushed back to physics
+        physics_manager.set_transform_sync_tick(global_transform_components.change_tick());
+        physics_manager.set_velocity_sync_tick(vel_components.change_tick());
     }
 }
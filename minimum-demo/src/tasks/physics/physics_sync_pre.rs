@@ -3,7 +3,7 @@ use crate::base::resource::{DataRequirement, Read, Write};
 use crate::resources::PhysicsManager;
 
 use crate::components;
-use crate::base::component::{ReadComponent, WriteComponent};
+use crate::base::component::ReadComponent;
 use crate::base::{
     ComponentStorage, EntitySet, ResourceTask, ResourceTaskImpl, TaskConfig, TaskContextFlags,
 };
@@ -15,14 +15,14 @@ impl ResourceTaskImpl for PhysicsSyncPre {
         Read<EntitySet>,
         Write<PhysicsManager>,
         ReadComponent<components::PhysicsBodyComponent>,
-        WriteComponent<crate::framework::components::TransformComponent>,
-        WriteComponent<crate::framework::components::VelocityComponent>,
+        ReadComponent<crate::framework::components::GlobalTransformComponent>,
+        ReadComponent<crate::framework::components::VelocityComponent>,
     );
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<crate::base::task::PhasePhysics>();
         config.this_provides_data_to::<crate::tasks::UpdatePhysicsTask>();
-        config.run_only_if(crate::framework::context_flags::PLAYMODE_PLAYING);
+        config.run_only_if_flag::<crate::framework::context_flags::PlayModePlaying>();
     }
 
     fn run(
@@ -33,32 +33,39 @@ impl ResourceTaskImpl for PhysicsSyncPre {
             entity_set,
             mut physics_manager,
             physics_body_components,
-            mut pos_components,
-            mut vel_components,
+            global_transform_components,
+            vel_components,
         ) = data;
 
-        for (entity, body_component) in physics_body_components.iter(&entity_set) {
-            let body: &mut nphysics::object::RigidBody<f32> = physics_manager
-                .world_mut()
-                .rigid_body_mut(body_component.body_handle())
-                .unwrap();
+        // Anything that changed since PhysicsSyncPost copied it from physics was edited elsewhere
+        // (i.e. by the editor), so push it to the physics world
+        let transform_sync_tick = physics_manager.transform_sync_tick();
+        for (entity, global_transform_component) in
+            global_transform_components.iter_changed(&entity_set, transform_sync_tick)
+        {
+            if let Some(body_component) = physics_body_components.get(&entity) {
+                let body: &mut nphysics::object::RigidBody<f32> = physics_manager
+                    .world_mut()
+                    .rigid_body_mut(body_component.body_handle())
+                    .unwrap();
 
-            if let Some(pos_component) = pos_components.get_mut(&entity) {
-                if pos_component.requires_sync_to_physics() {
-                    body.set_position(nphysics::math::Isometry::from_parts(
-                        nphysics::math::Translation::from(pos_component.position()),
-                        body.position().rotation,
-                    ));
-
-                    pos_component.clear_requires_sync_to_physics();
-                }
+                body.set_position(nphysics::math::Isometry::from_parts(
+                    nphysics::math::Translation::from(global_transform_component.position()),
+                    body.position().rotation,
+                ));
             }
+        }
+
+        let velocity_sync_tick = physics_manager.velocity_sync_tick();
+        for (entity, vel_component) in vel_components.iter_changed(&entity_set, velocity_sync_tick)
+        {
+            if let Some(body_component) = physics_body_components.get(&entity) {
+                let body: &mut nphysics::object::RigidBody<f32> = physics_manager
+                    .world_mut()
+                    .rigid_body_mut(body_component.body_handle())
+                    .unwrap();
 
-            if let Some(vel_component) = vel_components.get_mut(&entity) {
-                if vel_component.requires_sync_to_physics() {
-                    body.set_linear_velocity(vel_component.velocity());
-                    vel_component.clear_requires_sync_to_physics();
-                }
+                body.set_linear_velocity(vel_component.velocity());
             }
         }
     }
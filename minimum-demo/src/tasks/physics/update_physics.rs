@@ -1,25 +1,26 @@
 use crate::base::resource::{DataRequirement, Read, Write};
 
 use crate::resources::PhysicsManager;
-use crate::framework::resources::TimeState;
+use crate::framework::resources::FixedTimestep;
 
 use crate::base::{ResourceTask, ResourceTaskImpl, TaskConfig, TaskContextFlags};
 
 pub struct UpdatePhysics;
 pub type UpdatePhysicsTask = ResourceTask<UpdatePhysics>;
 impl ResourceTaskImpl for UpdatePhysics {
-    type RequiredResources = (Read<TimeState>, Write<PhysicsManager>);
+    type RequiredResources = (Read<FixedTimestep>, Write<PhysicsManager>);
 
     fn configure(config: &mut TaskConfig) {
+        // PhasePhysics is a fixed timestep phase, so this runs once per physics step
         config.this_runs_during_phase::<crate::base::task::PhasePhysics>();
-        config.run_only_if(crate::framework::context_flags::PLAYMODE_PLAYING);
+        config.run_only_if_flag::<crate::framework::context_flags::PlayModePlaying>();
     }
 
     fn run(
         _context_flags: &TaskContextFlags,
         data: <Self::RequiredResources as DataRequirement>::Borrow,
     ) {
-        let (time_state, mut physics) = data;
-        physics.update(&time_state);
+        let (fixed_timestep, mut physics) = data;
+        physics.step(fixed_timestep.step_secs());
     }
 }
@@ -1,53 +1,31 @@
-use crate::base::resource::{DataRequirement, Read};
-use crate::base::{ResourceTaskImpl, TaskConfig, TaskContextFlags};
+use crate::base::resource::ReadBorrow;
 
 use crate::framework::resources::TimeState;
 
 use crate::components;
-use crate::base::component::{ReadComponent, WriteComponent};
-use crate::base::ComponentStorage;
-
-pub struct UpdatePositionWithVelocity;
-pub type UpdatePositionWithVelocityTask = crate::base::ResourceTask<UpdatePositionWithVelocity>;
-impl ResourceTaskImpl for UpdatePositionWithVelocity {
-    type RequiredResources = (
-        Read<crate::base::EntitySet>,
-        Read<TimeState>,
-        WriteComponent<crate::framework::components::TransformComponent>,
-        ReadComponent<crate::framework::components::VelocityComponent>,
-        ReadComponent<components::PhysicsBodyComponent>,
-    );
-
-    fn configure(config: &mut TaskConfig) {
-        config.this_runs_during_phase::<crate::base::task::PhasePrePhysicsGameplay>();
-        config.this_provides_data_to::<crate::tasks::PhysicsSyncPreTask>();
-        config.run_only_if(crate::framework::context_flags::PLAYMODE_PLAYING);
-    }
-
-    fn run(
-        _context_flags: &TaskContextFlags,
-        data: <Self::RequiredResources as DataRequirement>::Borrow,
-    ) {
-        let (
-            entity_set,
-            time_state,
-            mut transform_components,
-            velocity_components,
-            physics_body_components,
-        ) = data;
-
-        let dt = time_state.playing().previous_frame_dt;
-
-        for (entity, vel) in velocity_components.iter(&entity_set) {
-            if physics_body_components.exists(&entity) {
-                // Skip any entities that have a physics body as movement is being controlled by
-                // nphysics
-                continue;
-            }
-
-            if let Some(pos) = transform_components.get_mut(&entity) {
-                *pos.position_mut() += vel.velocity() * dt;
-            }
-        }
-    }
+use crate::base::component::{par_join, ReadComponentBorrow, Without, WriteComponentBorrow};
+use crate::base::rayon::prelude::*;
+
+// Registered with WorldBuilder::add_fn_task, see register_tasks()
+pub fn update_position_with_velocity(
+    entity_set: ReadBorrow<crate::base::EntitySet>,
+    time_state: ReadBorrow<TimeState>,
+    mut transform_components: WriteComponentBorrow<crate::framework::components::TransformComponent>,
+    velocity_components: ReadComponentBorrow<crate::framework::components::VelocityComponent>,
+    physics_body_components: ReadComponentBorrow<components::PhysicsBodyComponent>,
+) {
+    let dt = time_state.playing().previous_frame_dt;
+
+    // Skip any entities that have a physics body as movement is being controlled by nphysics
+    par_join(
+        &entity_set,
+        (
+            &mut *transform_components,
+            &*velocity_components,
+            Without(&*physics_body_components),
+        ),
+    )
+    .for_each(|(_entity, pos, vel, ())| {
+        *pos.position_mut() += vel.velocity() * dt;
+    });
 }
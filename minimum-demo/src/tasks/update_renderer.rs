@@ -1,4 +1,5 @@
 use crate::renderer::Renderer;
+use crate::base::resource::ResourceId;
 use crate::base::task::ReadAllTaskImpl;
 use crate::base::ResourceMap;
 use crate::base::TaskConfig;
@@ -10,6 +11,9 @@ pub type UpdateRendererTask = crate::base::ReadAllTask<UpdateRenderer>;
 impl ReadAllTaskImpl for UpdateRenderer {
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<crate::base::task::PhaseRender>();
+
+        // Reads everything, but also renders with the renderer
+        config.add_write(ResourceId::new::<Renderer>());
     }
 
     fn run(_context_flags: &TaskContextFlags, resource_map: &ResourceMap) {
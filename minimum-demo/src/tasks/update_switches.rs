@@ -0,0 +1,74 @@
+use crate::base::resource::{DataRequirement, Read};
+use crate::base::{ResourceTaskImpl, TaskConfig, TaskContextFlags};
+
+use crate::components;
+use crate::framework::components::GlobalTransformComponent;
+use crate::framework::resources::PersistentEntityLookup;
+use crate::base::component::{ReadComponent, WriteComponent};
+use crate::base::ComponentStorage;
+
+pub struct UpdateSwitches;
+pub type UpdateSwitchesTask = crate::base::ResourceTask<UpdateSwitches>;
+impl ResourceTaskImpl for UpdateSwitches {
+    type RequiredResources = (
+        Read<crate::base::EntitySet>,
+        Read<PersistentEntityLookup>,
+        ReadComponent<components::SwitchComponent>,
+        ReadComponent<components::PlayerComponent>,
+        ReadComponent<GlobalTransformComponent>,
+        WriteComponent<crate::base::PendingDeleteComponent>,
+    );
+
+    fn configure(config: &mut TaskConfig) {
+        config.this_runs_during_phase::<crate::base::task::PhasePostPhysicsGameplay>();
+        config.this_uses_data_from::<crate::framework::tasks::UpdateGlobalTransformsTask>();
+        config.run_only_if_flag::<crate::framework::context_flags::PlayModePlaying>();
+    }
+
+    fn run(
+        _context_flags: &TaskContextFlags,
+        data: <Self::RequiredResources as DataRequirement>::Borrow,
+    ) {
+        let (
+            entity_set,
+            persistent_entity_lookup,
+            switch_components,
+            player_components,
+            global_transform_components,
+            mut delete_components,
+        ) = data;
+
+        let player_positions: Vec<_> = player_components
+            .iter(&entity_set)
+            .filter_map(|(entity, _)| global_transform_components.get(&entity))
+            .map(|global_transform| global_transform.position())
+            .collect();
+
+        for (entity, switch) in switch_components.iter(&entity_set) {
+            // Look the door up by ID every time, since it gets a new handle when it is recreated
+            // (i.e. when it's modified in the editor). It may have already been opened, or not
+            // exist at all.
+            let door = match switch
+                .door()
+                .id()
+                .and_then(|id| persistent_entity_lookup.get(id))
+            {
+                Some(door) => door,
+                None => continue,
+            };
+
+            let switch_position = match global_transform_components.get(&entity) {
+                Some(global_transform) => global_transform.position(),
+                None => continue,
+            };
+
+            let is_pressed = player_positions
+                .iter()
+                .any(|position| glm::distance(position, &switch_position) <= switch.radius());
+
+            if is_pressed {
+                entity_set.enqueue_free(door, &mut *delete_components);
+            }
+        }
+    }
+}
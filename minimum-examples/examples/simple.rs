@@ -176,6 +176,6 @@ fn main() {
     // Create a bunch of objects
     create_objects(&world.resource_map);
 
-    let update_loop = UpdateLoopSingleThreaded::new(world, 0);
+    let mut update_loop = UpdateLoopSingleThreaded::new(world, 0);
     update_loop.run();
 }
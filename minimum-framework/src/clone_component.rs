@@ -9,8 +9,11 @@ use base::EntityHandle;
 use base::EntitySet;
 use base::ResourceMap;
 use crate::prototype::FrameworkEntityPrototypeInner;
+use crate::resources::PersistentEntityLookup;
+use crate::ResolveEntityRefs;
 
 use base::component::ComponentCreateQueueFlushListener;
+use base::component::ComponentStorage;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
@@ -165,12 +168,31 @@ impl<T: Component + Clone + SelectableComponentPrototype<T>>
 
 pub struct CloneComponentFactory<T: Component> {
     inner: base::BasicComponentFactory<T>,
+
+    // Only set if created with with_entity_refs. The entities are kept so that their components
+    // can be resolved once they are created
+    resolve_entity_refs: Option<fn(&mut T, &PersistentEntityLookup)>,
+    entities_to_resolve: Vec<EntityHandle>,
 }
 
 impl<T: Component> CloneComponentFactory<T> {
     pub fn new() -> Self {
         CloneComponentFactory::<T> {
             inner: base::BasicComponentFactory::new(),
+            resolve_entity_refs: None,
+            entities_to_resolve: vec![],
+        }
+    }
+}
+
+impl<T: Component + ResolveEntityRefs> CloneComponentFactory<T> {
+    /// Use for components that contain EntityRefPrototypes. They are resolved after the components
+    /// are created, so they may refer to entities created in the same frame.
+    pub fn with_entity_refs() -> Self {
+        CloneComponentFactory::<T> {
+            inner: base::BasicComponentFactory::new(),
+            resolve_entity_refs: Some(T::resolve_entity_refs),
+            entities_to_resolve: vec![],
         }
     }
 }
@@ -184,11 +206,29 @@ impl<T: Component + Clone> ComponentFactory<CloneComponentPrototype<T>>
         prototype: &CloneComponentPrototype<T>,
     ) {
         self.inner.enqueue_create(entity_handle, &prototype.inner);
+
+        if self.resolve_entity_refs.is_some() {
+            self.entities_to_resolve.push(entity_handle.clone());
+        }
     }
 }
 
 impl<T: Component + Clone> ComponentCreateQueueFlushListener for CloneComponentFactory<T> {
     fn flush_creates(&mut self, resource_map: &ResourceMap, entity_set: &EntitySet) {
         self.inner.flush_creates(resource_map, entity_set);
+
+        if let Some(resolve_entity_refs) = self.resolve_entity_refs {
+            if self.entities_to_resolve.is_empty() {
+                return;
+            }
+
+            let persistent_entity_lookup = resource_map.fetch::<PersistentEntityLookup>();
+            let mut storage = resource_map.fetch_mut::<<T as Component>::Storage>();
+            for entity_handle in self.entities_to_resolve.drain(..) {
+                if let Some(component) = storage.get_mut(&entity_handle) {
+                    (resolve_entity_refs)(component, &*persistent_entity_lookup);
+                }
+            }
+        }
     }
 }
@@ -1,19 +1,26 @@
 use base::component::SlabComponentStorage;
 
-use crate::resources::TimeState;
+use crate::resources::{PlayingModeTime, TimeState};
 
-#[derive(Debug, Clone)]
+// Measured in playing time, so the deadline can be saved and loaded, and doesn't move while the
+// game is paused
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FreeAtTimeComponent {
-    free_time: std::time::Instant,
+    free_time: PlayingModeTime,
 }
 
 impl FreeAtTimeComponent {
-    pub fn new(free_time: std::time::Instant) -> Self {
+    /// i.e. `FreeAtTimeComponent::new(time_state.playing().total_time + lifetime)`
+    pub fn new(free_time: PlayingModeTime) -> Self {
         FreeAtTimeComponent { free_time }
     }
 
+    pub fn free_time(&self) -> PlayingModeTime {
+        self.free_time
+    }
+
     pub fn should_free(&self, time_state: &TimeState) -> bool {
-        time_state.playing().frame_start_instant >= self.free_time
+        time_state.playing().total_time >= self.free_time
     }
 }
 
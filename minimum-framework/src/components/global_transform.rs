@@ -0,0 +1,67 @@
+use base::component::{ComponentStorage, VecComponentStorage};
+use base::{Component, EntityHandle};
+
+use crate::components::{ParentComponent, TransformComponent};
+
+/// The transform of an entity in world space, that is its TransformComponent composed with the
+/// TransformComponents of its parents. UpdateGlobalTransformsTask adds, updates and removes these
+/// once a frame for every entity with a TransformComponent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalTransformComponent {
+    transform: TransformComponent,
+}
+
+impl GlobalTransformComponent {
+    pub fn new(transform: TransformComponent) -> Self {
+        GlobalTransformComponent { transform }
+    }
+
+    pub fn transform(&self) -> &TransformComponent {
+        &self.transform
+    }
+
+    /// Only needed when the TransformComponent is changed in a way that must be visible before the
+    /// next update (i.e. when copying a position out of physics). Update both to match.
+    pub fn transform_mut(&mut self) -> &mut TransformComponent {
+        &mut self.transform
+    }
+
+    pub fn position(&self) -> crate::components::transform::Position {
+        self.transform.position()
+    }
+
+    pub fn scale(&self) -> crate::components::transform::Scale {
+        self.transform.scale()
+    }
+
+    pub fn uniform_scale(&self) -> f32 {
+        self.transform.uniform_scale()
+    }
+
+    pub fn rotation(&self) -> crate::components::transform::Rotation {
+        self.transform.rotation()
+    }
+}
+
+impl Component for GlobalTransformComponent {
+    type Storage = VecComponentStorage<Self>;
+}
+
+/// The global transform that the entity's TransformComponent is relative to, which is the one of
+/// its nearest ancestor that has one. None for entities without a parent.
+pub fn parent_global_transform<'a>(
+    entity_handle: &EntityHandle,
+    parent_components: &<ParentComponent as Component>::Storage,
+    global_transform_components: &'a <GlobalTransformComponent as Component>::Storage,
+) -> Option<&'a GlobalTransformComponent> {
+    let mut parent_component = parent_components.get(entity_handle);
+    while let Some(p) = parent_component {
+        if let Some(parent_global_transform) = global_transform_components.get(p.parent()) {
+            return Some(parent_global_transform);
+        }
+
+        parent_component = parent_components.get(p.parent());
+    }
+
+    None
+}
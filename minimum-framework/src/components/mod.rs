@@ -17,8 +17,18 @@ pub use debug_draw_rect::DebugDrawRectComponent;
 mod free_at_time;
 pub use free_at_time::FreeAtTimeComponent;
 
+pub mod global_transform;
+pub use global_transform::GlobalTransformComponent;
+
+mod parent;
+pub use parent::ParentComponent;
+pub use parent::ParentComponentFactory;
+pub use parent::ParentComponentFreeHandler;
+pub use parent::ParentComponentPrototype;
+
 mod persistent_entity;
 pub use persistent_entity::PersistentEntityComponent;
+pub use persistent_entity::PersistentEntityComponentFreeHandler;
 
 pub mod transform;
 pub use transform::TransformComponentPrototype;
@@ -0,0 +1,221 @@
+use base::component::SlabComponentStorage;
+use base::component::{ComponentCreateQueueFlushListener, ComponentStorage};
+use base::Component;
+use base::ComponentFactory;
+use base::ComponentPrototype;
+use base::EntityHandle;
+use base::EntitySet;
+use base::PendingDeleteComponent;
+use base::ResourceMap;
+
+use crate::resources::EntityHierarchy;
+use hashbrown::HashSet;
+use std::collections::VecDeque;
+
+/// Makes the entity a child of another entity. The child is destroyed along with its parent, and
+/// its TransformComponent is relative to the parent's.
+///
+/// Use `EntityHierarchy::set_parent` rather than adding/removing this component directly, so that
+/// the hierarchy's index of children stays up to date.
+#[derive(Clone, Debug)]
+pub struct ParentComponent {
+    parent: EntityHandle,
+}
+
+impl ParentComponent {
+    pub(crate) fn new(parent: EntityHandle) -> Self {
+        ParentComponent { parent }
+    }
+
+    pub fn parent(&self) -> &EntityHandle {
+        &self.parent
+    }
+}
+
+impl Component for ParentComponent {
+    type Storage = SlabComponentStorage<Self>;
+}
+
+//
+// The free handler keeps EntityHierarchy up to date and destroys the children of destroyed entities.
+// It relies on PendingDeleteComponent being registered after ParentComponent (WorldBuilder::build
+// registers it last), otherwise the flag is gone before the handler can see which entities are
+// being destroyed and children would outlive their parent.
+//
+pub struct ParentComponentFreeHandler {}
+
+impl base::component::ComponentFreeHandler<ParentComponent> for ParentComponentFreeHandler {
+    fn on_entities_free(
+        resource_map: &ResourceMap,
+        entity_handles: &[EntityHandle],
+        storage: &mut <ParentComponent as Component>::Storage,
+    ) {
+        let mut entity_hierarchy = resource_map.fetch_mut::<EntityHierarchy>();
+        let mut delete_components =
+            resource_map.fetch_mut::<<PendingDeleteComponent as Component>::Storage>();
+
+        let freed: HashSet<&EntityHandle> = entity_handles.iter().collect();
+
+        for entity_handle in entity_handles {
+            if let Some(parent_component) = storage.get(entity_handle) {
+                entity_hierarchy.remove_child(parent_component.parent(), entity_handle);
+            }
+
+            // Entities that are being destroyed (rather than just losing their ParentComponent) are
+            // still flagged as pending delete here, since that component is freed last. Flag their
+            // children too, EntitySet::flush_free will free them before it returns.
+            if delete_components.exists(entity_handle) {
+                for child in entity_hierarchy.take_children(entity_handle) {
+                    if !freed.contains(&child) && !delete_components.exists(&child) {
+                        delete_components
+                            .allocate(&child, PendingDeleteComponent::new())
+                            .unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
+
+//
+// Creates a component
+//
+#[derive(Clone)]
+pub struct ParentComponentPrototype {
+    parent: EntityHandle,
+}
+
+impl ParentComponentPrototype {
+    pub fn new(parent: EntityHandle) -> Self {
+        ParentComponentPrototype { parent }
+    }
+}
+
+impl ComponentPrototype for ParentComponentPrototype {
+    type Factory = ParentComponentFactory;
+}
+
+//
+// Factory for Parent components
+//
+pub struct ParentComponentFactory {
+    prototypes: VecDeque<(EntityHandle, ParentComponentPrototype)>,
+}
+
+impl ParentComponentFactory {
+    pub fn new() -> Self {
+        ParentComponentFactory {
+            prototypes: VecDeque::new(),
+        }
+    }
+}
+
+impl ComponentFactory<ParentComponentPrototype> for ParentComponentFactory {
+    fn enqueue_create(
+        &mut self,
+        entity_handle: &EntityHandle,
+        prototype: &ParentComponentPrototype,
+    ) {
+        self.prototypes
+            .push_back((entity_handle.clone(), prototype.clone()));
+    }
+}
+
+impl ComponentCreateQueueFlushListener for ParentComponentFactory {
+    fn flush_creates(&mut self, resource_map: &ResourceMap, entity_set: &EntitySet) {
+        if self.prototypes.is_empty() {
+            return;
+        }
+
+        let mut entity_hierarchy = resource_map.fetch_mut::<EntityHierarchy>();
+        let mut storage = resource_map.fetch_mut::<<ParentComponent as Component>::Storage>();
+        for (entity_handle, data) in self.prototypes.drain(..) {
+            if entity_set.get_entity_ref(&entity_handle).is_none()
+                || entity_set.get_entity_ref(&data.parent).is_none()
+            {
+                continue;
+            }
+
+            if let Err(e) =
+                entity_hierarchy.set_parent(&mut *storage, &entity_handle, Some(&data.parent))
+            {
+                warn!("Could not set parent of {:?}: {:?}", entity_handle, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_entities::*;
+    use crate::TestHarness;
+
+    #[test]
+    fn test_create_with_parent() {
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        let parent = create_child(&mut harness, None);
+        let child = create_child(&mut harness, Some(&parent));
+
+        assert_eq!(parent_of(&harness, &child), Some(parent.clone()));
+        assert_eq!(children_of(&harness, &parent), vec![child]);
+        assert_eq!(parent_of(&harness, &parent), None);
+    }
+
+    // Also covers PendingDeleteComponent being registered after ParentComponent
+    #[test]
+    fn test_destroy_cascades_to_children() {
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        let root = create_child(&mut harness, None);
+        let child = create_child(&mut harness, Some(&root));
+        let _grandchild = create_child(&mut harness, Some(&child));
+        let other = create_child(&mut harness, None);
+
+        enqueue_free(&mut harness, &root);
+        harness.step();
+
+        assert_eq!(harness.entities(), vec![other]);
+        assert!(children_of(&harness, &root).is_empty());
+        assert!(children_of(&harness, &child).is_empty());
+    }
+
+    #[test]
+    fn test_destroy_child_keeps_parent() {
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        let parent = create_child(&mut harness, None);
+        let child_a = create_child(&mut harness, Some(&parent));
+        let child_b = create_child(&mut harness, Some(&parent));
+
+        enqueue_free(&mut harness, &child_a);
+        harness.flush_entity_set();
+
+        assert_eq!(harness.entity_count(), 2);
+        assert_eq!(children_of(&harness, &parent), vec![child_b]);
+    }
+
+    #[test]
+    fn test_remove_parent_component_keeps_child() {
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        let parent = create_child(&mut harness, None);
+        let child = create_child(&mut harness, Some(&parent));
+        let grandchild = create_child(&mut harness, Some(&child));
+
+        {
+            let resource_map = harness.resource_map();
+            resource_map
+                .fetch::<EntitySet>()
+                .remove_component::<ParentComponent>(&resource_map, &child);
+        }
+
+        // The child becomes a root entity, but keeps its own children
+        assert_eq!(parent_of(&harness, &child), None);
+        assert!(children_of(&harness, &parent).is_empty());
+        assert_eq!(children_of(&harness, &child), vec![grandchild.clone()]);
+
+        enqueue_free(&mut harness, &parent);
+        harness.flush_entity_set();
+
+        assert_eq!(harness.entities(), vec![child.clone(), grandchild.clone()]);
+        assert_eq!(parent_of(&harness, &grandchild), Some(child));
+    }
+}
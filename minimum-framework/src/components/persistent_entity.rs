@@ -1,15 +1,25 @@
+use crate::persist::PersistentEntityId;
+use crate::resources::PersistentEntityLookup;
 use crate::FrameworkEntityPrototype;
+use base::component::ComponentStorage;
 use base::component::SlabComponentStorage;
 use base::Component;
+use base::EntityHandle;
+use base::ResourceMap;
 
 #[derive(Clone)]
 pub struct PersistentEntityComponent {
+    id: PersistentEntityId,
     prototype: FrameworkEntityPrototype,
 }
 
 impl PersistentEntityComponent {
-    pub fn new(prototype: FrameworkEntityPrototype) -> Self {
-        PersistentEntityComponent { prototype }
+    pub fn new(id: PersistentEntityId, prototype: FrameworkEntityPrototype) -> Self {
+        PersistentEntityComponent { id, prototype }
+    }
+
+    pub fn id(&self) -> &PersistentEntityId {
+        &self.id
     }
 
     pub fn entity_prototype(&self) -> &FrameworkEntityPrototype {
@@ -24,3 +34,61 @@ impl PersistentEntityComponent {
 impl Component for PersistentEntityComponent {
     type Storage = SlabComponentStorage<Self>;
 }
+
+//
+// The free handler removes freed entities from PersistentEntityLookup
+//
+pub struct PersistentEntityComponentFreeHandler {}
+
+impl base::component::ComponentFreeHandler<PersistentEntityComponent>
+    for PersistentEntityComponentFreeHandler
+{
+    fn on_entities_free(
+        resource_map: &ResourceMap,
+        entity_handles: &[EntityHandle],
+        storage: &mut <PersistentEntityComponent as Component>::Storage,
+    ) {
+        let mut persistent_entity_lookup = resource_map.fetch_mut::<PersistentEntityLookup>();
+        for entity_handle in entity_handles {
+            if let Some(persistent_entity_component) = storage.get(entity_handle) {
+                persistent_entity_lookup.remove(persistent_entity_component.id(), entity_handle);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::persist::PersistentEntityId;
+    use crate::test_entities::*;
+    use crate::TestHarness;
+
+    #[test]
+    fn test_free_removes_from_lookup() {
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        let entity_handle = create_persistent(&mut harness, None);
+        let id = persistent_id(&harness, &entity_handle);
+        assert_eq!(find_persistent(&harness, &id), Some(entity_handle.clone()));
+
+        enqueue_free(&mut harness, &entity_handle);
+        harness.flush_entity_set();
+        assert_eq!(find_persistent(&harness, &id), None);
+    }
+
+    #[test]
+    fn test_free_keeps_recreated_entity() {
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        let id = PersistentEntityId::new();
+        let old = create_entity(&mut harness, persistent_prototype(id));
+
+        // The old entity may not have been freed yet when the entity is recreated
+        let recreated = create_entity(&mut harness, persistent_prototype(id));
+        assert_eq!(find_persistent(&harness, &id), Some(recreated.clone()));
+
+        enqueue_free(&mut harness, &old);
+        harness.flush_entity_set();
+        assert_eq!(harness.entities(), vec![recreated.clone()]);
+        assert_eq!(find_persistent(&harness, &id), Some(recreated.clone()));
+        assert_eq!(persistent_id(&harness, &recreated), id);
+    }
+}
@@ -3,8 +3,10 @@ use crate::inspect::common_types::*;
 
 #[cfg(feature = "editor")]
 use imgui_inspect_derive::Inspect;
-use base::component::VecComponentStorage;
+use base::component::{ComponentStorage, VecComponentStorage};
+use base::{Component, EntityHandle};
 use crate::CloneComponentPrototype;
+use crate::components::ParentComponent;
 
 #[cfg(feature = "dim2")]
 pub type Position = glm::Vec2;
@@ -59,20 +61,16 @@ pub fn default_rotation() -> Rotation {
     glm::quat_identity()
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Inspect)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Inspect)]
 pub struct TransformComponent {
-    #[inspect(proxy_type = "ImPosition", on_set = "inspect_transform_updated")]
+    #[inspect(proxy_type = "ImPosition")]
     position: Position,
 
-    #[inspect(proxy_type = "ImScale", on_set = "inspect_transform_updated")]
+    #[inspect(proxy_type = "ImScale")]
     scale: Scale,
 
-    #[inspect(proxy_type = "ImRotation", on_set = "inspect_transform_updated")]
+    #[inspect(proxy_type = "ImRotation")]
     rotation: Rotation,
-
-    #[inspect(skip)]
-    #[serde(skip)]
-    requires_sync_to_physics: bool,
 }
 
 pub type TransformComponentPrototype = CloneComponentPrototype<TransformComponent>;
@@ -83,7 +81,6 @@ impl Default for TransformComponent {
             position: default_position(),
             scale: default_scale(),
             rotation: default_rotation(),
-            requires_sync_to_physics: false,
         }
     }
 }
@@ -94,7 +91,6 @@ impl TransformComponent {
             position,
             scale,
             rotation,
-            requires_sync_to_physics: false,
         }
     }
 
@@ -120,27 +116,153 @@ impl TransformComponent {
 
     pub fn rotation_mut(&mut self) -> &mut Rotation { &mut self.rotation }
 
-    pub fn requires_sync_to_physics(&self) -> bool {
-        self.requires_sync_to_physics
+    /// Given a transform relative to this one (i.e. a child's transform relative to its parent),
+    /// returns it relative to whatever this transform is relative to
+    #[cfg(feature = "dim2")]
+    pub fn compose(&self, local: &TransformComponent) -> TransformComponent {
+        let position = glm::rotate_vec2(&self.scale.component_mul(&local.position), self.rotation);
+        TransformComponent {
+            position: self.position + position,
+            scale: self.scale.component_mul(&local.scale),
+            rotation: self.rotation + local.rotation,
+        }
+    }
+
+    /// Given a transform relative to this one (i.e. a child's transform relative to its parent),
+    /// returns it relative to whatever this transform is relative to
+    #[cfg(feature = "dim3")]
+    pub fn compose(&self, local: &TransformComponent) -> TransformComponent {
+        let position = glm::quat_rotate_vec3(&self.rotation, &self.scale.component_mul(&local.position));
+        TransformComponent {
+            position: self.position + position,
+            scale: self.scale.component_mul(&local.scale),
+            rotation: self.rotation * local.rotation,
+        }
     }
 
-    pub fn clear_requires_sync_to_physics(&mut self) {
-        self.requires_sync_to_physics = false
+    /// Inverse of `compose` for a direction/offset (i.e. a drag in world space)
+    #[cfg(feature = "dim2")]
+    pub fn to_local_vector(&self, vector: Position) -> Position {
+        glm::rotate_vec2(&vector, -self.rotation).component_div(&self.scale)
     }
 
-    //TODO: Replace with a better solution that doesn't require O(n) iteration
-    // - Allow register callback on inspector?
-    // - Some sort of flagging system?
-    // - Attach an extra component?
-    pub fn inspect_transform_updated(&mut self) {
-        self.requires_sync_to_physics = true;
+    /// Inverse of `compose` for a direction/offset (i.e. a drag in world space)
+    #[cfg(feature = "dim3")]
+    pub fn to_local_vector(&self, vector: Position) -> Position {
+        glm::quat_rotate_vec3(&glm::quat_inverse(&self.rotation), &vector).component_div(&self.scale)
     }
 
-    pub fn editor_transform_updated(&mut self) {
-        self.requires_sync_to_physics = true;
+    /// Inverse of `compose` for a position
+    pub fn to_local_position(&self, position: Position) -> Position {
+        self.to_local_vector(position - self.position)
     }
 }
 
-impl base::Component for TransformComponent {
+/// The transform of the entity in world space, found by composing it with the transforms of its
+/// parents. Parents without a TransformComponent are skipped. Returns None if the entity has no
+/// TransformComponent.
+///
+/// Prefer GlobalTransformComponent, which is kept up to date once a frame. This is for when it may
+/// not exist yet (i.e. while the entity is being created).
+pub fn world_transform(
+    entity_handle: &EntityHandle,
+    transform_components: &<TransformComponent as Component>::Storage,
+    parent_components: &<ParentComponent as Component>::Storage,
+) -> Option<TransformComponent> {
+    let mut transform = transform_components.get(entity_handle)?.clone();
+
+    let mut parent_component = parent_components.get(entity_handle);
+    while let Some(p) = parent_component {
+        if let Some(parent_transform) = transform_components.get(p.parent()) {
+            transform = parent_transform.compose(&transform);
+        }
+
+        parent_component = parent_components.get(p.parent());
+    }
+
+    Some(transform)
+}
+
+impl Component for TransformComponent {
     type Storage = VecComponentStorage<Self>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_entities::*;
+    use base::component::ComponentRegistry;
+    use base::EntitySet;
+
+    #[test]
+    fn test_compose() {
+        let parent = transform(10.0, 0.0, 2.0, 90.0);
+        let child = transform(1.0, 0.0, 0.5, 90.0);
+
+        // The child's position is scaled and rotated by the parent
+        assert_transforms_eq(&parent.compose(&child), &transform(10.0, 2.0, 1.0, 180.0));
+        assert_transforms_eq(&TransformComponent::default().compose(&child), &child);
+    }
+
+    #[test]
+    fn test_to_local_position_round_trip() {
+        let parent = transform(-3.0, 4.0, 2.5, 30.0);
+        for &(x, y) in &[(0.0, 0.0), (1.0, 0.0), (-7.5, 2.0), (3.0, -11.0)] {
+            let local = transform(x, y, 1.0, 45.0);
+            let composed = parent.compose(&local);
+            assert_positions_eq(
+                parent.to_local_position(composed.position()),
+                position(x, y),
+            );
+
+            // And back again, starting from a world position
+            let world_position = position(x, y);
+            let mut local = TransformComponent::default();
+            *local.position_mut() = parent.to_local_position(world_position);
+            assert_positions_eq(parent.compose(&local).position(), world_position);
+        }
+    }
+
+    #[test]
+    fn test_world_transform() {
+        let mut entity_set = EntitySet::new(ComponentRegistry::new());
+        let e: Vec<_> = (0..4).map(|_| entity_set.allocate()).collect();
+
+        // e[1] has no transform, so it's skipped and e[2] is relative to e[0]
+        let mut transform_components = <TransformComponent as Component>::Storage::new();
+        let mut parent_components = <ParentComponent as Component>::Storage::new();
+        let mut entity_hierarchy = crate::resources::EntityHierarchy::new();
+        for (entity_handle, transform) in vec![
+            (&e[0], transform(10.0, 0.0, 2.0, 90.0)),
+            (&e[2], transform(1.0, 0.0, 1.0, 0.0)),
+            (&e[3], transform(0.0, 1.0, 0.5, 90.0)),
+        ] {
+            transform_components
+                .allocate(entity_handle, transform)
+                .unwrap();
+        }
+
+        for i in 1..4 {
+            entity_hierarchy
+                .set_parent(&mut parent_components, &e[i], Some(&e[i - 1]))
+                .unwrap();
+        }
+
+        let world_transform = |entity_handle| {
+            world_transform(entity_handle, &transform_components, &parent_components)
+        };
+        assert_transforms_eq(
+            &world_transform(&e[0]).unwrap(),
+            &transform(10.0, 0.0, 2.0, 90.0),
+        );
+        assert!(world_transform(&e[1]).is_none());
+        assert_transforms_eq(
+            &world_transform(&e[2]).unwrap(),
+            &transform(10.0, 2.0, 2.0, 90.0),
+        );
+        assert_transforms_eq(
+            &world_transform(&e[3]).unwrap(),
+            &transform(8.0, 2.0, 1.0, 180.0),
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use base::EntityHandle;
+
+use crate::persist::PersistentEntityId;
+use crate::resources::PersistentEntityLookup;
+
+#[cfg(feature = "editor")]
+use imgui_inspect::InspectArgsDefault;
+#[cfg(feature = "editor")]
+use std::cell::RefCell;
+
+/// A reference to a persistent entity that can be placed in a component prototype. It is saved as
+/// the PersistentEntityId of the entity, and resolved to the entity's current EntityHandle when the
+/// component is created. (See `ResolveEntityRefs`)
+///
+/// The handle is not updated if the referenced entity is recreated later (for example, when it is
+/// modified in the editor). Use `resolve` to look it up again, or look up the ID in
+/// PersistentEntityLookup each time the entity is needed.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EntityRefPrototype {
+    id: Option<PersistentEntityId>,
+
+    #[serde(skip)]
+    entity_handle: Option<EntityHandle>,
+}
+
+impl EntityRefPrototype {
+    pub fn new(id: Option<PersistentEntityId>) -> Self {
+        EntityRefPrototype {
+            id,
+            entity_handle: None,
+        }
+    }
+
+    pub fn id(&self) -> Option<&PersistentEntityId> {
+        self.id.as_ref()
+    }
+
+    pub fn set_id(&mut self, id: Option<PersistentEntityId>) {
+        self.id = id;
+        self.entity_handle = None;
+    }
+
+    /// The referenced entity, if the reference has been resolved and the entity existed at the time
+    pub fn entity_handle(&self) -> Option<&EntityHandle> {
+        self.entity_handle.as_ref()
+    }
+
+    /// Look up the entity that currently has the referenced ID
+    pub fn resolve(&mut self, persistent_entity_lookup: &PersistentEntityLookup) {
+        self.entity_handle = self
+            .id
+            .and_then(|id| persistent_entity_lookup.get(&id).cloned());
+    }
+}
+
+/// Implemented by components that contain EntityRefPrototypes. Register the component's factory
+/// with `CloneComponentFactory::with_entity_refs` so that they are resolved when it is created.
+pub trait ResolveEntityRefs {
+    fn resolve_entity_refs(&mut self, persistent_entity_lookup: &PersistentEntityLookup);
+}
+
+//
+// Implement inspect for entity refs, drawn as a button that opens a list of persistent entities
+//
+#[cfg(feature = "editor")]
+thread_local! {
+    // The inspect traits don't have access to resources, so the inspector fills this in before it
+    // draws
+    static ENTITY_REF_CANDIDATES: RefCell<Vec<(PersistentEntityId, EntityHandle)>> = RefCell::new(vec![]);
+}
+
+#[cfg(feature = "editor")]
+pub(crate) fn set_entity_ref_candidates(persistent_entity_lookup: &PersistentEntityLookup) {
+    let mut candidates: Vec<_> = persistent_entity_lookup
+        .iter()
+        .map(|(id, entity_handle)| (*id, entity_handle.clone()))
+        .collect();
+    candidates.sort_by(|a, b| a.0.uuid().cmp(b.0.uuid()));
+
+    ENTITY_REF_CANDIDATES.with(|c| *c.borrow_mut() = candidates);
+}
+
+#[cfg(feature = "editor")]
+fn entity_ref_description(id: Option<&PersistentEntityId>) -> String {
+    match id {
+        Some(id) => ENTITY_REF_CANDIDATES.with(|c| {
+            match c
+                .borrow()
+                .iter()
+                .find(|(candidate_id, _)| candidate_id == id)
+            {
+                Some((_, entity_handle)) => format!("{:?} {}", entity_handle, id),
+                None => format!("(missing) {}", id),
+            }
+        }),
+        None => "None".to_string(),
+    }
+}
+
+#[cfg(feature = "editor")]
+impl imgui_inspect::InspectRenderDefault<EntityRefPrototype> for EntityRefPrototype {
+    fn render(
+        data: &[&EntityRefPrototype],
+        label: &'static str,
+        ui: &imgui::Ui,
+        _args: &InspectArgsDefault,
+    ) {
+        if data.len() == 0 {
+            return;
+        }
+
+        ui.text(&imgui::im_str!(
+            "{}: {}",
+            label,
+            entity_ref_description(data[0].id())
+        ));
+    }
+
+    fn render_mut(
+        data: &mut [&mut EntityRefPrototype],
+        label: &'static str,
+        ui: &imgui::Ui,
+        _args: &InspectArgsDefault,
+    ) -> bool {
+        if data.len() == 0 {
+            return false;
+        }
+
+        let popup_id = imgui::im_str!("{}##entity_ref_picker", label);
+        let button_text = imgui::im_str!("{}##{}", entity_ref_description(data[0].id()), label);
+        if ui.button(&button_text, [0.0, 0.0]) {
+            ui.open_popup(&popup_id);
+        }
+
+        ui.same_line(0.0);
+        ui.text(&imgui::im_str!("{}", label));
+
+        let mut selected = None;
+        ui.popup(&popup_id, || {
+            if imgui::Selectable::new(imgui::im_str!("None")).build(ui) {
+                selected = Some(None);
+            }
+
+            ENTITY_REF_CANDIDATES.with(|c| {
+                for (id, entity_handle) in c.borrow().iter() {
+                    let s = imgui::im_str!("{:?} {}", entity_handle, id);
+                    let is_selected = data[0].id() == Some(id);
+                    if imgui::Selectable::new(&s).selected(is_selected).build(ui) {
+                        selected = Some(Some(*id));
+                    }
+                }
+            });
+        });
+
+        match selected {
+            Some(id) => {
+                for d in data {
+                    d.set_id(id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_entities::*;
+    use crate::{CloneComponentFactory, CloneComponentPrototype, TestHarness};
+    use base::component::SlabComponentStorage;
+    use base::{BasicEntityPrototype, Component};
+
+    #[derive(Clone)]
+    struct TargetComponent {
+        target: EntityRefPrototype,
+    }
+
+    impl Component for TargetComponent {
+        type Storage = SlabComponentStorage<Self>;
+    }
+
+    impl ResolveEntityRefs for TargetComponent {
+        fn resolve_entity_refs(&mut self, persistent_entity_lookup: &PersistentEntityLookup) {
+            self.target.resolve(persistent_entity_lookup);
+        }
+    }
+
+    fn create_harness() -> TestHarness {
+        let world = build_world()
+            .with_component(<TargetComponent as Component>::Storage::new())
+            .with_component_factory(CloneComponentFactory::<TargetComponent>::with_entity_refs());
+
+        TestHarness::new(world, FRAME_TIME)
+    }
+
+    fn enqueue_create_targeting(harness: &mut TestHarness, id: Option<PersistentEntityId>) {
+        harness.enqueue_create_entity(BasicEntityPrototype::new(vec![Box::new(
+            CloneComponentPrototype::new(TargetComponent {
+                target: EntityRefPrototype::new(id),
+            }),
+        )]));
+    }
+
+    fn target_of(harness: &TestHarness, entity_handle: &EntityHandle) -> Option<EntityHandle> {
+        harness
+            .read_component(entity_handle, |component: &TargetComponent| {
+                component.target.entity_handle().cloned()
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let id = PersistentEntityId::new();
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        let entity_handle = create_entity(&mut harness, persistent_prototype(id));
+
+        // Only the ID is saved, the resolved handle is not
+        let mut entity_ref = EntityRefPrototype::new(Some(id));
+        harness.read_resource(|persistent_entity_lookup: &PersistentEntityLookup| {
+            entity_ref.resolve(persistent_entity_lookup)
+        });
+        assert_eq!(entity_ref.entity_handle(), Some(&entity_handle));
+
+        let json = serde_json::to_value(&entity_ref).unwrap();
+        assert_eq!(json, serde_json::to_value(&id).unwrap());
+
+        let deserialized: EntityRefPrototype = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.id(), Some(&id));
+        assert_eq!(deserialized.entity_handle(), None);
+
+        // An empty reference is saved as null
+        let json = serde_json::to_value(&EntityRefPrototype::new(None)).unwrap();
+        assert_eq!(json, serde_json::Value::Null);
+
+        let deserialized: EntityRefPrototype = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.id(), None);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let id = PersistentEntityId::new();
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        let entity_handle = create_entity(&mut harness, persistent_prototype(id));
+
+        let mut entity_ref = EntityRefPrototype::new(Some(id));
+        let mut missing_ref = EntityRefPrototype::new(Some(PersistentEntityId::new()));
+        harness.read_resource(|persistent_entity_lookup: &PersistentEntityLookup| {
+            entity_ref.resolve(persistent_entity_lookup);
+            missing_ref.resolve(persistent_entity_lookup);
+        });
+        assert_eq!(entity_ref.entity_handle(), Some(&entity_handle));
+        assert_eq!(missing_ref.entity_handle(), None);
+
+        // Changing the ID clears the handle until it's resolved again
+        entity_ref.set_id(None);
+        assert_eq!(entity_ref.entity_handle(), None);
+    }
+
+    #[test]
+    fn test_resolve_in_same_flush() {
+        let mut harness = create_harness();
+        let id = PersistentEntityId::new();
+
+        // The referencing entities are queued before the entity they refer to
+        enqueue_create_targeting(&mut harness, Some(id));
+        enqueue_create_targeting(&mut harness, Some(PersistentEntityId::new()));
+        enqueue_create_targeting(&mut harness, None);
+        harness.enqueue_create_entity(persistent_prototype(id));
+        harness.flush_entity_set();
+
+        let target = find_persistent(&harness, &id).unwrap();
+        let mut targets: Vec<_> = harness
+            .entities_with_component::<TargetComponent>()
+            .iter()
+            .map(|entity_handle| target_of(&harness, entity_handle))
+            .collect();
+        targets.sort_by_key(|target| target.is_some());
+        assert_eq!(targets, vec![None, None, Some(target)]);
+    }
+
+    #[test]
+    fn test_recreated_target_is_found_by_id() {
+        let mut harness = create_harness();
+        let id = PersistentEntityId::new();
+        enqueue_create_targeting(&mut harness, Some(id));
+        harness.enqueue_create_entity(persistent_prototype(id));
+        harness.flush_entity_set();
+
+        let referencing = harness.entities_with_component::<TargetComponent>()[0].clone();
+        let old_target = find_persistent(&harness, &id).unwrap();
+
+        // Recreate the target with the same ID, like the editor does when it's modified
+        enqueue_free(&mut harness, &old_target);
+        harness.flush_entity_set();
+        let new_target = create_entity(&mut harness, persistent_prototype(id));
+
+        // The handle resolved when the referencing entity was created is stale, but looking up the
+        // ID finds the recreated entity
+        let mut target = harness
+            .read_component(&referencing, |component: &TargetComponent| {
+                component.target.clone()
+            })
+            .unwrap();
+        assert_eq!(target.entity_handle(), Some(&old_target));
+
+        harness.read_resource(|persistent_entity_lookup: &PersistentEntityLookup| {
+            assert_eq!(
+                target.id().and_then(|id| persistent_entity_lookup.get(id)),
+                Some(&new_target)
+            );
+            target.resolve(persistent_entity_lookup);
+        });
+        assert_eq!(target.entity_handle(), Some(&new_target));
+    }
+}
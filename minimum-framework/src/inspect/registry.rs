@@ -343,6 +343,11 @@ impl InspectRegistry {
         ui: &imgui::Ui,
         set_inspector_tab: &mut Option<InspectorTab>,
     ) {
+        // EntityRefPrototype fields list the persistent entities that can be picked
+        crate::entity_ref::set_entity_ref_candidates(
+            &*resource_map.fetch::<crate::resources::PersistentEntityLookup>(),
+        );
+
         let tab_bar_str = imgui::im_str!("Inspector");
 
         unsafe {
@@ -29,6 +29,10 @@ mod clone_component;
 pub use clone_component::CloneComponentFactory;
 pub use clone_component::CloneComponentPrototype;
 
+mod entity_ref;
+pub use entity_ref::EntityRefPrototype;
+pub use entity_ref::ResolveEntityRefs;
+
 pub mod components;
 pub mod resources;
 
@@ -46,9 +50,17 @@ pub use prototype::FrameworkComponentPrototype;
 pub use prototype::FrameworkEntityPersistencePolicy;
 pub use prototype::FrameworkEntityPrototype;
 pub use prototype::FrameworkEntityPrototypeInner;
+pub use prototype::gather_persistent_entity_prototypes;
+pub use prototype::enqueue_create_entity_prototypes;
 
 pub mod tasks;
 
+mod test_harness;
+pub use test_harness::TestHarness;
+
+#[cfg(test)]
+pub(crate) mod test_entities;
+
 #[derive(Copy, Clone, PartialEq, strum_macros::EnumCount, Debug)]
 pub enum PlayMode {
     // Represents the game being frozen for debug purposes
@@ -64,17 +76,48 @@ pub enum PlayMode {
 //PLAYMODE_COUNT exists due to strum_macros::EnumCount
 const PLAY_MODE_COUNT: usize = PLAYMODE_COUNT;
 
+/// Context flags used by the framework. These must be added to the world builder (see
+/// `register_context_flags`) before any task that filters on them.
 pub mod context_flags {
+    use base::task::ContextFlag;
+
     // For pause status. Flags will be set based on if the game is in a certain playmode
-    pub const PLAYMODE_SYSTEM: usize = 1;
-    pub const PLAYMODE_PAUSED: usize = 2;
-    pub const PLAYMODE_PLAYING: usize = 4;
+    pub struct PlayModeSystem;
+    impl ContextFlag for PlayModeSystem {}
+
+    pub struct PlayModePaused;
+    impl ContextFlag for PlayModePaused {}
+
+    pub struct PlayModePlaying;
+    impl ContextFlag for PlayModePlaying {}
 
     // For multiplayer games:
     // - Dedicated Server will only run Net_Server
     // - Pure client will only have Net_Client
     // - "Listen" client will have both
     // - Singleplayer will have both
-    pub const AUTHORITY_SERVER: usize = 8;
-    pub const AUTHORITY_CLIENT: usize = 16;
+    pub struct AuthorityServer;
+    impl ContextFlag for AuthorityServer {}
+
+    pub struct AuthorityClient;
+    impl ContextFlag for AuthorityClient {}
+
+    /// Set the play mode flags for the next frame. Each mode also sets the flags of the modes below
+    /// it, so tasks that run while paused also run while playing.
+    pub fn set_play_mode(dispatch_control: &mut base::DispatchControl, play_mode: crate::PlayMode) {
+        use crate::PlayMode;
+
+        dispatch_control.set_context_flag::<PlayModeSystem>();
+        dispatch_control.set_context_flag_to::<PlayModePaused>(play_mode != PlayMode::System);
+        dispatch_control.set_context_flag_to::<PlayModePlaying>(play_mode == PlayMode::Playing);
+    }
+
+    /// Add all of the framework's context flags to the world
+    pub fn register_context_flags(world_builder: &mut base::WorldBuilder) {
+        world_builder.add_context_flag::<PlayModeSystem>();
+        world_builder.add_context_flag::<PlayModePaused>();
+        world_builder.add_context_flag::<PlayModePlaying>();
+        world_builder.add_context_flag::<AuthorityServer>();
+        world_builder.add_context_flag::<AuthorityClient>();
+    }
 }
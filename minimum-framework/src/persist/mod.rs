@@ -1,6 +1,10 @@
 mod registry;
 
 pub use registry::PersistRegistry;
+
+mod persistent_entity_id;
+pub use persistent_entity_id::PersistentEntityId;
+
 use serde::de::DeserializeOwned;
 pub use serde::{Deserialize, Serialize};
 
@@ -0,0 +1,46 @@
+/// Identifies a persistent entity across save/load, level reset and being recreated by the editor.
+/// Unlike an EntityHandle, it is stored in the level file. Use `PersistentEntityLookup` to find the
+/// entity that currently has the ID.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PersistentEntityId(uuid::Uuid);
+
+impl PersistentEntityId {
+    /// Generate a new, random ID
+    pub fn new() -> Self {
+        PersistentEntityId(uuid::Uuid::new_v4())
+    }
+
+    pub fn uuid(&self) -> &uuid::Uuid {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for PersistentEntityId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_is_unique() {
+        assert_ne!(PersistentEntityId::new(), PersistentEntityId::new());
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let id = PersistentEntityId::new();
+
+        // Saved as just the UUID string
+        let json = serde_json::to_value(&id).unwrap();
+        assert_eq!(json, serde_json::Value::String(id.to_string()));
+        assert_eq!(json, serde_json::Value::String(id.uuid().to_string()));
+
+        let deserialized: PersistentEntityId = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, id);
+    }
+}
@@ -1,12 +1,9 @@
-#[cfg(feature = "editor")]
-use crate::components::PersistentEntityComponent;
-
 use crate::persist::ComponentPrototypeSerializer;
+use crate::persist::PersistentEntityId;
 use crate::{
     FrameworkComponentPrototypeDyn, FrameworkEntityPersistencePolicy, FrameworkEntityPrototype, FrameworkComponentPrototype
 };
 use hashbrown::HashMap;
-use base::EntityPrototype;
 use base::ResourceMap;
 use std::marker::PhantomData;
 use std::sync::Arc;
@@ -26,13 +23,29 @@ impl SavedComponent {
 
 #[derive(Serialize, Deserialize)]
 struct SavedObject {
+    // Objects saved before IDs were added get a new one when loaded
+    #[serde(default = "PersistentEntityId::new")]
+    pub id: PersistentEntityId,
+
     pub saved_components: Vec<SavedComponent>,
+
+    // Index of the parent object in LevelFile::saved_objects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<usize>,
 }
 
 impl SavedObject {
     #[cfg(feature = "editor")]
-    pub fn new(saved_components: Vec<SavedComponent>) -> Self {
-        SavedObject { saved_components }
+    pub fn new(
+        id: PersistentEntityId,
+        saved_components: Vec<SavedComponent>,
+        parent: Option<usize>,
+    ) -> Self {
+        SavedObject {
+            id,
+            saved_components,
+            parent,
+        }
     }
 }
 
@@ -238,7 +251,7 @@ impl PersistRegistry {
         let input = std::fs::read_to_string(path)?;
         let serialized_level = serde_json::from_str::<LevelFile>(&input)?;
 
-        let mut entities: Vec<Box<dyn EntityPrototype>> = vec![];
+        let mut entities = vec![];
         for entity in serialized_level.saved_objects {
             let mut deserialized_components: Vec<Box<dyn FrameworkComponentPrototypeDyn>> = vec![];
             for component in entity.saved_components {
@@ -266,17 +279,17 @@ impl PersistRegistry {
                 }
             }
 
-            entities.push(Box::new(FrameworkEntityPrototype::new(
+            let prototype = FrameworkEntityPrototype::new(
                 std::path::PathBuf::from("loaded from file"),
-                FrameworkEntityPersistencePolicy::Persistent,
+                FrameworkEntityPersistencePolicy::Persistent(entity.id),
                 deserialized_components,
-            )));
+            );
+
+            entities.push((prototype, entity.parent));
         }
 
         let mut entity_factory = resource_map.fetch_mut::<base::EntityFactory>();
-        for e in entities {
-            entity_factory.enqueue_create(e);
-        }
+        crate::enqueue_create_entity_prototypes(&mut entity_factory, entities);
 
         Ok(())
     }
@@ -287,21 +300,17 @@ impl PersistRegistry {
         resource_map: &ResourceMap,
         path: P,
     ) -> Result<(), SerializeError> {
-        use base::Component;
-        use base::EntitySet;
+        // Get the prototypes of all persistent entities. This represents all the data we need to save
+        let prototypes = crate::gather_persistent_entity_prototypes(resource_map);
 
-        // Get the entities and all persistent entity components. This represents all the data we need to save
-        let entity_set = resource_map.fetch::<EntitySet>();
-        let persistent_entity_components =
-            resource_map.fetch::<<PersistentEntityComponent as Component>::Storage>();
-
-        // Iterate entities and their entity prototypes, adding them to saved_objects
+        // Iterate the entity prototypes, adding them to saved_objects
         let mut saved_objects = vec![];
-        for (_entity_handle, component_prototype) in persistent_entity_components.iter(&*entity_set)
-        {
+        for (prototype, parent) in prototypes {
+            // Only persistent entities are gathered, so they all have an ID
+            let id = prototype.persistent_id().unwrap();
+
             // Access the data in the prototype.
-            let arc = component_prototype.entity_prototype().inner().clone();
-            let pep = arc.lock().unwrap();
+            let pep = prototype.lock();
 
             // Iterate their component prototypes, adding them to the saved_components list
             let mut saved_components = vec![];
@@ -331,7 +340,7 @@ impl PersistRegistry {
                 }
             }
 
-            saved_objects.push(SavedObject::new(saved_components));
+            saved_objects.push(SavedObject::new(id, saved_components, parent));
         }
 
         let serialized_level = LevelFile::new(saved_objects);
@@ -354,3 +363,189 @@ impl PersistRegistry {
         self.registered_component_prototypes_by_type_id[&type_id].create_default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::FrameworkActionQueue;
+    use crate::test_entities::*;
+    use crate::TestHarness;
+
+    fn temp_level_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("minimum-test-{}.json", uuid::Uuid::new_v4()))
+    }
+
+    // Loads the level from a temporary file, and creates the entities
+    fn load_level(harness: &mut TestHarness, persist_registry: &PersistRegistry, level: &str) {
+        let path = temp_level_path();
+        std::fs::write(&path, level).unwrap();
+        let result = persist_registry.load(&harness.resource_map(), &path);
+        std::fs::remove_file(&path).unwrap();
+
+        result.unwrap();
+        harness.flush_entity_set();
+    }
+
+    #[test]
+    fn test_saved_object_parent() {
+        let level_file = LevelFile {
+            saved_objects: vec![
+                SavedObject {
+                    id: PersistentEntityId::new(),
+                    saved_components: vec![],
+                    parent: Some(1),
+                },
+                SavedObject {
+                    id: PersistentEntityId::new(),
+                    saved_components: vec![],
+                    parent: None,
+                },
+            ],
+        };
+
+        // Root objects don't save a parent
+        let json = serde_json::to_value(&level_file).unwrap();
+        assert_eq!(json["saved_objects"][0]["parent"], 1);
+        assert!(json["saved_objects"][1].get("parent").is_none());
+
+        let level_file: LevelFile = serde_json::from_value(json).unwrap();
+        assert_eq!(level_file.saved_objects[0].parent, Some(1));
+        assert_eq!(level_file.saved_objects[1].parent, None);
+    }
+
+    #[test]
+    fn test_saved_object_id() {
+        let id = PersistentEntityId::new();
+        let json = serde_json::to_value(&SavedObject {
+            id,
+            saved_components: vec![],
+            parent: None,
+        })
+        .unwrap();
+        assert_eq!(json["id"], serde_json::Value::String(id.to_string()));
+
+        let saved_object: SavedObject = serde_json::from_value(json).unwrap();
+        assert_eq!(saved_object.id, id);
+
+        // Objects saved before IDs were added get a new one
+        let level_file: LevelFile = serde_json::from_str(
+            r#"{ "saved_objects": [
+                { "saved_components": [] },
+                { "saved_components": [] }
+            ] }"#,
+        )
+        .unwrap();
+        assert_ne!(
+            level_file.saved_objects[0].id,
+            level_file.saved_objects[1].id
+        );
+    }
+
+    #[test]
+    fn test_load_without_ids() {
+        let level = r#"{ "saved_objects": [
+            { "saved_components": [] },
+            { "saved_components": [], "parent": 0 }
+        ] }"#;
+
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        load_level(&mut harness, &PersistRegistry::new(), level);
+
+        // Every loaded entity can be found by its new ID
+        let entities = harness.entities();
+        assert_eq!(entities.len(), 2);
+        for entity_handle in &entities {
+            let id = persistent_id(&harness, entity_handle);
+            assert_eq!(find_persistent(&harness, &id), Some(entity_handle.clone()));
+        }
+
+        assert_ne!(
+            persistent_id(&harness, &entities[0]),
+            persistent_id(&harness, &entities[1])
+        );
+    }
+
+    #[test]
+    fn test_reset_keeps_ids() {
+        let ids: Vec<_> = (0..2).map(|_| PersistentEntityId::new()).collect();
+        let level = format!(
+            r#"{{ "saved_objects": [
+                {{ "id": "{}", "saved_components": [] }},
+                {{ "id": "{}", "saved_components": [], "parent": 0 }}
+            ] }}"#,
+            ids[0], ids[1]
+        );
+
+        let mut harness = TestHarness::new(
+            build_world().with_resource(FrameworkActionQueue::new()),
+            FRAME_TIME,
+        );
+        load_level(&mut harness, &PersistRegistry::new(), &level);
+
+        {
+            let resource_map = harness.resource_map();
+            let mut framework_action_queue = resource_map.fetch_mut::<FrameworkActionQueue>();
+            framework_action_queue.enqueue_reset_level();
+            framework_action_queue.process_queue(&resource_map);
+        }
+        harness.flush_entity_set();
+
+        assert_eq!(harness.entity_count(), 2);
+        let root = find_persistent(&harness, &ids[0]).unwrap();
+        let child = find_persistent(&harness, &ids[1]).unwrap();
+        assert_eq!(persistent_id(&harness, &root), ids[0]);
+        assert_eq!(persistent_id(&harness, &child), ids[1]);
+        assert_eq!(parent_of(&harness, &child), Some(root));
+    }
+
+    #[test]
+    fn test_load_parents() {
+        let ids: Vec<_> = (0..3).map(|_| PersistentEntityId::new()).collect();
+        let level = format!(
+            r#"{{ "saved_objects": [
+                {{ "id": "{}", "saved_components": [], "parent": 1 }},
+                {{ "id": "{}", "saved_components": [] }},
+                {{ "id": "{}", "saved_components": [], "parent": 0 }}
+            ] }}"#,
+            ids[0], ids[1], ids[2]
+        );
+
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        load_level(&mut harness, &PersistRegistry::new(), &level);
+
+        let entities: Vec<_> = ids
+            .iter()
+            .map(|id| find_persistent(&harness, id).unwrap())
+            .collect();
+        assert_eq!(parent_of(&harness, &entities[0]), Some(entities[1].clone()));
+        assert_eq!(parent_of(&harness, &entities[1]), None);
+        assert_eq!(parent_of(&harness, &entities[2]), Some(entities[0].clone()));
+    }
+
+    #[cfg(feature = "editor")]
+    #[test]
+    fn test_save_load_keeps_parents() {
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        let root = create_persistent(&mut harness, None);
+        let child = create_persistent(&mut harness, Some(&root));
+        let grandchild = create_persistent(&mut harness, Some(&child));
+
+        let persist_registry = PersistRegistry::new();
+        let path = temp_level_path();
+        persist_registry
+            .save(&harness.resource_map(), &path)
+            .unwrap();
+        let level = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut loaded = TestHarness::new(build_world(), FRAME_TIME);
+        load_level(&mut loaded, &persist_registry, &level);
+
+        let root = find_persistent(&loaded, &persistent_id(&harness, &root)).unwrap();
+        let child = find_persistent(&loaded, &persistent_id(&harness, &child)).unwrap();
+        let grandchild = find_persistent(&loaded, &persistent_id(&harness, &grandchild)).unwrap();
+        assert_eq!(parent_of(&loaded, &root), None);
+        assert_eq!(parent_of(&loaded, &child), Some(root));
+        assert_eq!(parent_of(&loaded, &grandchild), Some(child));
+    }
+}
@@ -1,13 +1,20 @@
 use base::entity::EntityPrototype;
 use base::{Component, ComponentPrototype};
 
+use base::EntityFactory;
+use base::EntityHandle;
 use base::EntityRef;
+use base::EntitySet;
 use base::ResourceMap;
+use base::component::ComponentStorage;
+use hashbrown::HashMap;
 
 use std::sync::Arc;
 use std::sync::Mutex;
 
-use crate::components::PersistentEntityComponent;
+use crate::components::{ParentComponent, ParentComponentPrototype, PersistentEntityComponent};
+use crate::persist::PersistentEntityId;
+use crate::resources::PersistentEntityLookup;
 
 #[cfg(feature = "editor")]
 use crate::components::editor::EditorShapeComponentPrototype;
@@ -86,8 +93,9 @@ impl FrameworkEntityPrototypeInner {
 
 #[derive(Clone)]
 pub enum FrameworkEntityPersistencePolicy {
-    // Saved to disk and is recreated on level reset
-    Persistent,
+    // Saved to disk and is recreated on level reset. The ID stays the same across save/load, so
+    // it can be used to refer to the entity
+    Persistent(PersistentEntityId),
 
     // Is destroyed on level reset (i.e. spawned at runtime)
     Transient,
@@ -123,6 +131,18 @@ impl FrameworkEntityPrototype {
     pub fn inner(&self) -> &Arc<Mutex<FrameworkEntityPrototypeInner>> {
         &self.inner
     }
+
+    pub fn persistence_policy(&self) -> &FrameworkEntityPersistencePolicy {
+        &self.persistence_policy
+    }
+
+    /// The ID of entities created from this prototype, if they are persistent
+    pub fn persistent_id(&self) -> Option<PersistentEntityId> {
+        match self.persistence_policy {
+            FrameworkEntityPersistencePolicy::Persistent(id) => Some(id),
+            FrameworkEntityPersistencePolicy::Transient => None,
+        }
+    }
 }
 
 impl EntityPrototype for FrameworkEntityPrototype {
@@ -157,13 +177,208 @@ impl EntityPrototype for FrameworkEntityPrototype {
 
         // if the entity is persistent, attach a PersistentEntityComponent to it
         match self.persistence_policy {
-            FrameworkEntityPersistencePolicy::Persistent => {
+            FrameworkEntityPersistencePolicy::Persistent(id) => {
                 // Add PersistentEntityComponent to any component that is persistent
                 let mut storage =
                     resource_map.fetch_mut::<<PersistentEntityComponent as Component>::Storage>();
-                entity.add_component(&mut *storage, PersistentEntityComponent::new(self.clone())).unwrap();
+                entity.add_component(&mut *storage, PersistentEntityComponent::new(id, self.clone())).unwrap();
+
+                let mut persistent_entity_lookup = resource_map.fetch_mut::<PersistentEntityLookup>();
+                persistent_entity_lookup.insert(id, entity.handle());
             }
             _ => {}
         }
     }
 }
+
+/// The prototypes of all persistent entities, each with the index of its parent within the list (if
+/// the parent is also persistent). `enqueue_create_entity_prototypes` can recreate the entities
+/// from them.
+pub fn gather_persistent_entity_prototypes(
+    resource_map: &ResourceMap,
+) -> Vec<(FrameworkEntityPrototype, Option<usize>)> {
+    let entity_set = resource_map.fetch::<EntitySet>();
+    let persistent_entity_components =
+        resource_map.fetch::<<PersistentEntityComponent as Component>::Storage>();
+    let parent_components = resource_map.fetch::<<ParentComponent as Component>::Storage>();
+
+    let entity_handles: Vec<_> = persistent_entity_components
+        .iter(&entity_set)
+        .map(|(entity_handle, _)| entity_handle)
+        .collect();
+
+    let indices: HashMap<_, _> = entity_handles
+        .iter()
+        .enumerate()
+        .map(|(index, entity_handle)| (entity_handle.clone(), index))
+        .collect();
+
+    entity_handles
+        .iter()
+        .map(|entity_handle| {
+            let prototype = persistent_entity_components
+                .get(entity_handle)
+                .unwrap()
+                .entity_prototype()
+                .clone();
+            let parent = parent_components
+                .get(entity_handle)
+                .and_then(|parent_component| indices.get(parent_component.parent()).cloned());
+            (prototype, parent)
+        })
+        .collect()
+}
+
+/// Enqueue creating an entity for each prototype. Entities that have a parent index are made
+/// children of the entity created from the prototype at that index.
+pub fn enqueue_create_entity_prototypes(
+    entity_factory: &mut EntityFactory,
+    prototypes: Vec<(FrameworkEntityPrototype, Option<usize>)>,
+) {
+    let parents: Vec<_> = prototypes.iter().map(|(_, parent)| *parent).collect();
+
+    // Parents are created first, so that their handle is known when their children are created
+    fn visit(index: usize, parents: &[Option<usize>], visited: &mut [bool], order: &mut Vec<usize>) {
+        if visited[index] {
+            return;
+        }
+
+        visited[index] = true;
+        if let Some(parent) = parents[index].filter(|parent| *parent < parents.len()) {
+            visit(parent, parents, visited, order);
+        }
+
+        order.push(index);
+    }
+
+    let mut visited = vec![false; parents.len()];
+    let mut order = Vec::with_capacity(parents.len());
+    for index in 0..parents.len() {
+        visit(index, &parents, &mut visited, &mut order);
+    }
+
+    let created_entities = Arc::new(Mutex::new(vec![None; parents.len()]));
+    let mut prototypes: Vec<_> = prototypes.into_iter().map(Some).collect();
+    for index in order {
+        let (prototype, parent) = prototypes[index].take().unwrap();
+        entity_factory.enqueue_create(Box::new(ChildEntityPrototype {
+            prototype,
+            index,
+            parent,
+            created_entities: created_entities.clone(),
+        }));
+    }
+}
+
+// Creates the entity from the wrapped prototype and attaches it to the entity that was created for
+// the parent index
+struct ChildEntityPrototype {
+    prototype: FrameworkEntityPrototype,
+    index: usize,
+    parent: Option<usize>,
+    created_entities: Arc<Mutex<Vec<Option<EntityHandle>>>>,
+}
+
+impl EntityPrototype for ChildEntityPrototype {
+    fn create(&self, resource_map: &ResourceMap, entity: &EntityRef) {
+        self.prototype.create(resource_map, entity);
+
+        let mut created_entities = self.created_entities.lock().unwrap();
+        created_entities[self.index] = Some(entity.handle());
+
+        let parent_handle = self
+            .parent
+            .and_then(|parent| created_entities.get(parent)?.clone());
+
+        if let Some(parent_handle) = parent_handle {
+            ParentComponentPrototype::new(parent_handle).enqueue_create(resource_map, &entity.handle());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_entities::*;
+    use crate::TestHarness;
+
+    fn create_entity_prototypes(
+        harness: &mut TestHarness,
+        prototypes: Vec<(FrameworkEntityPrototype, Option<usize>)>,
+    ) {
+        harness.write_resource(|entity_factory: &mut EntityFactory| {
+            enqueue_create_entity_prototypes(entity_factory, prototypes)
+        });
+        harness.flush_entity_set();
+    }
+
+    // Each child is listed before its parent, so they are only attached if parents are created first
+    fn create_persistent_hierarchy(harness: &mut TestHarness) -> Vec<PersistentEntityId> {
+        let ids: Vec<_> = (0..4).map(|_| PersistentEntityId::new()).collect();
+        create_entity_prototypes(
+            harness,
+            vec![
+                (persistent_prototype(ids[0]), Some(2)),
+                (persistent_prototype(ids[1]), Some(0)),
+                (persistent_prototype(ids[2]), None),
+                (persistent_prototype(ids[3]), Some(7)),
+            ],
+        );
+
+        ids
+    }
+
+    fn check_persistent_hierarchy(harness: &TestHarness, ids: &[PersistentEntityId]) {
+        let entities: Vec<_> = ids
+            .iter()
+            .map(|id| find_persistent(harness, id).unwrap())
+            .collect();
+
+        assert_eq!(parent_of(harness, &entities[0]), Some(entities[2].clone()));
+        assert_eq!(parent_of(harness, &entities[1]), Some(entities[0].clone()));
+        assert_eq!(parent_of(harness, &entities[2]), None);
+
+        // The parent index is out of range, so it's created as a root entity
+        assert_eq!(parent_of(harness, &entities[3]), None);
+    }
+
+    #[test]
+    fn test_enqueue_creates_parents_first() {
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        let ids = create_persistent_hierarchy(&mut harness);
+
+        assert_eq!(harness.entity_count(), 4);
+        check_persistent_hierarchy(&harness, &ids);
+    }
+
+    #[test]
+    fn test_gather_and_recreate_keeps_parents() {
+        let mut harness = TestHarness::new(build_world(), FRAME_TIME);
+        let ids = create_persistent_hierarchy(&mut harness);
+
+        // Transient entities aren't gathered, even if their parent is persistent. A persistent entity
+        // with a transient parent is recreated as a root entity.
+        let root = find_persistent(&harness, &ids[2]).unwrap();
+        create_child(&mut harness, Some(&root));
+        let transient = create_child(&mut harness, None);
+        let orphan = create_persistent(&mut harness, Some(&transient));
+        let orphan_id = persistent_id(&harness, &orphan);
+
+        let prototypes = gather_persistent_entity_prototypes(&harness.resource_map());
+        assert_eq!(prototypes.len(), 5);
+
+        // Same as resetting the level
+        {
+            let resource_map = harness.resource_map();
+            resource_map.fetch_mut::<EntitySet>().clear(&resource_map);
+        }
+        assert!(find_persistent(&harness, &ids[0]).is_none());
+
+        create_entity_prototypes(&mut harness, prototypes);
+        assert_eq!(harness.entity_count(), 5);
+        check_persistent_hierarchy(&harness, &ids);
+
+        let orphan = find_persistent(&harness, &orphan_id).unwrap();
+        assert_eq!(parent_of(&harness, &orphan), None);
+    }
+}
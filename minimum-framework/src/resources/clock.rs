@@ -0,0 +1,81 @@
+use std::time;
+
+/// Where `TimeState` gets the length of each frame from. Swapping the clock makes it possible to
+/// replay, test, or otherwise control how time passes without touching the tasks that read time.
+pub trait Clock: mopa::Any + Send + Sync {
+    /// Returns how much time passed since the previous call. Called once per frame.
+    fn tick(&mut self) -> time::Duration;
+}
+
+mopafy!(Clock);
+
+/// Measures the time that actually passed between frames
+pub struct RealClock {
+    previous_instant: time::Instant,
+}
+
+impl RealClock {
+    pub fn new() -> Self {
+        RealClock {
+            previous_instant: time::Instant::now(),
+        }
+    }
+}
+
+impl Clock for RealClock {
+    fn tick(&mut self) -> time::Duration {
+        let now_instant = time::Instant::now();
+        let elapsed = now_instant - self.previous_instant;
+        self.previous_instant = now_instant;
+        elapsed
+    }
+}
+
+/// Every frame is exactly the same length, no matter how much time actually passed
+pub struct FixedStepClock {
+    step: time::Duration,
+}
+
+impl FixedStepClock {
+    pub fn new(step: time::Duration) -> Self {
+        FixedStepClock { step }
+    }
+
+    pub fn step(&self) -> time::Duration {
+        self.step
+    }
+
+    pub fn set_step(&mut self, step: time::Duration) {
+        self.step = step;
+    }
+}
+
+impl Clock for FixedStepClock {
+    fn tick(&mut self) -> time::Duration {
+        self.step
+    }
+}
+
+/// Time only passes when `advance` is called. The time advanced since the previous frame is
+/// consumed by the next frame, and frames where it wasn't advanced have zero length.
+pub struct ManualClock {
+    pending: time::Duration,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock {
+            pending: time::Duration::from_secs(0),
+        }
+    }
+
+    pub fn advance(&mut self, elapsed: time::Duration) {
+        self.pending += elapsed;
+    }
+}
+
+impl Clock for ManualClock {
+    fn tick(&mut self) -> time::Duration {
+        std::mem::replace(&mut self.pending, time::Duration::from_secs(0))
+    }
+}
@@ -1,4 +1,5 @@
 use crate::components::editor::EditorSelectedComponent;
+use crate::persist::PersistentEntityId;
 use crate::{FrameworkEntityPersistencePolicy, FrameworkEntityPrototype};
 use base::{Component, EntityPrototype, EntitySet, PendingDeleteComponent, ResourceMap};
 use std::collections::VecDeque;
@@ -22,11 +23,11 @@ impl EditorActionQueue {
             {
                 let mut editor_selected_components =
                     resource_map.fetch_mut::<<EditorSelectedComponent as Component>::Storage>();
-                editor_selected_components.free_all();
+                editor_selected_components.free_all(&*entity_set);
 
                 let pec = FrameworkEntityPrototype::new(
                     std::path::PathBuf::from("testpath"),
-                    FrameworkEntityPersistencePolicy::Persistent,
+                    FrameworkEntityPersistencePolicy::Persistent(PersistentEntityId::new()),
                     vec![],
                 );
 
@@ -7,6 +7,7 @@ pub struct WindowOptions {
     pub show_imgui_demo: bool,
     pub show_entity_list: bool,
     pub show_inspector: bool,
+    pub show_task_profiler: bool,
 }
 
 impl WindowOptions {
@@ -17,6 +18,7 @@ impl WindowOptions {
             show_imgui_demo: false,
             show_entity_list: false,
             show_inspector: false,
+            show_task_profiler: false,
         }
     }
 
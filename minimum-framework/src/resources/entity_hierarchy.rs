@@ -0,0 +1,209 @@
+use base::component::ComponentStorage;
+use base::Component;
+use base::EntityHandle;
+use hashbrown::HashMap;
+
+use crate::components::ParentComponent;
+
+#[derive(Debug)]
+pub enum SetParentError {
+    // The parent is the entity itself or one of its descendants
+    WouldCreateCycle,
+}
+
+/// Index of the children of every entity that has any. The parent of an entity is stored on the
+/// child as a ParentComponent. Both are kept in sync by `set_parent` and by the free handler for
+/// ParentComponent.
+pub struct EntityHierarchy {
+    children: HashMap<EntityHandle, Vec<EntityHandle>>,
+}
+
+impl EntityHierarchy {
+    pub fn new() -> Self {
+        EntityHierarchy {
+            children: HashMap::new(),
+        }
+    }
+
+    /// The direct children of the entity, in the order they were added
+    pub fn children(&self, entity_handle: &EntityHandle) -> &[EntityHandle] {
+        self.children
+            .get(entity_handle)
+            .map(|children| children.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// All descendants of the entity, depth first, with their depth below it (children are at
+    /// depth 1). Parents always come before their children.
+    pub fn descendants(&self, entity_handle: &EntityHandle) -> Vec<(EntityHandle, usize)> {
+        let mut descendants = vec![];
+        let mut stack: Vec<_> = self
+            .children(entity_handle)
+            .iter()
+            .rev()
+            .map(|child| (child.clone(), 1))
+            .collect();
+
+        while let Some((child, depth)) = stack.pop() {
+            stack.extend(
+                self.children(&child)
+                    .iter()
+                    .rev()
+                    .map(|grandchild| (grandchild.clone(), depth + 1)),
+            );
+            descendants.push((child, depth));
+        }
+
+        descendants
+    }
+
+    /// Make the entity a child of `parent`, or a root entity if `parent` is None. The entity is
+    /// removed from its previous parent, if it had one.
+    pub fn set_parent(
+        &mut self,
+        parent_components: &mut <ParentComponent as Component>::Storage,
+        entity_handle: &EntityHandle,
+        parent: Option<&EntityHandle>,
+    ) -> Result<(), SetParentError> {
+        if let Some(parent) = parent {
+            // Walk up from the new parent, we must not find the entity on the way
+            let mut ancestor = Some(parent.clone());
+            while let Some(a) = ancestor {
+                if a == *entity_handle {
+                    return Err(SetParentError::WouldCreateCycle);
+                }
+
+                ancestor = parent_components.get(&a).map(|p| p.parent().clone());
+            }
+        }
+
+        if let Some(old_parent_component) = parent_components.get(entity_handle) {
+            let old_parent = old_parent_component.parent().clone();
+            self.remove_child(&old_parent, entity_handle);
+            parent_components.free(entity_handle);
+        }
+
+        if let Some(parent) = parent {
+            parent_components
+                .allocate(entity_handle, ParentComponent::new(parent.clone()))
+                .unwrap();
+            self.children
+                .entry(parent.clone())
+                .or_insert_with(Vec::new)
+                .push(entity_handle.clone());
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn remove_child(&mut self, parent: &EntityHandle, child: &EntityHandle) {
+        if let Some(children) = self.children.get_mut(parent) {
+            children.retain(|c| c != child);
+            if children.is_empty() {
+                self.children.remove(parent);
+            }
+        }
+    }
+
+    pub(crate) fn take_children(&mut self, parent: &EntityHandle) -> Vec<EntityHandle> {
+        self.children.remove(parent).unwrap_or_else(Vec::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::component::ComponentRegistry;
+    use base::EntitySet;
+
+    fn allocate_entities(count: usize) -> Vec<EntityHandle> {
+        let mut entity_set = EntitySet::new(ComponentRegistry::new());
+        (0..count).map(|_| entity_set.allocate()).collect()
+    }
+
+    fn assert_would_create_cycle(result: Result<(), SetParentError>) {
+        match result {
+            Err(SetParentError::WouldCreateCycle) => {}
+            result => panic!("Expected WouldCreateCycle, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_set_parent() {
+        let e = allocate_entities(4);
+        let mut entity_hierarchy = EntityHierarchy::new();
+        let mut parent_components = <ParentComponent as Component>::Storage::new();
+
+        entity_hierarchy
+            .set_parent(&mut parent_components, &e[1], Some(&e[0]))
+            .unwrap();
+        entity_hierarchy
+            .set_parent(&mut parent_components, &e[2], Some(&e[0]))
+            .unwrap();
+        entity_hierarchy
+            .set_parent(&mut parent_components, &e[3], Some(&e[1]))
+            .unwrap();
+
+        assert_eq!(
+            entity_hierarchy.children(&e[0]),
+            &[e[1].clone(), e[2].clone()]
+        );
+        assert_eq!(
+            entity_hierarchy.descendants(&e[0]),
+            vec![(e[1].clone(), 1), (e[3].clone(), 2), (e[2].clone(), 1)]
+        );
+
+        // Moving an entity removes it from its previous parent
+        entity_hierarchy
+            .set_parent(&mut parent_components, &e[2], Some(&e[1]))
+            .unwrap();
+        assert_eq!(entity_hierarchy.children(&e[0]), &[e[1].clone()]);
+        assert_eq!(
+            entity_hierarchy.children(&e[1]),
+            &[e[3].clone(), e[2].clone()]
+        );
+        assert_eq!(parent_components.get(&e[2]).unwrap().parent(), &e[1]);
+
+        entity_hierarchy
+            .set_parent(&mut parent_components, &e[2], None)
+            .unwrap();
+        assert_eq!(entity_hierarchy.children(&e[1]), &[e[3].clone()]);
+        assert!(!parent_components.exists(&e[2]));
+    }
+
+    #[test]
+    fn test_set_parent_rejects_cycles() {
+        let e = allocate_entities(3);
+        let mut entity_hierarchy = EntityHierarchy::new();
+        let mut parent_components = <ParentComponent as Component>::Storage::new();
+
+        entity_hierarchy
+            .set_parent(&mut parent_components, &e[1], Some(&e[0]))
+            .unwrap();
+        entity_hierarchy
+            .set_parent(&mut parent_components, &e[2], Some(&e[1]))
+            .unwrap();
+
+        assert_would_create_cycle(entity_hierarchy.set_parent(
+            &mut parent_components,
+            &e[0],
+            Some(&e[0]),
+        ));
+        assert_would_create_cycle(entity_hierarchy.set_parent(
+            &mut parent_components,
+            &e[0],
+            Some(&e[2]),
+        ));
+        assert_would_create_cycle(entity_hierarchy.set_parent(
+            &mut parent_components,
+            &e[1],
+            Some(&e[2]),
+        ));
+
+        // Nothing changed
+        assert!(!parent_components.exists(&e[0]));
+        assert_eq!(parent_components.get(&e[1]).unwrap().parent(), &e[0]);
+        assert_eq!(entity_hierarchy.children(&e[1]), &[e[2].clone()]);
+        assert!(entity_hierarchy.children(&e[2]).is_empty());
+    }
+}
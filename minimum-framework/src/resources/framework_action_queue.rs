@@ -1,6 +1,4 @@
-use crate::components;
 use crate::PlayMode;
-use base::Component;
 use base::EntitySet;
 use base::ResourceMap;
 use std::collections::VecDeque;
@@ -81,37 +79,15 @@ impl FrameworkActionQueue {
     pub fn enqueue_change_play_mode(&mut self, new_play_mode: PlayMode) {
         self.queue.push_back(Box::new(move |resource_map| {
             info!("change_play_mode {:?}", new_play_mode);
-            // Clear playmode flags
             let mut dispatch_control = resource_map.fetch_mut::<base::DispatchControl>();
-            *dispatch_control.next_frame_context_flags_mut() &=
-                !(crate::context_flags::PLAYMODE_SYSTEM
-                    | crate::context_flags::PLAYMODE_PAUSED
-                    | crate::context_flags::PLAYMODE_PLAYING);
-
-            // Set the appropriate ones
-            match new_play_mode {
-                PlayMode::System => {
-                    *dispatch_control.next_frame_context_flags_mut() |=
-                        crate::context_flags::PLAYMODE_SYSTEM;
-                }
-                PlayMode::Paused => {
-                    *dispatch_control.next_frame_context_flags_mut() |=
-                        crate::context_flags::PLAYMODE_SYSTEM
-                            | crate::context_flags::PLAYMODE_PAUSED;
-                }
-                PlayMode::Playing => {
-                    *dispatch_control.next_frame_context_flags_mut() |=
-                        crate::context_flags::PLAYMODE_SYSTEM
-                            | crate::context_flags::PLAYMODE_PAUSED
-                            | crate::context_flags::PLAYMODE_PLAYING;
-
-                    #[cfg(feature = "editor")]
-                    {
-                        use crate::resources::editor::EditorUiState;
-                        let mut editor_ui_state = resource_map.fetch_mut::<EditorUiState>();
-                        editor_ui_state.set_inspector_tab =
-                            Some(crate::inspect::InspectorTab::Runtime);
-                    }
+            crate::context_flags::set_play_mode(&mut dispatch_control, new_play_mode);
+
+            #[cfg(feature = "editor")]
+            {
+                if new_play_mode == PlayMode::Playing {
+                    use crate::resources::editor::EditorUiState;
+                    let mut editor_ui_state = resource_map.fetch_mut::<EditorUiState>();
+                    editor_ui_state.set_inspector_tab = Some(crate::inspect::InspectorTab::Runtime);
                 }
             }
         }));
@@ -123,28 +99,15 @@ impl FrameworkActionQueue {
     pub fn enqueue_reset_level(&mut self) {
         self.queue.push_back(Box::new(move |resource_map| {
             info!("enqueue_reset_level");
-            // Collect all the data needed to re-create the persistent entities
-            let prototypes = {
-                let mut prototypes = vec![];
-
-                // Every persistent entity will have a component with the components that created it
-                let persistent_entity_components =
-                    resource_map
-                        .fetch::<<components::PersistentEntityComponent as Component>::Storage>();
-                for persistent_entity_component in persistent_entity_components.iter_values() {
-                    prototypes.push(persistent_entity_component.entity_prototype().clone());
-                }
-
-                prototypes
-            };
+            // Collect all the data needed to re-create the persistent entities. Every persistent
+            // entity will have a component with the components that created it
+            let prototypes = crate::gather_persistent_entity_prototypes(resource_map);
 
             let mut entity_set = resource_map.fetch_mut::<base::EntitySet>();
             entity_set.clear(resource_map);
 
             let mut entity_factory = resource_map.fetch_mut::<base::EntityFactory>();
-            for prototype in prototypes {
-                entity_factory.enqueue_create(Box::new(prototype));
-            }
+            crate::enqueue_create_entity_prototypes(&mut entity_factory, prototypes);
 
             //TODO: Retain selection
             #[cfg(feature = "editor")]
@@ -6,6 +6,20 @@ pub mod editor;
 
 pub use framework_action_queue::FrameworkActionQueue;
 pub use time_state::TimeState;
+pub use time_state::ModeTimeState;
+pub use time_state::PausedModeTime;
+pub use time_state::PlayingModeTime;
+pub use time_state::SystemModeTime;
+
+mod clock;
+pub use clock::Clock;
+pub use clock::FixedStepClock;
+pub use clock::ManualClock;
+pub use clock::RealClock;
+
+// These live in base because the schedulers use them, but are set up by the framework
+pub use base::task::FixedTimestep;
+pub use base::task::TaskProfiler;
 
 mod debug_draw;
 pub use debug_draw::DebugDraw;
@@ -21,5 +35,12 @@ pub use input_state::MouseButtonEvent;
 pub use input_state::KeyboardButton;
 pub use input_state::KeyboardButtonEvent;
 
+mod entity_hierarchy;
+pub use entity_hierarchy::EntityHierarchy;
+pub use entity_hierarchy::SetParentError;
+
+mod persistent_entity_lookup;
+pub use persistent_entity_lookup::PersistentEntityLookup;
+
 mod camera_state;
 pub use camera_state::CameraState;
\ No newline at end of file
@@ -0,0 +1,88 @@
+use base::EntityHandle;
+use hashbrown::HashMap;
+
+use crate::persist::PersistentEntityId;
+
+/// Finds the entity that currently has a PersistentEntityId. Entities are added when they are
+/// created from a persistent FrameworkEntityPrototype and removed by the free handler for
+/// PersistentEntityComponent.
+pub struct PersistentEntityLookup {
+    entities: HashMap<PersistentEntityId, EntityHandle>,
+}
+
+impl PersistentEntityLookup {
+    pub fn new() -> Self {
+        PersistentEntityLookup {
+            entities: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, id: &PersistentEntityId) -> Option<&EntityHandle> {
+        self.entities.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&PersistentEntityId, &EntityHandle)> {
+        self.entities.iter()
+    }
+
+    // An entity that is recreated with the same ID replaces the old one, which may not have been
+    // freed yet
+    pub(crate) fn insert(&mut self, id: PersistentEntityId, entity_handle: EntityHandle) {
+        self.entities.insert(id, entity_handle);
+    }
+
+    // Only removes the ID if it still refers to the entity. The entity may have already been
+    // replaced by a recreated one with the same ID.
+    pub(crate) fn remove(&mut self, id: &PersistentEntityId, entity_handle: &EntityHandle) {
+        if self.entities.get(id) == Some(entity_handle) {
+            self.entities.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::component::ComponentRegistry;
+    use base::EntitySet;
+
+    #[test]
+    fn test_insert_and_get() {
+        let mut entity_set = EntitySet::new(ComponentRegistry::new());
+        let ids = [PersistentEntityId::new(), PersistentEntityId::new()];
+        let entities = [entity_set.allocate(), entity_set.allocate()];
+
+        let mut lookup = PersistentEntityLookup::new();
+        lookup.insert(ids[0], entities[0].clone());
+        lookup.insert(ids[1], entities[1].clone());
+
+        assert_eq!(lookup.get(&ids[0]), Some(&entities[0]));
+        assert_eq!(lookup.get(&ids[1]), Some(&entities[1]));
+        assert_eq!(lookup.get(&PersistentEntityId::new()), None);
+        assert_eq!(lookup.iter().count(), 2);
+
+        lookup.remove(&ids[0], &entities[0]);
+        assert_eq!(lookup.get(&ids[0]), None);
+        assert_eq!(lookup.get(&ids[1]), Some(&entities[1]));
+    }
+
+    #[test]
+    fn test_remove_keeps_recreated_entity() {
+        let mut entity_set = EntitySet::new(ComponentRegistry::new());
+        let id = PersistentEntityId::new();
+        let old = entity_set.allocate();
+        let recreated = entity_set.allocate();
+
+        // The entity is recreated before the old one is freed
+        let mut lookup = PersistentEntityLookup::new();
+        lookup.insert(id, old.clone());
+        lookup.insert(id, recreated.clone());
+        assert_eq!(lookup.get(&id), Some(&recreated));
+
+        lookup.remove(&id, &old);
+        assert_eq!(lookup.get(&id), Some(&recreated));
+
+        lookup.remove(&id, &recreated);
+        assert_eq!(lookup.get(&id), None);
+    }
+}
@@ -1,10 +1,70 @@
 use crate::PlayMode;
 use std::time;
 
+use super::{Clock, RealClock};
+
 const NANOS_PER_SEC: u32 = 1_000_000_000;
 
-//TODO: Exposing duration/instant is a little dangerous, it would be better if durations/instants
-// from each mode were different types, and couldn't be used directly with stdlib duration/instants
+// Declares a point in time for one of the play modes. Each mode gets its own type so that a time
+// measured in one mode can't be compared against a time from another mode.
+macro_rules! mode_time {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(
+            Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+        )]
+        pub struct $name(time::Duration);
+
+        impl $name {
+            /// The point reached once `duration` has been spent in the mode
+            pub fn from_duration(duration: time::Duration) -> Self {
+                $name(duration)
+            }
+
+            /// Total time spent in the mode, up to this point
+            pub fn as_duration(&self) -> time::Duration {
+                self.0
+            }
+
+            /// Time between an earlier point and this one, or zero if `earlier` is actually later
+            pub fn duration_since(&self, earlier: $name) -> time::Duration {
+                self.0
+                    .checked_sub(earlier.0)
+                    .unwrap_or_else(|| time::Duration::from_secs(0))
+            }
+        }
+
+        impl std::ops::Add<time::Duration> for $name {
+            type Output = $name;
+
+            fn add(self, rhs: time::Duration) -> $name {
+                $name(self.0 + rhs)
+            }
+        }
+
+        impl std::ops::AddAssign<time::Duration> for $name {
+            fn add_assign(&mut self, rhs: time::Duration) {
+                self.0 += rhs;
+            }
+        }
+    };
+}
+
+mode_time!(
+    /// A point in system time, which passes in every play mode (including while editing)
+    SystemModeTime
+);
+
+mode_time!(
+    /// A point in paused time, which passes while paused or playing
+    PausedModeTime
+);
+
+mode_time!(
+    /// A point in playing time, which only passes while playing and is affected by the time scale.
+    /// Gameplay deadlines (i.e. `FreeAtTimeComponent`) should be measured in this.
+    PlayingModeTime
+);
 
 //TODO: Avoid using pub for fields
 
@@ -17,78 +77,116 @@ pub struct TimeState {
     // rust Instant object captured when the application started
     pub app_start_instant: time::Instant,
 
-    // rust Instant object captured at the start of the frame
-    pub previous_instant: time::Instant,
-
     // The game can be in different levels of play/pause, this determines what mode we are currently in
     pub play_mode: PlayMode,
 
-    play_mode_states: [ModeTimeState; crate::PLAYMODE_COUNT],
+    system: ModeTimeState<SystemModeTime>,
+    paused: ModeTimeState<PausedModeTime>,
+    playing: ModeTimeState<PlayingModeTime>,
+
+    // Determines how long each frame is
+    clock: Box<dyn Clock>,
+
+    // Multiplies the length of each frame in the Playing mode (i.e. 0.5 for slow motion)
+    time_scale: f64,
 }
 
 impl TimeState {
+    /// Creates a time state that measures the time that actually passed between frames
     pub fn new() -> TimeState {
+        Self::with_clock(RealClock::new())
+    }
+
+    pub fn with_clock<C: Clock>(clock: C) -> TimeState {
         let now_instant = time::Instant::now();
         let now_system_time = time::SystemTime::now();
 
         return TimeState {
             app_start_system_time: now_system_time,
             app_start_instant: now_instant,
-            previous_instant: now_instant,
             play_mode: PlayMode::Playing,
-            play_mode_states: [ModeTimeState::new(); crate::PLAY_MODE_COUNT],
+            system: ModeTimeState::new(),
+            paused: ModeTimeState::new(),
+            playing: ModeTimeState::new(),
+            clock: Box::new(clock),
+            time_scale: 1.0,
         };
     }
 
+    /// Replace the clock. Takes effect next update.
+    pub fn set_clock<C: Clock>(&mut self, clock: C) {
+        self.clock = Box::new(clock);
+    }
+
+    /// Returns the clock if it is a C, i.e. `time_state.clock::<FixedStepClock>()`
+    pub fn clock<C: Clock>(&self) -> Option<&C> {
+        self.clock.downcast_ref::<C>()
+    }
+
+    /// Returns the clock if it is a C, i.e. to advance a `ManualClock`
+    pub fn clock_mut<C: Clock>(&mut self) -> Option<&mut C> {
+        self.clock.downcast_mut::<C>()
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// Speed up (> 1.0) or slow down (< 1.0) time in the Playing mode. System and Paused time are
+    /// not affected, so the editor and UI keep running at normal speed.
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        assert!(time_scale >= 0.0);
+        self.time_scale = time_scale;
+    }
+
     pub fn update(&mut self, play_mode: PlayMode) {
         // Cache the mode we are in this frame
         self.play_mode = play_mode;
 
         // Determine length of time since last tick
-        let now_instant = time::Instant::now();
-        let elapsed = now_instant - self.previous_instant;
-        self.previous_instant = now_instant;
-
-        for play_mode_index in 0..crate::PLAY_MODE_COUNT {
-            let mode_elapsed = if play_mode_index <= (play_mode as usize) {
-                elapsed
-            } else {
-                std::time::Duration::from_secs(0)
-            };
+        let elapsed = self.clock.tick();
 
-            self.play_mode_states[play_mode_index].update(mode_elapsed);
+        // Time passes in the current mode and every mode below it
+        let mut mode_elapsed = [time::Duration::from_secs(0); crate::PLAY_MODE_COUNT];
+        for play_mode_elapsed in mode_elapsed.iter_mut().take(play_mode as usize + 1) {
+            *play_mode_elapsed = elapsed;
         }
 
+        let playing_elapsed =
+            mode_elapsed[PlayMode::Playing as usize].mul_f64(self.time_scale);
+
+        self.system.update(mode_elapsed[PlayMode::System as usize]);
+        self.paused.update(mode_elapsed[PlayMode::Paused as usize]);
+        self.playing.update(playing_elapsed);
+
         trace!(
             "fps: {:.1}  dt: {:.2}ms",
-            self.play_mode_states[0].fps,
-            self.play_mode_states[0].previous_frame_dt * 1000.0
+            self.system.fps,
+            self.system.previous_frame_dt * 1000.0
         );
-        if self.play_mode_states[0].previous_frame_dt > 1.0 / 30.0 {
+        if self.system.previous_frame_dt > 1.0 / 30.0 {
             //warn!("slow frame (dt: {:.2}ms)", dt);
         }
     }
 
-    pub fn system(&self) -> &ModeTimeState {
-        &self.play_mode_states[PlayMode::System as usize]
+    pub fn system(&self) -> &ModeTimeState<SystemModeTime> {
+        &self.system
     }
 
-    pub fn paused(&self) -> &ModeTimeState {
-        &self.play_mode_states[PlayMode::Paused as usize]
+    pub fn paused(&self) -> &ModeTimeState<PausedModeTime> {
+        &self.paused
     }
 
-    pub fn playing(&self) -> &ModeTimeState {
-        &self.play_mode_states[PlayMode::Playing as usize]
+    pub fn playing(&self) -> &ModeTimeState<PlayingModeTime> {
+        &self.playing
     }
 }
 
+/// Timing for one play mode. `T` is the mode's point-in-time type (i.e. `PlayingModeTime`)
 #[derive(Copy, Clone)]
-pub struct ModeTimeState {
-    // Duration of time passed since app_start_system_time
-    pub total_time: time::Duration,
-
-    // rust Instant object captured at the start of the frame
-    pub frame_start_instant: time::Instant,
+pub struct ModeTimeState<T> {
+    // Time spent in the mode since the app started
+    pub total_time: T,
 
     // duration of time passed during the previous frame
     pub previous_frame_time: time::Duration,
@@ -102,13 +200,14 @@ pub struct ModeTimeState {
     pub frame_count: u64,
 }
 
-impl ModeTimeState {
+impl<T> ModeTimeState<T>
+where
+    T: Copy + Default + std::ops::AddAssign<time::Duration>,
+{
     pub fn new() -> Self {
-        let now_instant = time::Instant::now();
         let zero_duration = time::Duration::from_secs(0);
         return ModeTimeState {
-            total_time: zero_duration,
-            frame_start_instant: now_instant,
+            total_time: T::default(),
             previous_frame_time: zero_duration,
             previous_frame_dt: 0.0,
             fps: 0.0,
@@ -119,7 +218,6 @@ impl ModeTimeState {
 
     pub fn update(&mut self, elapsed: std::time::Duration) {
         self.total_time += elapsed;
-        self.frame_start_instant += elapsed;
         self.previous_frame_time = elapsed;
 
         // this can eventually be replaced with as_float_secs
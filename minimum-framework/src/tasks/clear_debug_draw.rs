@@ -10,7 +10,7 @@ impl ResourceTaskImpl for ClearDebugDraw {
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<base::task::PhaseFrameBegin>();
-        config.run_only_if(crate::context_flags::AUTHORITY_CLIENT);
+        config.run_only_if_flag::<crate::context_flags::AuthorityClient>();
     }
 
     fn run(
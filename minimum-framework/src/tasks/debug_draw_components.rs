@@ -13,29 +13,34 @@ impl ResourceTaskImpl for DebugDrawComponents {
         Read<base::EntitySet>,
         ReadComponent<crate::components::DebugDrawCircleComponent>,
         ReadComponent<crate::components::DebugDrawRectComponent>,
-        ReadComponent<crate::components::TransformComponent>,
+        ReadComponent<crate::components::GlobalTransformComponent>,
     );
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<base::task::PhasePreRender>();
-        config.run_only_if(crate::context_flags::AUTHORITY_CLIENT);
+        config.run_only_if_flag::<crate::context_flags::AuthorityClient>();
     }
 
     fn run(
         _context_flags: &TaskContextFlags,
         data: <Self::RequiredResources as DataRequirement>::Borrow,
     ) {
-        let (mut debug_draw, entity_set, circle_components, rect_components, transform_components) =
-            data;
+        let (
+            mut debug_draw,
+            entity_set,
+            circle_components,
+            rect_components,
+            global_transform_components,
+        ) = data;
 
         for (entity_index, circle) in circle_components.iter(&entity_set) {
-            if let Some(transform) = transform_components.get(&entity_index) {
+            if let Some(transform) = global_transform_components.get(&entity_index) {
                 debug_draw.add_circle(transform.position().xy(), circle.radius() * transform.uniform_scale(), circle.color())
             }
         }
 
         for (entity_index, rect) in rect_components.iter(&entity_set) {
-            if let Some(transform) = transform_components.get(&entity_index) {
+            if let Some(transform) = global_transform_components.get(&entity_index) {
                 let rect_size = glm::vec2(rect.size().x * transform.scale().x, rect.size().y * transform.scale().y);
                 let half_extents = rect_size / 2.0;
                 let p0 = half_extents;
@@ -21,8 +21,8 @@ impl ResourceTaskImpl for EditorDrawSelectionShapes {
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<crate::base::task::PhasePreRender>();
         config.this_uses_data_from::<crate::tasks::editor::EditorHandleInputTask>();
-        config.run_only_if(crate::context_flags::AUTHORITY_CLIENT);
-        config.run_only_if(crate::context_flags::PLAYMODE_SYSTEM);
+        config.run_only_if_flag::<crate::context_flags::AuthorityClient>();
+        config.run_only_if_flag::<crate::context_flags::PlayModeSystem>();
     }
 
     fn run(
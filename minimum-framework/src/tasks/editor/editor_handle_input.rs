@@ -1,6 +1,6 @@
 use crate::base::resource::{DataRequirement, Read, Write};
 use crate::base::ComponentStorage;
-use crate::base::{ResourceTaskImpl, TaskConfig, TaskContextFlags, WriteComponent, Component, EntitySet};
+use crate::base::{ResourceTaskImpl, TaskConfig, TaskContextFlags, ReadComponent, WriteComponent, Component, EntitySet};
 
 use crate::resources::DebugDraw;
 use crate::resources::InputState;
@@ -17,6 +17,9 @@ use crate::components::editor::EditorModifiedComponent;
 use ncollide::world::CollisionGroups;
 use crate::components::TransformComponent;
 use crate::components::TransformComponentPrototype;
+use crate::components::GlobalTransformComponent;
+use crate::components::ParentComponent;
+use crate::components::global_transform::parent_global_transform;
 
 pub struct EditorHandleInput;
 pub type EditorHandleInputTask = crate::base::ResourceTask<EditorHandleInput>;
@@ -33,13 +36,15 @@ impl ResourceTaskImpl for EditorHandleInput {
         WriteComponent<TransformComponent>,
         WriteComponent<PersistentEntityComponent>,
         WriteComponent<EditorModifiedComponent>,
-        Read<FrameworkOptions>
+        Read<FrameworkOptions>,
+        ReadComponent<GlobalTransformComponent>,
+        ReadComponent<ParentComponent>,
     );
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<crate::base::task::PhasePreRender>();
         config.this_uses_data_from::<crate::tasks::editor::EditorUpdateSelectionWorldTask>();
-        config.run_only_if(crate::context_flags::PLAYMODE_SYSTEM);
+        config.run_only_if_flag::<crate::context_flags::PlayModeSystem>();
     }
 
     fn run(
@@ -58,7 +63,9 @@ impl ResourceTaskImpl for EditorHandleInput {
             mut transform_components,
             mut persistent_entity_components,
             mut editor_modified_components,
-            framework_options
+            framework_options,
+            global_transform_components,
+            parent_components,
         ) = data;
 
         if input_state.is_key_just_down(framework_options.keybinds.translate_tool) {
@@ -73,33 +80,31 @@ impl ResourceTaskImpl for EditorHandleInput {
             editor_ui_state.active_editor_tool = EditorTool::Rotate;
         }
 
-        if context_flags.flags()
-            & (crate::context_flags::PLAYMODE_PAUSED
-                | crate::context_flags::PLAYMODE_PLAYING)
-            != 0
+        if context_flags.is_set::<crate::context_flags::PlayModePaused>()
+            || context_flags.is_set::<crate::context_flags::PlayModePlaying>()
         {
             return;
         }
 
         // Escape cancels the selection
         if input_state.is_key_just_down(framework_options.keybinds.clear_selection) {
-            editor_selected_components.free_all();
+            editor_selected_components.free_all(&*entity_set);
         }
 
         editor_draw.update(&*input_state, &*camera_state);
 
 
-        handle_translate_gizmo_input(&*entity_set,  &mut* editor_selected_components,  &mut *editor_draw, &mut *transform_components, &mut *persistent_entity_components, &mut *editor_modified_components);
+        handle_translate_gizmo_input(&*entity_set,  &mut* editor_selected_components,  &mut *editor_draw, &mut *transform_components, &mut *persistent_entity_components, &mut *editor_modified_components, &*global_transform_components, &*parent_components);
         handle_scale_gizmo_input(&*entity_set, &mut* editor_selected_components, &mut *editor_draw, &mut *transform_components, &mut *persistent_entity_components, &mut *editor_modified_components);
         handle_rotate_gizmo_input(&*entity_set,  &mut* editor_selected_components, &mut *editor_draw, &mut *transform_components, &mut *persistent_entity_components, &mut *editor_modified_components);
 
-        handle_select_input( &*input_state, &* camera_state, &* editor_collision_world, &mut* editor_selected_components, &mut*debug_draw, &mut *editor_draw, &* framework_options);
+        handle_select_input(&*entity_set, &*input_state, &* camera_state, &* editor_collision_world, &mut* editor_selected_components, &mut*debug_draw, &mut *editor_draw, &* framework_options);
 
         match editor_ui_state.active_editor_tool {
             //EditorTool::Select => handle_select_tool_input(&*entity_set, &*input_state, &* camera_state, &* editor_collision_world, &mut* editor_selected_components, &mut*debug_draw, &editor_ui_state),
-            EditorTool::Translate => draw_translate_gizmo(&*entity_set, &mut* editor_selected_components, &mut*debug_draw, &mut *editor_draw, &* transform_components),
-            EditorTool::Scale => draw_scale_gizmo(&*entity_set, &mut* editor_selected_components, &mut*debug_draw, &mut *editor_draw, &* transform_components),
-            EditorTool::Rotate => draw_rotate_gizmo(&*entity_set, &mut* editor_selected_components, &mut*debug_draw, &mut *editor_draw, &* transform_components)
+            EditorTool::Translate => draw_translate_gizmo(&*entity_set, &mut* editor_selected_components, &mut*debug_draw, &mut *editor_draw, &* global_transform_components),
+            EditorTool::Scale => draw_scale_gizmo(&*entity_set, &mut* editor_selected_components, &mut*debug_draw, &mut *editor_draw, &* global_transform_components),
+            EditorTool::Rotate => draw_rotate_gizmo(&*entity_set, &mut* editor_selected_components, &mut*debug_draw, &mut *editor_draw, &* global_transform_components)
         }
     }
 }
@@ -110,7 +115,9 @@ fn handle_translate_gizmo_input(
     editor_draw: &mut EditorDraw,
     transform_components: &mut <TransformComponent as Component>::Storage,
     persistent_entity_components: &mut <PersistentEntityComponent as Component>::Storage,
-    editor_modified_components: &mut <EditorModifiedComponent as Component>::Storage
+    editor_modified_components: &mut <EditorModifiedComponent as Component>::Storage,
+    global_transform_components: &<GlobalTransformComponent as Component>::Storage,
+    parent_components: &<ParentComponent as Component>::Storage
 
 ) {
     if let Some(drag_in_progress) = editor_draw.shape_drag_in_progress_or_just_finished(MouseButton::Left) {
@@ -145,6 +152,8 @@ fn handle_translate_gizmo_input(
         }
 
         for (entity_handle, _) in editor_selected_components.iter(&entity_set) {
+            // The drag is in world space, but children are positioned relative to their parent
+            let parent_transform = parent_global_transform(&entity_handle, parent_components, global_transform_components);
 
             // If we are ending the drag and manage to find a persistent component prototype, we will
             // update that and recreate the object. In which case, we can skip updating the transform
@@ -159,7 +168,7 @@ fn handle_translate_gizmo_input(
                         // Edit the prototype
                         #[cfg(feature = "dim3")]
                         let world_space_accumulated_delta = glm::vec2_to_vec3(&world_space_accumulated_delta);
-                        *transform_component_prototype.data_mut().position_mut() += world_space_accumulated_delta;
+                        *transform_component_prototype.data_mut().position_mut() += to_local_vector(parent_transform, world_space_accumulated_delta);
 
                         // Mark the object as needing to be recreated
                         if !editor_modified_components.exists(&entity_handle) {
@@ -179,23 +188,32 @@ fn handle_translate_gizmo_input(
                     // drag is complete.
                     #[cfg(feature = "dim3")]
                     let world_space_previous_frame_delta = glm::vec2_to_vec3(&world_space_previous_frame_delta);
-                    *transform_component.position_mut() += world_space_previous_frame_delta;
-                    transform_component.requires_sync_to_physics();
+                    *transform_component.position_mut() += to_local_vector(parent_transform, world_space_previous_frame_delta);
                 }
             }
         }
     }
 }
 
+fn to_local_vector(
+    parent_global_transform: Option<&GlobalTransformComponent>,
+    world_space_vector: crate::components::transform::Position
+) -> crate::components::transform::Position {
+    match parent_global_transform {
+        Some(parent_global_transform) => parent_global_transform.transform().to_local_vector(world_space_vector),
+        None => world_space_vector
+    }
+}
+
 fn draw_translate_gizmo(
     entity_set: &EntitySet,
     editor_selected_components: &mut <EditorSelectedComponent as Component>::Storage,
     debug_draw: &mut DebugDraw,
     editor_draw: &mut EditorDraw,
-    transform_components: &<TransformComponent as Component>::Storage
+    global_transform_components: &<GlobalTransformComponent as Component>::Storage
 ) {
     for (entity, _) in editor_selected_components.iter(&entity_set) {
-        if let Some(transform) = transform_components.get(&entity) {
+        if let Some(transform) = global_transform_components.get(&entity) {
             let position = transform.position();
 
             let x_color = glm::vec4(0.0, 1.0, 0.0, 1.0);
@@ -383,7 +401,6 @@ fn handle_scale_gizmo_input(
                     #[cfg(feature = "dim3")]
                     let adjusted = glm::vec2_to_vec3(&adjusted);
                     *transform_component.scale_mut() += adjusted;
-                    transform_component.requires_sync_to_physics();
                 }
             }
         }
@@ -395,10 +412,10 @@ fn draw_scale_gizmo(
     editor_selected_components: &mut <EditorSelectedComponent as Component>::Storage,
     debug_draw: &mut DebugDraw,
     editor_draw: &mut EditorDraw,
-    transform_components: &<TransformComponent as Component>::Storage
+    global_transform_components: &<GlobalTransformComponent as Component>::Storage
 ) {
     for (entity, _) in editor_selected_components.iter(&entity_set) {
-        if let Some(transform) = transform_components.get(&entity) {
+        if let Some(transform) = global_transform_components.get(&entity) {
             let position = transform.position();
 
             //TODO: Make this resolution independent. Need a UI multiplier?
@@ -546,7 +563,6 @@ fn handle_rotate_gizmo_input(
                         *transform_component.rotation_mut() *= rotation;
                     }
 
-                    transform_component.requires_sync_to_physics();
                 }
             }
         }
@@ -558,10 +574,10 @@ fn draw_rotate_gizmo(
     editor_selected_components: &mut <EditorSelectedComponent as Component>::Storage,
     debug_draw: &mut DebugDraw,
     editor_draw: &mut EditorDraw,
-    transform_components: &<TransformComponent as Component>::Storage
+    global_transform_components: &<GlobalTransformComponent as Component>::Storage
 ) {
     for (entity, _) in editor_selected_components.iter(&entity_set) {
-        if let Some(pos) = transform_components.get(&entity) {
+        if let Some(pos) = global_transform_components.get(&entity) {
             let position = pos.position();
 
             //TODO: Make this resolution independent. Need a UI multiplier?
@@ -584,6 +600,7 @@ fn draw_rotate_gizmo(
 }
 
 fn handle_select_input(
+    entity_set: &EntitySet,
     input_state: &InputState,
     camera_state: &CameraState,
     editor_collision_world: &EditorCollisionWorld,
@@ -662,7 +679,7 @@ fn handle_select_input(
 
         // default selecting behavior is to drop the old selection
         if !add_to_selection && !subtract_from_selection {
-            editor_selected_components.free_all();
+            editor_selected_components.free_all(entity_set);
         }
 
         for entity in entities {
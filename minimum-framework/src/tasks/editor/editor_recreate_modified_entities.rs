@@ -4,7 +4,9 @@ use crate::base::task::TaskConfig;
 use crate::base::ResourceMap;
 use crate::base::TaskContextFlags;
 
+use crate::base::EntityHandle;
 use crate::components::editor::EditorSelectedComponent;
+use hashbrown::HashMap;
 
 pub struct EditorRecreateModifiedEntities;
 pub type EditorRecreateModifiedEntitiesTask = crate::base::WriteAllTask<EditorRecreateModifiedEntities>;
@@ -20,12 +22,15 @@ impl WriteAllTaskImpl for EditorRecreateModifiedEntities {
 
         let mut entity_set = resource_map.fetch_mut::<crate::base::EntitySet>();
 
-        // Find all the modified persistent entities. Return a tuple of (prototypes, is_selected), and mark them for deletion
+        // Find all the modified persistent entities. Return a tuple of (prototypes, is_selected, entity_handle, parent, children),
+        // and mark them for deletion. Their children are detached first, otherwise they would be deleted too.
         // (the scoping here is intentional, we want to avoid having any active fetch when we call flush_free)
         let prototypes = {
             let persistent_entity_components = resource_map.fetch::<<crate::components::PersistentEntityComponent as Component>::Storage>();
             let editor_modified_components = resource_map.fetch::<<crate::components::editor::EditorModifiedComponent as Component>::Storage>();
             let editor_selected_components = resource_map.fetch::<<crate::components::editor::EditorSelectedComponent as Component>::Storage>();
+            let mut parent_components = resource_map.fetch_mut::<<crate::components::ParentComponent as Component>::Storage>();
+            let mut entity_hierarchy = resource_map.fetch_mut::<crate::resources::EntityHierarchy>();
             let mut pending_delete_components =
                 resource_map.fetch_mut::<<crate::base::PendingDeleteComponent as Component>::Storage>();
 
@@ -39,8 +44,14 @@ impl WriteAllTaskImpl for EditorRecreateModifiedEntities {
                 {
                     let prototype = persistent_entity_component.entity_prototype().clone();
                     let selected = editor_selected_components.exists(&entity_handle);
+                    let parent = parent_components.get(&entity_handle).map(|p| p.parent().clone());
+                    let children = entity_hierarchy.children(&entity_handle).to_vec();
 
-                    prototypes.push((prototype, selected));
+                    for child in &children {
+                        entity_hierarchy.set_parent(&mut *parent_components, child, None).unwrap();
+                    }
+
+                    prototypes.push((prototype, selected, entity_handle.clone(), parent, children));
                     entity_set.enqueue_free(&entity_handle, &mut *pending_delete_components);
                 }
             }
@@ -61,11 +72,16 @@ impl WriteAllTaskImpl for EditorRecreateModifiedEntities {
         // Recreate the entities (the scoping here is intentional, we want to avoid having any active fetch when we call flush_creates)
         {
             let mut editor_selected_components = resource_map.fetch_mut::<<crate::components::editor::EditorSelectedComponent as Component>::Storage>();
-            for (prototype, is_selected) in prototypes {
+            let mut recreated_entities = HashMap::new();
+            let mut links = vec![];
+            for (prototype, is_selected, old_entity_handle, parent, children) in prototypes {
                 use crate::base::EntityPrototype;
                 let entity = entity_set.allocate_get();
                 prototype.create(resource_map, &entity);
 
+                recreated_entities.insert(old_entity_handle, entity.handle());
+                links.push((entity.handle(), parent, children));
+
                 // If the entity was selected before it was deleted, re-select it
                 if is_selected {
                     editor_selected_components
@@ -73,6 +89,26 @@ impl WriteAllTaskImpl for EditorRecreateModifiedEntities {
                         .unwrap();
                 }
             }
+
+            // Restore the hierarchy. The parent/children may have been recreated as well
+            let resolve = |entity_handle: &EntityHandle| {
+                recreated_entities
+                    .get(entity_handle)
+                    .unwrap_or(entity_handle)
+                    .clone()
+            };
+
+            let mut parent_components = resource_map.fetch_mut::<<crate::components::ParentComponent as Component>::Storage>();
+            let mut entity_hierarchy = resource_map.fetch_mut::<crate::resources::EntityHierarchy>();
+            for (entity_handle, parent, children) in links {
+                if let Some(parent) = parent {
+                    entity_hierarchy.set_parent(&mut *parent_components, &entity_handle, Some(&resolve(&parent))).unwrap();
+                }
+
+                for child in children {
+                    entity_hierarchy.set_parent(&mut *parent_components, &resolve(&child), Some(&entity_handle)).unwrap();
+                }
+            }
         }
 
         entity_set.flush_creates(resource_map);
@@ -16,23 +16,23 @@ impl ResourceTaskImpl for EditorUpdateSelectionShapesWithPosition {
         Read<EntitySet>,
         Write<EditorCollisionWorld>,
         ReadComponent<crate::components::editor::EditorShapeComponent>,
-        ReadComponent<crate::components::TransformComponent>,
+        ReadComponent<crate::components::GlobalTransformComponent>,
     );
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<crate::base::task::PhasePreRender>();
         config.this_provides_data_to::<crate::tasks::editor::EditorUpdateSelectionWorldTask>();
-        config.run_only_if(crate::context_flags::PLAYMODE_SYSTEM);
+        config.run_only_if_flag::<crate::context_flags::PlayModeSystem>();
     }
 
     fn run(
         _context_flags: &TaskContextFlags,
         data: <Self::RequiredResources as DataRequirement>::Borrow,
     ) {
-        let (entity_set, mut collision_world, editor_shape_components, transform_components) = data;
+        let (entity_set, mut collision_world, editor_shape_components, global_transform_components) = data;
 
         for (entity, editor_shape_component) in editor_shape_components.iter(&entity_set) {
-            if let Some(transform_component) = transform_components.get(&entity) {
+            if let Some(transform_component) = global_transform_components.get(&entity) {
 
                 #[cfg(feature = "dim2")]
                 let isometry = ncollide::math::Isometry::new(transform_component.position(), transform_component.rotation());
@@ -12,7 +12,7 @@ impl ResourceTaskImpl for EditorUpdateSelectionWorld {
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<crate::base::task::PhasePreRender>();
-        config.run_only_if(crate::context_flags::PLAYMODE_SYSTEM);
+        config.run_only_if_flag::<crate::context_flags::PlayModeSystem>();
     }
 
     fn run(
@@ -17,7 +17,7 @@ impl ResourceTaskImpl for HandleFreeAtTimeComponents {
 
     fn configure(config: &mut TaskConfig) {
         config.this_runs_during_phase::<base::task::PhaseFrameBegin>();
-        config.run_only_if(crate::context_flags::PLAYMODE_PLAYING);
+        config.run_only_if_flag::<crate::context_flags::PlayModePlaying>();
     }
 
     fn run(
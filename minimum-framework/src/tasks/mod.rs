@@ -10,6 +10,9 @@ pub use clear_debug_draw::ClearDebugDrawTask;
 mod update_entity_set;
 pub use update_entity_set::UpdateEntitySetTask;
 
+mod update_global_transforms;
+pub use update_global_transforms::UpdateGlobalTransformsTask;
+
 mod debug_draw_components;
 pub use debug_draw_components::DebugDrawComponentsTask;
 
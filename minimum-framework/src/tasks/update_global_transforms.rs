@@ -0,0 +1,304 @@
+use base::resource::{DataRequirement, Read};
+use base::{
+    ComponentStorage, EntityHandle, ReadComponent, ResourceTaskImpl, TaskConfig, TaskContextFlags,
+    WriteComponent,
+};
+
+use crate::components::global_transform::parent_global_transform;
+use crate::components::{GlobalTransformComponent, ParentComponent, TransformComponent};
+use crate::resources::EntityHierarchy;
+
+pub struct UpdateGlobalTransforms;
+pub type UpdateGlobalTransformsTask = base::ResourceTask<UpdateGlobalTransforms>;
+impl ResourceTaskImpl for UpdateGlobalTransforms {
+    type RequiredResources = (
+        Read<base::EntitySet>,
+        Read<EntityHierarchy>,
+        ReadComponent<TransformComponent>,
+        ReadComponent<ParentComponent>,
+        WriteComponent<GlobalTransformComponent>,
+    );
+
+    fn configure(config: &mut TaskConfig) {
+        // After physics has moved things, before anything is drawn
+        config.this_runs_during_phase::<base::task::PhasePostPhysicsGameplay>();
+    }
+
+    fn run(
+        _context_flags: &TaskContextFlags,
+        data: <Self::RequiredResources as DataRequirement>::Borrow,
+    ) {
+        let (
+            entity_set,
+            entity_hierarchy,
+            transform_components,
+            parent_components,
+            mut global_transform_components,
+        ) = data;
+
+        // Drop the global transforms of entities that no longer have a transform
+        let stale: Vec<_> = global_transform_components
+            .iter(&entity_set)
+            .filter(|(entity_handle, _)| !transform_components.exists(entity_handle))
+            .map(|(entity_handle, _)| entity_handle)
+            .collect();
+
+        for entity_handle in stale {
+            global_transform_components.free(&entity_handle);
+        }
+
+        // Walk down from each root. Parents are visited before their children, so a child's parent
+        // is always up to date.
+        for entity in entity_set.iter() {
+            let root = entity.handle();
+            if parent_components.exists(&root) {
+                continue;
+            }
+
+            update_global_transform(
+                &root,
+                &transform_components,
+                &parent_components,
+                &mut global_transform_components,
+            );
+
+            for (descendant, _depth) in entity_hierarchy.descendants(&root) {
+                update_global_transform(
+                    &descendant,
+                    &transform_components,
+                    &parent_components,
+                    &mut global_transform_components,
+                );
+            }
+        }
+    }
+}
+
+fn update_global_transform(
+    entity_handle: &EntityHandle,
+    transform_components: &<TransformComponent as base::Component>::Storage,
+    parent_components: &<ParentComponent as base::Component>::Storage,
+    global_transform_components: &mut <GlobalTransformComponent as base::Component>::Storage,
+) {
+    let transform = match transform_components.get(entity_handle) {
+        Some(transform) => transform,
+        None => return,
+    };
+
+    let global_transform = match parent_global_transform(
+        entity_handle,
+        parent_components,
+        global_transform_components,
+    ) {
+        Some(parent_global_transform) => parent_global_transform.transform().compose(transform),
+        None => transform.clone(),
+    };
+
+    // Only write when something changed, so that iter_changed() finds what actually moved
+    let unchanged = global_transform_components
+        .get(entity_handle)
+        .map(|existing| transforms_approx_eq(existing.transform(), &global_transform));
+
+    match unchanged {
+        Some(true) => {}
+        Some(false) => {
+            *global_transform_components.get_mut(entity_handle).unwrap() =
+                GlobalTransformComponent::new(global_transform);
+        }
+        None => {
+            global_transform_components
+                .allocate(
+                    entity_handle,
+                    GlobalTransformComponent::new(global_transform),
+                )
+                .unwrap();
+        }
+    }
+}
+
+// Positions copied out of physics go world -> local -> world, which doesn't round trip exactly
+const EPSILON: f32 = 0.0001;
+
+fn positions_approx_eq(
+    a: crate::components::transform::Position,
+    b: crate::components::transform::Position,
+) -> bool {
+    (a - b).norm() < EPSILON
+}
+
+// Rotations are compared by how they transform a vector, like assert_transforms_eq
+fn transforms_approx_eq(a: &TransformComponent, b: &TransformComponent) -> bool {
+    let x_axis = crate::components::transform::Position::x();
+    positions_approx_eq(a.position(), b.position())
+        && positions_approx_eq(a.scale(), b.scale())
+        && positions_approx_eq(a.to_local_vector(x_axis), b.to_local_vector(x_axis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::TransformComponentPrototype;
+    use crate::test_entities::*;
+    use crate::{CloneComponentFactory, TestHarness};
+    use base::component::ChangeTick;
+    use base::{BasicEntityPrototype, Component, EntitySet};
+
+    fn create_harness() -> TestHarness {
+        let world = build_world()
+            .with_component(<TransformComponent as Component>::Storage::new())
+            .with_component(<GlobalTransformComponent as Component>::Storage::new())
+            .with_component_factory(CloneComponentFactory::<TransformComponent>::new())
+            .with_task::<UpdateGlobalTransformsTask>();
+
+        TestHarness::new(world, FRAME_TIME)
+    }
+
+    fn create_with_transform(
+        harness: &mut TestHarness,
+        transform: TransformComponent,
+        parent: Option<&EntityHandle>,
+    ) -> EntityHandle {
+        let entity_handle = create_entity(
+            harness,
+            BasicEntityPrototype::new(vec![Box::new(TransformComponentPrototype::new(transform))]),
+        );
+        set_parent(harness, &entity_handle, parent);
+        entity_handle
+    }
+
+    fn global_transform(harness: &TestHarness, entity_handle: &EntityHandle) -> TransformComponent {
+        harness
+            .read_component(
+                entity_handle,
+                |global_transform: &GlobalTransformComponent| global_transform.transform().clone(),
+            )
+            .unwrap()
+    }
+
+    fn change_tick(harness: &TestHarness) -> ChangeTick {
+        harness.read_resource(
+            |storage: &<GlobalTransformComponent as Component>::Storage| storage.change_tick(),
+        )
+    }
+
+    fn changed_since(harness: &TestHarness, since: ChangeTick) -> Vec<EntityHandle> {
+        let resource_map = harness.resource_map();
+        let entity_set = resource_map.fetch::<EntitySet>();
+        let global_transform_components =
+            resource_map.fetch::<<GlobalTransformComponent as Component>::Storage>();
+        global_transform_components
+            .iter_changed(&entity_set, since)
+            .map(|(entity_handle, _)| entity_handle)
+            .collect()
+    }
+
+    // Returns root, child, grandchild
+    fn create_hierarchy(harness: &mut TestHarness) -> Vec<EntityHandle> {
+        let root = create_with_transform(harness, transform(10.0, 0.0, 2.0, 90.0), None);
+        let child = create_with_transform(harness, transform(1.0, 0.0, 1.0, 0.0), Some(&root));
+        let grandchild =
+            create_with_transform(harness, transform(0.0, 1.0, 0.5, 90.0), Some(&child));
+        vec![root, child, grandchild]
+    }
+
+    #[test]
+    fn test_three_levels() {
+        let mut harness = create_harness();
+        let e = create_hierarchy(&mut harness);
+        harness.step();
+
+        assert_transforms_eq(
+            &global_transform(&harness, &e[0]),
+            &transform(10.0, 0.0, 2.0, 90.0),
+        );
+        assert_transforms_eq(
+            &global_transform(&harness, &e[1]),
+            &transform(10.0, 2.0, 2.0, 90.0),
+        );
+        assert_transforms_eq(
+            &global_transform(&harness, &e[2]),
+            &transform(8.0, 2.0, 1.0, 180.0),
+        );
+
+        // Moving the root moves its descendants
+        harness.write_component(&e[0], |transform: &mut TransformComponent| {
+            *transform.position_mut() = position(0.0, 0.0);
+        });
+        harness.step();
+
+        assert_transforms_eq(
+            &global_transform(&harness, &e[2]),
+            &transform(-2.0, 2.0, 1.0, 180.0),
+        );
+    }
+
+    #[test]
+    fn test_unchanged_frame_is_not_changed() {
+        let mut harness = create_harness();
+        let e = create_hierarchy(&mut harness);
+        harness.step();
+
+        let tick = change_tick(&harness);
+        harness.step();
+        assert!(changed_since(&harness, tick).is_empty());
+
+        // Only the moved entity and its descendants change
+        let tick = change_tick(&harness);
+        harness.write_component(&e[1], |transform: &mut TransformComponent| {
+            *transform.position_mut() = position(2.0, 0.0);
+        });
+        harness.step();
+
+        let mut changed = changed_since(&harness, tick);
+        changed.sort_by_key(|entity_handle| entity_handle.index());
+        assert_eq!(changed, vec![e[1].clone(), e[2].clone()]);
+    }
+
+    #[test]
+    fn test_moved_by_physics_sync_is_not_changed() {
+        let mut harness = create_harness();
+        let root = create_with_transform(&mut harness, transform(-3.1, 4.7, 1.7, 37.0), None);
+        let child = create_with_transform(&mut harness, transform(1.0, 0.0, 1.0, 0.0), Some(&root));
+        harness.step();
+
+        // Like PhysicsSyncPost: the body's world position is written to the transform relative to
+        // the parent and to the global transform, and the global transforms' tick is saved so that
+        // the copy isn't pushed back to physics
+        let world_position = position(-10.0, -3.6);
+        let parent_transform = global_transform(&harness, &root);
+        harness.write_component(&child, |transform: &mut TransformComponent| {
+            *transform.position_mut() = parent_transform.to_local_position(world_position);
+        });
+        harness.write_component(&child, |global_transform: &mut GlobalTransformComponent| {
+            *global_transform.transform_mut().position_mut() = world_position;
+        });
+        let tick = change_tick(&harness);
+        harness.step();
+
+        assert_positions_eq(
+            global_transform(&harness, &child).position(),
+            world_position,
+        );
+        assert!(changed_since(&harness, tick).is_empty());
+    }
+
+    #[test]
+    fn test_removed_transform_removes_global_transform() {
+        let mut harness = create_harness();
+        let e = create_hierarchy(&mut harness);
+        harness.step();
+
+        {
+            let resource_map = harness.resource_map();
+            resource_map
+                .fetch::<EntitySet>()
+                .remove_component::<TransformComponent>(&resource_map, &e[2]);
+        }
+        harness.step();
+
+        assert!(harness
+            .entities_with_component::<GlobalTransformComponent>()
+            .iter()
+            .all(|entity_handle| *entity_handle != e[2]));
+    }
+}
@@ -1,9 +1,9 @@
-use crate::base::resource::{DataRequirement, Read, Write};
+use crate::base::resource::{DataRequirement, Read, Write, WriteOption};
 use crate::base::{ResourceTaskImpl, TaskConfig, TaskContextFlags};
 
 use crate::resources::InputState;
 use crate::resources::FrameworkOptions;
-use crate::resources::{FrameworkActionQueue, TimeState};
+use crate::resources::{FixedTimestep, FrameworkActionQueue, TimeState};
 
 pub struct UpdateTimeState;
 pub type UpdateTimeStateTask = crate::base::ResourceTask<UpdateTimeState>;
@@ -12,7 +12,8 @@ impl ResourceTaskImpl for UpdateTimeState {
         Write<TimeState>,
         Read<InputState>,
         Write<FrameworkActionQueue>,
-        Read<FrameworkOptions>
+        Read<FrameworkOptions>,
+        WriteOption<FixedTimestep>
     );
 
     fn configure(config: &mut TaskConfig) {
@@ -24,11 +25,11 @@ impl ResourceTaskImpl for UpdateTimeState {
         data: <Self::RequiredResources as DataRequirement>::Borrow,
     ) {
         use crate::PlayMode;
-        let (mut time_state, input_state, mut game_control, framework_options) = data;
+        let (mut time_state, input_state, mut game_control, framework_options, fixed_timestep) = data;
 
-        let play_mode = if context_flags.flags() & crate::context_flags::PLAYMODE_PLAYING != 0 {
+        let play_mode = if context_flags.is_set::<crate::context_flags::PlayModePlaying>() {
             PlayMode::Playing
-        } else if context_flags.flags() & crate::context_flags::PLAYMODE_PAUSED != 0 {
+        } else if context_flags.is_set::<crate::context_flags::PlayModePaused>() {
             PlayMode::Paused
         } else {
             PlayMode::System
@@ -36,6 +37,11 @@ impl ResourceTaskImpl for UpdateTimeState {
 
         time_state.update(play_mode);
 
+        // Fixed timestep phases (i.e. physics) only advance while playing
+        if let Some(mut fixed_timestep) = fixed_timestep {
+            fixed_timestep.add_time(time_state.playing().previous_frame_time);
+        }
+
         if input_state.is_key_just_down(framework_options.keybinds.edit_play_toggle) {
             let new_play_mode = match play_mode {
                 PlayMode::System => PlayMode::Playing,
@@ -0,0 +1,205 @@
+//! A world with the entity hierarchy and persistent entities set up, and helpers for creating and
+//! inspecting entities in it. Shared by the unit tests, tests that need more components add them to
+//! the world builder.
+
+use base::{BasicEntityPrototype, Component, EntityHandle, EntitySet, PendingDeleteComponent};
+use base::{EntityPrototype, WorldBuilder};
+
+use crate::components::transform::Position;
+use crate::components::{
+    ParentComponent, ParentComponentFactory, ParentComponentFreeHandler, ParentComponentPrototype,
+    PersistentEntityComponent, PersistentEntityComponentFreeHandler, TransformComponent,
+};
+use crate::persist::PersistentEntityId;
+use crate::resources::{EntityHierarchy, PersistentEntityLookup};
+use crate::tasks::UpdateEntitySetTask;
+use crate::{FrameworkEntityPersistencePolicy, FrameworkEntityPrototype, TestHarness};
+
+use std::time::Duration;
+
+pub(crate) const FRAME_TIME: Duration = Duration::from_millis(10);
+
+/// Adds the default phases, the hierarchy and persistent entity resources/components, and the
+/// task that updates the entity set
+pub(crate) fn build_world() -> WorldBuilder {
+    WorldBuilder::new()
+        .with_default_phases()
+        .with_resource(EntityHierarchy::new())
+        .with_resource(PersistentEntityLookup::new())
+        .with_component_and_free_handler::<_, _, ParentComponentFreeHandler>(
+            <ParentComponent as Component>::Storage::new(),
+        )
+        .with_component_and_free_handler::<_, _, PersistentEntityComponentFreeHandler>(
+            <PersistentEntityComponent as Component>::Storage::new(),
+        )
+        .with_component_factory(ParentComponentFactory::new())
+        .with_task::<UpdateEntitySetTask>()
+}
+
+/// Create the queued entities and return the ones that didn't exist before
+pub(crate) fn flush_created_entities(harness: &mut TestHarness) -> Vec<EntityHandle> {
+    let existing = harness.entities();
+    harness.flush_entity_set();
+    harness
+        .entities()
+        .into_iter()
+        .filter(|entity_handle| !existing.contains(entity_handle))
+        .collect()
+}
+
+/// Create an entity from the prototype immediately
+pub(crate) fn create_entity<P: EntityPrototype + 'static>(
+    harness: &mut TestHarness,
+    prototype: P,
+) -> EntityHandle {
+    harness.enqueue_create_entity(prototype);
+    let created = flush_created_entities(harness);
+    assert_eq!(created.len(), 1);
+    created[0].clone()
+}
+
+/// Create an entity immediately, as a child of `parent` if there is one
+pub(crate) fn create_child(
+    harness: &mut TestHarness,
+    parent: Option<&EntityHandle>,
+) -> EntityHandle {
+    let components: Vec<Box<dyn base::ComponentPrototypeDyn>> = match parent {
+        Some(parent) => vec![Box::new(ParentComponentPrototype::new(parent.clone()))],
+        None => vec![],
+    };
+
+    create_entity(harness, BasicEntityPrototype::new(components))
+}
+
+/// A prototype for a persistent entity without any components
+pub(crate) fn persistent_prototype(id: PersistentEntityId) -> FrameworkEntityPrototype {
+    FrameworkEntityPrototype::new(
+        std::path::PathBuf::from("test"),
+        FrameworkEntityPersistencePolicy::Persistent(id),
+        vec![],
+    )
+}
+
+/// Create a persistent entity with a new ID immediately, as a child of `parent` if there is one
+pub(crate) fn create_persistent(
+    harness: &mut TestHarness,
+    parent: Option<&EntityHandle>,
+) -> EntityHandle {
+    let entity_handle = create_entity(harness, persistent_prototype(PersistentEntityId::new()));
+    set_parent(harness, &entity_handle, parent);
+    entity_handle
+}
+
+pub(crate) fn set_parent(
+    harness: &mut TestHarness,
+    entity_handle: &EntityHandle,
+    parent: Option<&EntityHandle>,
+) {
+    let resource_map = harness.resource_map();
+    let mut parent_components = resource_map.fetch_mut::<<ParentComponent as Component>::Storage>();
+    resource_map
+        .fetch_mut::<EntityHierarchy>()
+        .set_parent(&mut *parent_components, entity_handle, parent)
+        .unwrap();
+}
+
+/// Flag the entity to be freed during the next entity set update
+pub(crate) fn enqueue_free(harness: &mut TestHarness, entity_handle: &EntityHandle) {
+    let resource_map = harness.resource_map();
+    let mut delete_components =
+        resource_map.fetch_mut::<<PendingDeleteComponent as Component>::Storage>();
+    resource_map
+        .fetch::<EntitySet>()
+        .enqueue_free(entity_handle, &mut *delete_components);
+}
+
+pub(crate) fn parent_of(
+    harness: &TestHarness,
+    entity_handle: &EntityHandle,
+) -> Option<EntityHandle> {
+    harness.read_component(entity_handle, |parent_component: &ParentComponent| {
+        parent_component.parent().clone()
+    })
+}
+
+pub(crate) fn children_of(
+    harness: &TestHarness,
+    entity_handle: &EntityHandle,
+) -> Vec<EntityHandle> {
+    harness.read_resource(|entity_hierarchy: &EntityHierarchy| {
+        entity_hierarchy.children(entity_handle).to_vec()
+    })
+}
+
+/// Panics if the entity isn't persistent
+pub(crate) fn persistent_id(
+    harness: &TestHarness,
+    entity_handle: &EntityHandle,
+) -> PersistentEntityId {
+    harness
+        .read_component(
+            entity_handle,
+            |persistent_entity: &PersistentEntityComponent| *persistent_entity.id(),
+        )
+        .unwrap()
+}
+
+/// The entity that currently has the ID, if there is one
+pub(crate) fn find_persistent(
+    harness: &TestHarness,
+    id: &PersistentEntityId,
+) -> Option<EntityHandle> {
+    harness.read_resource(|persistent_entity_lookup: &PersistentEntityLookup| {
+        persistent_entity_lookup.get(id).cloned()
+    })
+}
+
+/// A transform in the XY plane with a uniform scale, rotated counterclockwise around Z
+#[cfg(feature = "dim2")]
+pub(crate) fn transform(x: f32, y: f32, scale: f32, degrees: f32) -> TransformComponent {
+    TransformComponent::new(
+        position(x, y),
+        glm::vec2(scale, scale),
+        degrees.to_radians(),
+    )
+}
+
+/// A transform in the XY plane with a uniform scale, rotated counterclockwise around Z
+#[cfg(feature = "dim3")]
+pub(crate) fn transform(x: f32, y: f32, scale: f32, degrees: f32) -> TransformComponent {
+    TransformComponent::new(
+        position(x, y),
+        glm::vec3(scale, scale, scale),
+        glm::quat_angle_axis(degrees.to_radians(), &glm::vec3(0.0, 0.0, 1.0)),
+    )
+}
+
+#[cfg(feature = "dim2")]
+pub(crate) fn position(x: f32, y: f32) -> Position {
+    glm::vec2(x, y)
+}
+
+#[cfg(feature = "dim3")]
+pub(crate) fn position(x: f32, y: f32) -> Position {
+    glm::vec3(x, y, 0.0)
+}
+
+pub(crate) fn assert_positions_eq(actual: Position, expected: Position) {
+    assert!(
+        (actual - expected).norm() < 0.0001,
+        "{:?} != {:?}",
+        actual,
+        expected
+    );
+}
+
+/// Rotations are compared by how they transform a vector, so that equivalent angles/quaternions
+/// are equal
+pub(crate) fn assert_transforms_eq(actual: &TransformComponent, expected: &TransformComponent) {
+    assert_positions_eq(actual.position(), expected.position());
+    assert_positions_eq(actual.scale(), expected.scale());
+    assert_positions_eq(
+        actual.to_local_vector(position(1.0, 0.0)),
+        expected.to_local_vector(position(1.0, 0.0)),
+    );
+}
@@ -0,0 +1,381 @@
+use base::component::{Component, ComponentStorage};
+use base::resource::Resource;
+use base::util::TrustCellRef;
+use base::{
+    DispatchControl, EntityHandle, EntityPrototype, EntitySet, ResourceMap,
+    UpdateLoopSingleThreaded, WorldBuilder,
+};
+
+use crate::resources::{FixedStepClock, FixedTimestep, TimeState};
+use crate::PlayMode;
+
+use std::time::Duration;
+
+/// Steps a world one frame at a time with a fixed frame time, so that tasks can be covered by
+/// plain `cargo test` without a window. Resources, entities and components can be inspected or
+/// changed between frames.
+///
+/// The harness updates `TimeState` (and `FixedTimestep`, if it was added) at the start of each
+/// frame, so `UpdateTimeStateTask` should not be added to the world.
+pub struct TestHarness {
+    update_loop: UpdateLoopSingleThreaded,
+    play_mode: PlayMode,
+}
+
+impl TestHarness {
+    /// Builds the world. The framework's context flags are added, and the world starts in
+    /// `PlayMode::Playing` as both server and client (like a single player game).
+    pub fn new(mut world_builder: WorldBuilder, frame_time: Duration) -> Self {
+        crate::context_flags::register_context_flags(&mut world_builder);
+        world_builder.add_resource(TimeState::with_clock(FixedStepClock::new(frame_time)));
+
+        let update_loop = world_builder.build_update_loop_single_threaded(0);
+
+        {
+            let resource_map = update_loop.resource_map().borrow();
+            let mut dispatch_control = resource_map.fetch_mut::<DispatchControl>();
+            dispatch_control.set_context_flag::<crate::context_flags::AuthorityServer>();
+            dispatch_control.set_context_flag::<crate::context_flags::AuthorityClient>();
+            crate::context_flags::set_play_mode(&mut dispatch_control, PlayMode::Playing);
+        }
+
+        TestHarness {
+            update_loop,
+            play_mode: PlayMode::Playing,
+        }
+    }
+
+    /// Advance time by the frame time and run all tasks once
+    pub fn step(&mut self) {
+        {
+            let resource_map = self.update_loop.resource_map().borrow();
+            let mut time_state = resource_map.fetch_mut::<TimeState>();
+            time_state.update(self.play_mode);
+
+            // Fixed timestep phases (i.e. physics) only advance while playing. The trailing ; makes
+            // the fetch a temporary of this statement, so it is dropped before resource_map is
+            if let Some(mut fixed_timestep) = resource_map.try_fetch_mut::<FixedTimestep>() {
+                fixed_timestep.add_time(time_state.playing().previous_frame_time);
+            };
+        }
+
+        self.update_loop.step();
+    }
+
+    pub fn step_frames(&mut self, frame_count: usize) {
+        for _ in 0..frame_count {
+            self.step();
+        }
+    }
+
+    /// Panics if `TimeState`'s clock was replaced with one that isn't a `FixedStepClock`
+    pub fn frame_time(&self) -> Duration {
+        self.resource_map()
+            .fetch::<TimeState>()
+            .clock::<FixedStepClock>()
+            .unwrap()
+            .step()
+    }
+
+    /// Change how much time passes in each following frame
+    pub fn set_frame_time(&mut self, frame_time: Duration) {
+        self.resource_map()
+            .fetch_mut::<TimeState>()
+            .set_clock(FixedStepClock::new(frame_time));
+    }
+
+    pub fn play_mode(&self) -> PlayMode {
+        self.play_mode
+    }
+
+    /// Change the play mode (and the context flags for it), starting next frame
+    pub fn set_play_mode(&mut self, play_mode: PlayMode) {
+        self.play_mode = play_mode;
+        let resource_map = self.resource_map();
+        let mut dispatch_control = resource_map.fetch_mut::<DispatchControl>();
+        crate::context_flags::set_play_mode(&mut dispatch_control, play_mode);
+    }
+
+    /// Borrow the resource map, i.e. `harness.resource_map().fetch::<TimeState>()`. The borrow must
+    /// be released before the next step.
+    pub fn resource_map(&self) -> TrustCellRef<ResourceMap> {
+        self.update_loop.resource_map().borrow()
+    }
+
+    /// Read a resource. Panics if the resource doesn't exist.
+    pub fn read_resource<R: Resource, T, F: FnOnce(&R) -> T>(&self, f: F) -> T {
+        f(&*self.resource_map().fetch::<R>())
+    }
+
+    /// Change a resource. Panics if the resource doesn't exist.
+    pub fn write_resource<R: Resource, T, F: FnOnce(&mut R) -> T>(&mut self, f: F) -> T {
+        f(&mut *self.resource_map().fetch_mut::<R>())
+    }
+
+    /// Queue an entity to be created. It exists once `flush_entity_set` is called or the world's
+    /// `UpdateEntitySetTask` runs.
+    pub fn enqueue_create_entity<P: EntityPrototype + 'static>(&mut self, prototype: P) {
+        self.resource_map()
+            .fetch_mut::<base::EntityFactory>()
+            .enqueue_create(Box::new(prototype));
+    }
+
+    /// Create and free any entities that were queued, like `UpdateEntitySetTask` does at the end of
+    /// each frame
+    pub fn flush_entity_set(&mut self) {
+        let resource_map = self.resource_map();
+        resource_map.fetch_mut::<EntitySet>().update(&resource_map);
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.resource_map().fetch::<EntitySet>().entity_count()
+    }
+
+    /// Handles for all entities that currently exist
+    pub fn entities(&self) -> Vec<EntityHandle> {
+        self.resource_map()
+            .fetch::<EntitySet>()
+            .iter()
+            .map(|entity| entity.handle())
+            .collect()
+    }
+
+    /// Handles for all entities that currently have a component of type C
+    pub fn entities_with_component<C: Component>(&self) -> Vec<EntityHandle> {
+        let resource_map = self.resource_map();
+        let entity_set = resource_map.fetch::<EntitySet>();
+        let storage = resource_map.fetch::<C::Storage>();
+        entity_set
+            .iter()
+            .map(|entity| entity.handle())
+            .filter(|entity_handle| storage.exists(entity_handle))
+            .collect()
+    }
+
+    /// Read an entity's component, or return None if it doesn't have one
+    pub fn read_component<C: Component, T, F: FnOnce(&C) -> T>(
+        &self,
+        entity_handle: &EntityHandle,
+        f: F,
+    ) -> Option<T> {
+        self.resource_map()
+            .fetch::<C::Storage>()
+            .get(entity_handle)
+            .map(f)
+    }
+
+    /// Change an entity's component, or return None if it doesn't have one
+    pub fn write_component<C: Component, T, F: FnOnce(&mut C) -> T>(
+        &mut self,
+        entity_handle: &EntityHandle,
+        f: F,
+    ) -> Option<T> {
+        self.resource_map()
+            .fetch_mut::<C::Storage>()
+            .get_mut(entity_handle)
+            .map(f)
+    }
+
+    /// Finish testing and take the resources, i.e. to make final assertions
+    pub fn into_resource_map(self) -> ResourceMap {
+        self.update_loop.into_resource_map()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::FreeAtTimeComponent;
+    use crate::context_flags::{PlayModePaused, PlayModePlaying};
+    use crate::resources::PlayingModeTime;
+    use crate::tasks::{HandleFreeAtTimeComponentsTask, UpdateEntitySetTask};
+    use crate::{CloneComponentFactory, CloneComponentPrototype};
+    use base::resource::WriteBorrow;
+    use base::task::*;
+    use base::BasicEntityPrototype;
+
+    struct PlayingFrameCount(u32);
+    struct PausedFrameCount(u32);
+    struct PhysicsStepCount(u32);
+
+    fn count_playing_frames(mut count: WriteBorrow<PlayingFrameCount>) {
+        count.0 += 1;
+    }
+
+    fn count_paused_frames(mut count: WriteBorrow<PausedFrameCount>) {
+        count.0 += 1;
+    }
+
+    fn count_physics_steps(mut count: WriteBorrow<PhysicsStepCount>) {
+        count.0 += 1;
+    }
+
+    fn build_world() -> WorldBuilder {
+        WorldBuilder::new()
+            .with_resource(PlayingFrameCount(0))
+            .with_resource(PausedFrameCount(0))
+            .with_resource(PhysicsStepCount(0))
+            .with_component(<FreeAtTimeComponent as Component>::Storage::new())
+            .with_component_factory(CloneComponentFactory::<FreeAtTimeComponent>::new())
+            .with_phase::<PhaseFrameBegin>()
+            .with_phase::<PhaseGatherInput>()
+            .with_phase::<PhasePrePhysicsGameplay>()
+            .with_fixed_timestep_phase::<PhasePhysics>()
+            .with_phase::<PhasePostPhysicsGameplay>()
+            .with_phase::<PhasePreRender>()
+            .with_phase::<PhaseRender>()
+            .with_phase::<PhasePostRender>()
+            .with_phase::<PhaseEndFrame>()
+            .with_task::<HandleFreeAtTimeComponentsTask>()
+            .with_task::<UpdateEntitySetTask>()
+            .with_fn_task(count_playing_frames, |config| {
+                config.this_runs_during_phase::<PhaseFrameBegin>();
+                config.run_only_if_flag::<PlayModePlaying>();
+            })
+            .with_fn_task(count_paused_frames, |config| {
+                config.this_runs_during_phase::<PhaseFrameBegin>();
+                config.run_only_if_flag::<PlayModePaused>();
+            })
+            .with_fn_task(count_physics_steps, |config| {
+                config.this_runs_during_phase::<PhasePhysics>();
+            })
+    }
+
+    // Creates an entity that is freed once `free_time_ms` of playing time has passed
+    fn create_free_at_time_entity(harness: &mut TestHarness, free_time_ms: u64) {
+        let free_time = PlayingModeTime::from_duration(Duration::from_millis(free_time_ms));
+        harness.enqueue_create_entity(BasicEntityPrototype::new(vec![Box::new(
+            CloneComponentPrototype::new(FreeAtTimeComponent::new(free_time)),
+        )]));
+        harness.flush_entity_set();
+    }
+
+    #[test]
+    fn test_frame_time() {
+        let mut harness = TestHarness::new(build_world(), Duration::from_millis(10));
+        harness.step_frames(3);
+
+        harness.read_resource(|time_state: &TimeState| {
+            assert_eq!(time_state.system().frame_count, 3);
+            assert_eq!(
+                time_state.playing().previous_frame_time,
+                Duration::from_millis(10)
+            );
+            assert_eq!(
+                time_state.playing().total_time.as_duration(),
+                Duration::from_millis(30)
+            );
+        });
+
+        harness.set_frame_time(Duration::from_millis(5));
+        assert_eq!(harness.frame_time(), Duration::from_millis(5));
+        harness.step();
+
+        harness.read_resource(|time_state: &TimeState| {
+            assert_eq!(
+                time_state.playing().previous_frame_time,
+                Duration::from_millis(5)
+            );
+            assert_eq!(
+                time_state.playing().total_time.as_duration(),
+                Duration::from_millis(35)
+            );
+        });
+    }
+
+    #[test]
+    fn test_set_play_mode() {
+        let mut harness = TestHarness::new(build_world(), Duration::from_millis(10));
+        assert_eq!(harness.play_mode(), PlayMode::Playing);
+        harness.step_frames(2);
+
+        harness.set_play_mode(PlayMode::Paused);
+        assert_eq!(harness.play_mode(), PlayMode::Paused);
+        harness.step_frames(3);
+
+        // Paused tasks also run while playing, but playing tasks and time stop while paused
+        assert_eq!(harness.read_resource(|c: &PlayingFrameCount| c.0), 2);
+        assert_eq!(harness.read_resource(|c: &PausedFrameCount| c.0), 5);
+        harness.read_resource(|time_state: &TimeState| {
+            assert_eq!(time_state.play_mode, PlayMode::Paused);
+            assert_eq!(
+                time_state.playing().total_time.as_duration(),
+                Duration::from_millis(20)
+            );
+            assert_eq!(
+                time_state.paused().total_time.as_duration(),
+                Duration::from_millis(50)
+            );
+        });
+
+        harness.set_play_mode(PlayMode::System);
+        harness.step();
+        assert_eq!(harness.read_resource(|c: &PausedFrameCount| c.0), 5);
+    }
+
+    #[test]
+    fn test_fixed_timestep() {
+        let world_builder =
+            build_world().with_resource(FixedTimestep::new(Duration::from_millis(10), 4));
+        let mut harness = TestHarness::new(world_builder, Duration::from_millis(25));
+
+        // 25ms is 2 steps with 5ms left over, then 30ms is 3 steps with nothing left over
+        harness.step_frames(2);
+        assert_eq!(harness.read_resource(|c: &PhysicsStepCount| c.0), 5);
+        assert_eq!(harness.read_resource(|f: &FixedTimestep| f.substeps()), 3);
+
+        // No time is added while paused
+        harness.set_play_mode(PlayMode::Paused);
+        harness.step_frames(2);
+        assert_eq!(harness.read_resource(|c: &PhysicsStepCount| c.0), 5);
+        assert_eq!(harness.read_resource(|f: &FixedTimestep| f.substeps()), 0);
+    }
+
+    #[test]
+    fn test_flush_entity_set() {
+        let mut harness = TestHarness::new(build_world(), Duration::from_millis(10));
+        harness.enqueue_create_entity(BasicEntityPrototype::new(vec![]));
+        assert_eq!(harness.entity_count(), 0);
+
+        harness.flush_entity_set();
+        assert_eq!(harness.entity_count(), 1);
+        assert!(harness
+            .entities_with_component::<FreeAtTimeComponent>()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_free_at_time_while_playing() {
+        let mut harness = TestHarness::new(build_world(), Duration::from_millis(10));
+        create_free_at_time_entity(&mut harness, 50);
+        assert_eq!(
+            harness
+                .entities_with_component::<FreeAtTimeComponent>()
+                .len(),
+            1
+        );
+
+        harness.step_frames(4);
+        assert_eq!(harness.entity_count(), 1);
+
+        harness.step();
+        assert_eq!(harness.entity_count(), 0);
+    }
+
+    #[test]
+    fn test_free_at_time_not_while_paused() {
+        let mut harness = TestHarness::new(build_world(), Duration::from_millis(10));
+        create_free_at_time_entity(&mut harness, 50);
+
+        harness.set_play_mode(PlayMode::Paused);
+        harness.step_frames(10);
+        assert_eq!(harness.entity_count(), 1);
+
+        harness.set_play_mode(PlayMode::Playing);
+        harness.step_frames(4);
+        assert_eq!(harness.entity_count(), 1);
+
+        harness.step();
+        assert_eq!(harness.entity_count(), 0);
+    }
+}